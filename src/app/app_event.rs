@@ -2,7 +2,6 @@ use crate::{
     color,
     geometry::text::{Text, TextAlignment},
     node_class::{MeshRenderer, Viewport},
-    node_component::render_component::RenderComponent,
 };
 
 use super::app_prelude::*;
@@ -64,30 +63,14 @@ pub fn init_render(
     // Create an input layout from the vertex layout
     let input_layout = graphics_cache.create_input_layout_from_vertex_layout(None, &vertex_layout);
 
-    // Create a glyph map
-    let glyphs = Text::build_glyph_map(
-        // TODO: Expand this list if decide to keep this code
-        [
-            " !\"#$%&'()*+,-.",
-            "/0123456789:;<=",
-            ">?@ABCDEFGHIJKL",
-            "MNOPQRSTUVWXYZ[",
-            "\\]^_`abcdefghij",
-            "klmnopqrstuvwxy",
-            "z{|}~",
-        ],
-        Vector::zero(),
-        vector!(20, 20),
-        vector!(1, 1),
-    );
-
-    // Load a font texture
+    // Load a font texture, with its glyphs from "assets/ascii.atlas.ron"
     let font_texture = graphics_cache.create_texture_from_file(
         None,
         TextureType::Color,
         "assets/ascii.png",
         None,
-        Some(glyphs),
+        None,
+        TextureOptions::default(),
     )?;
 
     // Create a text object that can be converted to a mesh
@@ -112,6 +95,21 @@ pub fn init_render(
         Text::fragment_shader,
     )?;
 
+    // Create a material that blends the transparent parts of the glyphs
+    let material = graphics_cache.create_material(
+        None,
+        Material::new(program)
+            .with_pipeline_state(PipelineState::transparent())
+            // Pass the font texture to the parameters
+            .with_parameter(
+                "font_texture",
+                graphics_cache
+                    .get_texture(font_texture)
+                    .unwrap()
+                    .full_view(),
+            ),
+    )?;
+
     // Create a viewport node with a default camera
     let viewport_node = universe.create_node(None, Viewport::new_default());
 
@@ -122,16 +120,8 @@ pub fn init_render(
             Orientation::new_orthographic(Vector::zero(), 0.0),
             mesh,
             input_layout,
-            program,
-            RenderParameters::new()
-                // Pass the font texture to the parameters
-                .with(
-                    "font_texture",
-                    graphics_cache
-                        .get_texture(font_texture)
-                        .unwrap()
-                        .full_view(),
-                ),
+            material,
+            RenderParameters::new(),
         ),
     );
 
@@ -142,7 +132,7 @@ pub fn init_render(
 
 // Called when the engine renders a frame
 pub fn render(
-    _engine: &mut Engine,
+    engine: &mut Engine,
     universe: &mut Universe,
     _async_data: AppData<AsyncData>,
     graphics_cache: &mut GfxCache,
@@ -164,40 +154,25 @@ pub fn render(
     // Clear the framebuffer depth
     framebuffer.clear_depth();
 
+    // Get the timing information for this frame
+    let frame_info = engine.frame_info();
+
     // Find all viewport nodes in the universe
     let viewport_nodes = universe.nodes().with_class::<Viewport>();
 
     // For each viewport, render its contents with the appropriate viewport settings
     // and camera
     for node in viewport_nodes {
-        // Get the viewport information
+        // Render the viewport's children with its camera and viewport settings
         let viewport = node.class_as::<Viewport>().unwrap();
-        let camera = viewport.camera();
-
-        // Set the viewport in the state
-        unsafe {
-            framebuffer.__set_viewport(viewport.center(), viewport.size(), framebuffer_size);
-        }
-
-        // Find all children of the viewport node that have a RenderComponent
-        let children = universe
-            .nodes_with_handles(node.children())
-            .flatten()
-            .with_component::<RenderComponent>();
-
-        // For each child node, render it
-        for (child, render_component) in children {
-            // Render the child node using its render component
-            render_component.render(
-                &child,
-                &framebuffer,
-                framebuffer_size,
-                camera,
-                true,
-                graphics_cache,
-                Some(universe),
-            );
-        }
+        viewport.render(
+            &node,
+            &framebuffer,
+            framebuffer_size,
+            &frame_info,
+            graphics_cache,
+            universe,
+        );
     }
     Ok(())
 }
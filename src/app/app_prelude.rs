@@ -11,16 +11,36 @@ pub use crate::{
     geometry::orientation::*,
     geometry::shape::*,
     gfx::{
+        buffer::BufferUsage,
+        cubemap::{Cubemap, CubemapFace, CubemapLayout, CubemapView},
+        framebuffer::{Framebuffer, FramebufferLayout},
         gfx_cache::GfxCache,
+        indirect_batch::{DrawData, DrawElementsIndirectCommand, IndirectBatch},
+        material::{Material, ResolvedMaterial},
+        mesh::{IndexFormat, IndexType, MeshIndices, MeshOptions, MeshRange, PrimitiveTopology},
+        mesh_pool::MeshPool,
+        pipeline_state::{
+            BlendEquation, BlendFactor, BlendState, ColorWriteMask, CompareFunction, CullMode,
+            DepthState, PipelineState, PolygonMode, ScissorRect, StencilOperation, StencilState,
+        },
+        readback::PixelReadback,
         render_camera::RenderCamera,
         render_parameters::RenderParameters,
+        render_queue::{RenderQueue, SortKey},
+        ring_buffer::RingBuffer,
+        sampler::{FilterMode, MipmapMode, Mipmaps, SamplerSettings, TextureOptions, WrapMode},
         shader_gen::prelude::*,
         target_buffer::TargetBuffer,
         texture::{Texture, TextureRegion, TextureType, TextureView},
-        vertex_layout::VertexInput,
+        texture_array::{Texture3D, Texture3DView, TextureArray, TextureArrayView},
+        texture_atlas::{AtlasBuilder, AtlasPage},
+        texture_format::TextureFormat,
+        texture_metadata::TextureMetadata,
+        vertex_layout::{VertexAttribute, VertexFormat, VertexInput},
+        vertex_list::{VertexList, VertexListInput},
         Gfx,
     },
     node_class,
     universe_ref::*,
-    window::WindowEvents,
+    window::{ContextBackend, HeadlessContext, WindowEvents},
 };
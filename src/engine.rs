@@ -12,6 +12,20 @@ pub struct Engine {
     /// The time between the previous iteration and the current iteration.
     /// Measured in seconds.
     delta_time: Option<f32>,
+    /// The number of iterations started so far, minus one.
+    frame_index: u64,
+}
+
+/// Timing information about the current frame.
+/// Passed to the renderer to fill in the built-in time parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameInfo {
+    /// The time since the engine started, in seconds.
+    pub elapsed_time: f32,
+    /// The time between the previous iteration and the current iteration, in seconds.
+    pub delta_time: f32,
+    /// The index of the current frame, starting at 0.
+    pub frame_index: u64,
 }
 
 impl Engine {
@@ -22,6 +36,7 @@ impl Engine {
             start_time: None,
             last_iteration_time: None,
             delta_time: None,
+            frame_index: 0,
         }
     }
 
@@ -41,8 +56,11 @@ impl Engine {
         let now = Instant::now();
 
         // Set the start time if it hasn't been set yet.
+        // Otherwise, advance the frame index.
         if self.start_time.is_none() {
             self.start_time = Some(now);
+        } else {
+            self.frame_index += 1;
         }
 
         // Update delta time and last iteration time.
@@ -63,4 +81,27 @@ impl Engine {
     pub fn delta_time(&self) -> f32 {
         self.delta_time.unwrap_or(0.0)
     }
+
+    /// Get the time since the engine started.
+    /// Measured in seconds. Returns 0.0 before the first iteration.
+    pub fn elapsed_time(&self) -> f32 {
+        match (self.start_time, self.last_iteration_time) {
+            (Some(start), Some(last)) => (last - start).as_secs_f32(),
+            _ => 0.0,
+        }
+    }
+
+    /// Get the index of the current frame (iteration), starting at 0.
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Get the timing information for the current frame.
+    pub fn frame_info(&self) -> FrameInfo {
+        FrameInfo {
+            elapsed_time: self.elapsed_time(),
+            delta_time: self.delta_time(),
+            frame_index: self.frame_index(),
+        }
+    }
 }
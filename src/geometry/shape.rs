@@ -8,7 +8,7 @@ use crate::{
     gfx::{
         gfx_cache::GfxCache,
         texture::TextureView,
-        vertex_layout::VertexLayout,
+        vertex_layout::{VertexComponent, VertexInput, VertexLayout},
         vertex_list::{VertexList, VertexListInput},
     },
 };
@@ -27,6 +27,8 @@ pub struct ShapeTriangles {
     tex_coords: Vec<Vector2<f32>>,
     /// The indices of the vertices that make up the triangles.
     indices: Vec<u32>,
+    /// Additional inputs of the vertices, such as tangents or custom inputs, as flat components.
+    attributes: Vec<(VertexInput, Vec<VertexComponent>)>,
 }
 
 impl ShapeTriangles {
@@ -47,6 +49,7 @@ impl ShapeTriangles {
             colors,
             tex_coords,
             indices,
+            attributes: Vec::new(),
         }
     }
 
@@ -93,12 +96,37 @@ impl ShapeTriangles {
             colors: Vec::new(),
             tex_coords: Vec::new(),
             indices: Vec::new(),
+            attributes: Vec::new(),
         }
     }
 
     /// Appends the triangles from another shape to this shape.
+    /// Additional inputs that only one of the shapes has are filled with zeros for the other.
     pub fn append(&mut self, other: &mut ShapeTriangles) {
         let offset = self.positions.len() as u32;
+
+        // Add the inputs this shape is missing, then append (or zero fill) each input.
+        for (input, _) in &other.attributes {
+            if self.attribute(input.name()).is_none() {
+                let data = vec![0.0; self.positions.len() * input.component_count()];
+                self.attributes.push((input.clone(), data));
+            }
+        }
+        for (input, data) in &mut self.attributes {
+            match other
+                .attributes
+                .iter_mut()
+                .find(|(other_input, _)| other_input.name() == input.name())
+            {
+                Some((_, other_data)) => data.append(other_data),
+                None => data.resize(
+                    data.len() + other.positions.len() * input.component_count(),
+                    0.0,
+                ),
+            }
+        }
+        other.attributes.clear();
+
         self.positions.append(&mut other.positions);
         self.normals.append(&mut other.normals);
         self.colors.append(&mut other.colors);
@@ -132,21 +160,166 @@ impl ShapeTriangles {
         self.indices.len() / 3
     }
 
+    /// Returns the components of an additional input by name, such as `"Tangent"`.
+    pub fn attribute(&self, name: impl AsRef<str>) -> Option<&[VertexComponent]> {
+        self.attributes
+            .iter()
+            .find(|(input, _)| input.name() == name.as_ref())
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Sets an additional input, given as `component_count` components per vertex.
+    /// Replaces any existing input with the same name.
+    pub fn set_attribute(&mut self, input: VertexInput, data: Vec<VertexComponent>) -> Result<()> {
+        input.validate()?;
+        if data.len() != self.positions.len() * input.component_count() {
+            anyhow::bail!(
+                "Input {} must have {} components per vertex.",
+                input.name(),
+                input.component_count()
+            );
+        }
+
+        self.attributes
+            .retain(|(existing, _)| existing.name() != input.name());
+        self.attributes.push((input, data));
+        Ok(())
+    }
+
+    /// Sets an additional input and returns the shape, for chaining.
+    pub fn with_attribute(
+        mut self,
+        input: VertexInput,
+        data: Vec<VertexComponent>,
+    ) -> Result<Self> {
+        self.set_attribute(input, data)?;
+        Ok(self)
+    }
+
+    /// Computes the tangents and bitangents of the vertices from their positions, normals and
+    /// texture coordinates, for normal mapping.
+    /// Tangents are orthogonalized against the normals, and the bitangents keep the handedness
+    /// of the texture coordinates.
+    pub fn compute_tangents(&mut self) {
+        let vertex_count = self.positions.len();
+        let mut tangents = vec![[0.0f32; 3]; vertex_count];
+        let mut bitangents = vec![[0.0f32; 3]; vertex_count];
+
+        // Accumulate the tangent and bitangent of each triangle on its vertices.
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let p = [a, b, c].map(|i| to_array3(self.positions[i]));
+            let uv = [a, b, c].map(|i| [self.tex_coords[i].x(), self.tex_coords[i].y()]);
+
+            let edge1 = sub3(p[1], p[0]);
+            let edge2 = sub3(p[2], p[0]);
+            let (du1, dv1) = (uv[1][0] - uv[0][0], uv[1][1] - uv[0][1]);
+            let (du2, dv2) = (uv[2][0] - uv[0][0], uv[2][1] - uv[0][1]);
+
+            // Skip triangles with degenerate texture coordinates.
+            let determinant = du1 * dv2 - du2 * dv1;
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+            let r = 1.0 / determinant;
+            let tangent = scale3(sub3(scale3(edge1, dv2), scale3(edge2, dv1)), r);
+            let bitangent = scale3(sub3(scale3(edge2, du1), scale3(edge1, du2)), r);
+
+            for i in [a, b, c] {
+                tangents[i] = add3(tangents[i], tangent);
+                bitangents[i] = add3(bitangents[i], bitangent);
+            }
+        }
+
+        // Orthogonalize each tangent against its normal and derive the bitangent.
+        let mut tangent_data = Vec::with_capacity(vertex_count * 3);
+        let mut bitangent_data = Vec::with_capacity(vertex_count * 3);
+        for i in 0..vertex_count {
+            let normal = to_array3(self.normals[i]);
+            let mut tangent = sub3(tangents[i], scale3(normal, dot3(normal, tangents[i])));
+            if dot3(tangent, tangent) <= f32::EPSILON {
+                // Pick any direction perpendicular to the normal.
+                let axis = if normal[0].abs() < 0.9 {
+                    [1.0, 0.0, 0.0]
+                } else {
+                    [0.0, 1.0, 0.0]
+                };
+                tangent = sub3(axis, scale3(normal, dot3(normal, axis)));
+            }
+            let tangent = scale3(tangent, 1.0 / dot3(tangent, tangent).sqrt());
+
+            let mut bitangent = cross3(normal, tangent);
+            if dot3(bitangent, bitangents[i]) < 0.0 {
+                bitangent = scale3(bitangent, -1.0);
+            }
+
+            tangent_data.extend(tangent);
+            bitangent_data.extend(bitangent);
+        }
+
+        self.attributes.retain(|(input, _)| {
+            *input != VertexInput::Tangent && *input != VertexInput::Bitangent
+        });
+        self.attributes.push((VertexInput::Tangent, tangent_data));
+        self.attributes
+            .push((VertexInput::Bitangent, bitangent_data));
+    }
+
     /// Convert this `ShapeTriangles` into a `VertexList` using the given layout.
-    pub(crate) fn into_vertex_list(self, layout: Rc<VertexLayout>) -> Result<VertexList> {
-        VertexList::new(
-            layout,
-            &[
-                VertexListInput::Position(&self.positions),
-                VertexListInput::Normal(&self.normals),
-                VertexListInput::Color(&self.colors),
-                VertexListInput::TexCoord(&self.tex_coords),
-            ],
-            self.indices,
-        )
+    /// Tangents and bitangents are computed if the layout needs them and they were not set.
+    pub(crate) fn into_vertex_list(mut self, layout: Rc<VertexLayout>) -> Result<VertexList> {
+        let needs_tangents = layout.inputs().any(|input| {
+            matches!(input, VertexInput::Tangent | VertexInput::Bitangent)
+                && self.attribute(input.name()).is_none()
+        });
+        if needs_tangents {
+            self.compute_tangents();
+        }
+
+        let mut inputs = vec![
+            VertexListInput::Position(&self.positions),
+            VertexListInput::Normal(&self.normals),
+            VertexListInput::Color(&self.colors),
+            VertexListInput::TexCoord(&self.tex_coords),
+        ];
+        inputs.extend(
+            self.attributes
+                .iter()
+                .map(|(input, data)| VertexListInput::Components(input.clone(), data)),
+        );
+
+        VertexList::new(layout, &inputs, self.indices)
     }
 }
 
+fn to_array3(v: Vector3<f32>) -> [f32; 3] {
+    [v.x(), v.y(), v.z()]
+}
+
+fn add3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale3(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
 /// A trait for shapes that can be converted to a list of triangle vertices.
 pub trait ShapeToTriangles {
     /// Converts the shape to a list of triangle vertices.
@@ -309,3 +482,114 @@ impl ShapeToTriangles for Rectangle {
         unsafe { ShapeTriangles::new_unchecked(positions, normals, colors, tex_coords, indices) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit quad in the XY plane with the given normal and texture coordinates.
+    fn quad(normal: Vector3<f32>, tex_coords: [Vector2<f32>; 4]) -> ShapeTriangles {
+        ShapeTriangles::new(
+            vec![
+                vector!(0.0, 0.0, 0.0),
+                vector!(1.0, 0.0, 0.0),
+                vector!(1.0, 1.0, 0.0),
+                vector!(0.0, 1.0, 0.0),
+            ],
+            vec![normal; 4],
+            vec![color::WHITE; 4],
+            tex_coords.to_vec(),
+            vec![0, 1, 2, 0, 2, 3],
+        )
+        .unwrap()
+    }
+
+    /// Assert that every vertex of an attribute is close to `expected`.
+    fn assert_each_close(data: &[VertexComponent], expected: [f32; 3]) {
+        for vertex in data.chunks_exact(3) {
+            for (actual, expected) in vertex.iter().zip(expected) {
+                assert!(
+                    (actual - expected).abs() < 1e-5,
+                    "{:?} is not close to {:?}",
+                    vertex,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compute_tangents_follows_the_texture_coordinates() {
+        let mut shape = quad(
+            vector!(0.0, 0.0, 1.0),
+            [
+                vector!(0.0, 0.0),
+                vector!(1.0, 0.0),
+                vector!(1.0, 1.0),
+                vector!(0.0, 1.0),
+            ],
+        );
+        shape.compute_tangents();
+
+        assert_each_close(shape.attribute("Tangent").unwrap(), [1.0, 0.0, 0.0]);
+        assert_each_close(shape.attribute("Bitangent").unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn compute_tangents_keeps_mirrored_handedness() {
+        // U increases along -X, so the tangent flips but the bitangent still follows V.
+        let mut shape = quad(
+            vector!(0.0, 0.0, 1.0),
+            [
+                vector!(0.0, 0.0),
+                vector!(-1.0, 0.0),
+                vector!(-1.0, 1.0),
+                vector!(0.0, 1.0),
+            ],
+        );
+        shape.compute_tangents();
+
+        assert_each_close(shape.attribute("Tangent").unwrap(), [-1.0, 0.0, 0.0]);
+        assert_each_close(shape.attribute("Bitangent").unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn compute_tangents_orthogonalizes_against_the_normal() {
+        let mut shape = quad(
+            vector!(0.6, 0.0, 0.8),
+            [
+                vector!(0.0, 0.0),
+                vector!(1.0, 0.0),
+                vector!(1.0, 1.0),
+                vector!(0.0, 1.0),
+            ],
+        );
+        shape.compute_tangents();
+
+        assert_each_close(shape.attribute("Tangent").unwrap(), [0.8, 0.0, -0.6]);
+        assert_each_close(shape.attribute("Bitangent").unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn compute_tangents_picks_a_perpendicular_tangent_without_texture_coordinates() {
+        let mut shape = quad(vector!(0.0, 0.0, 1.0), [vector!(0.0, 0.0); 4]);
+        shape.compute_tangents();
+
+        assert_each_close(shape.attribute("Tangent").unwrap(), [1.0, 0.0, 0.0]);
+        assert_each_close(shape.attribute("Bitangent").unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn compute_tangents_replaces_existing_tangents() {
+        let mut shape = quad(vector!(0.0, 0.0, 1.0), [vector!(0.0, 0.0); 4]);
+        shape.compute_tangents();
+        shape.compute_tangents();
+
+        let tangents = shape
+            .attributes
+            .iter()
+            .filter(|(input, _)| *input == VertexInput::Tangent)
+            .count();
+        assert_eq!(tangents, 1);
+    }
+}
@@ -1,11 +1,15 @@
 use std::rc::Rc;
 
-use super::vertex_layout::{VertexComponent, VertexLayout};
+use anyhow::Result;
+
+use super::{gl_state::GlState, vertex_layout::VertexLayout};
 
 /// A buffer object that can be used to store data on the GPU.
 pub struct Buffer<T> {
     handle: u32,
     length: usize,
+    capacity: usize,
+    usage: BufferUsage,
     vertex_layout: Option<Rc<VertexLayout>>,
     _phantom: std::marker::PhantomData<T>,
 }
@@ -13,14 +17,49 @@ pub struct Buffer<T> {
 impl<T> !Send for Buffer<T> {}
 impl<T> !Sync for Buffer<T> {}
 
-pub type VertexBuffer = Buffer<VertexComponent>;
+pub type VertexBuffer = Buffer<u8>;
 pub type IndexBuffer = Buffer<u32>;
 
+/// Represents how often the contents of a buffer are expected to change.
+/// This is a hint to the driver about where to place the buffer's storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BufferUsage {
+    /// Uploaded once and drawn many times.
+    #[default]
+    Static,
+    /// Updated occasionally and drawn many times.
+    Dynamic,
+    /// Rewritten every frame and drawn a few times.
+    Stream,
+}
+
+impl BufferUsage {
+    /// Get the GL usage hint.
+    const fn gl_usage(self) -> u32 {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
 impl<T> Buffer<T> {
     /// Create a new buffer with the given length (in elements, not bytes).
     /// # Safety
     /// This function is unsafe because it should only be used on the main thread.
     pub(crate) unsafe fn __from_slice(data: &[T], vertex_layout: Option<Rc<VertexLayout>>) -> Self {
+        unsafe { Self::__from_slice_with_usage(data, vertex_layout, BufferUsage::Static) }
+    }
+
+    /// Create a new buffer from a slice with the given usage.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_slice_with_usage(
+        data: &[T],
+        vertex_layout: Option<Rc<VertexLayout>>,
+        usage: BufferUsage,
+    ) -> Self {
         let mut handle = 0;
         let length = data.len();
 
@@ -34,7 +73,7 @@ impl<T> Buffer<T> {
                 gl::ARRAY_BUFFER,
                 (std::mem::size_of::<T>() * length) as isize,
                 data.as_ptr() as *const _,
-                gl::STATIC_DRAW,
+                usage.gl_usage(),
             );
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
 
@@ -51,6 +90,8 @@ impl<T> Buffer<T> {
         Self {
             handle,
             length,
+            capacity: length,
+            usage,
             vertex_layout,
             _phantom: std::marker::PhantomData,
         }
@@ -62,7 +103,18 @@ impl<T> Buffer<T> {
         self.length
     }
 
+    /// Get the number of elements the buffer's storage can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the usage hint the buffer was created with.
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
     /// Get the GL handle.
+    /// The handle may change when the buffer is resized.
     pub fn handle(&self) -> u32 {
         self.handle
     }
@@ -72,6 +124,115 @@ impl<T> Buffer<T> {
     pub fn vertex_layout(&self) -> Option<Rc<VertexLayout>> {
         self.vertex_layout.clone()
     }
+
+    /// Overwrite the elements starting at `offset` with `data`.
+    /// Returns an error if the range is outside of the buffer's length.
+    pub fn update(&mut self, offset: usize, data: &[T]) -> Result<()> {
+        if offset + data.len() > self.length {
+            anyhow::bail!(
+                "Buffer update of {} elements at offset {} is outside of the buffer length {}",
+                data.len(),
+                offset,
+                self.length
+            );
+        }
+
+        unsafe {
+            gl::NamedBufferSubData(
+                self.handle,
+                (std::mem::size_of::<T>() * offset) as isize,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const _,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Replace the entire contents of the buffer, changing its length to `data.len()`.
+    /// The storage is orphaned first, so draws still using the old contents do not stall.
+    /// The storage is reallocated if `data` does not fit in the current capacity.
+    pub fn set_data(&mut self, data: &[T]) {
+        let capacity = self.capacity.max(data.len());
+
+        unsafe {
+            // Orphan (or grow) the storage, then upload into the fresh storage.
+            gl::NamedBufferData(
+                self.handle,
+                (std::mem::size_of::<T>() * capacity) as isize,
+                std::ptr::null(),
+                self.usage.gl_usage(),
+            );
+            gl::NamedBufferSubData(
+                self.handle,
+                0,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const _,
+            );
+        }
+
+        self.length = data.len();
+        self.capacity = capacity;
+    }
+
+    /// Orphan the buffer's storage, discarding its contents.
+    /// The driver hands out fresh storage, so the next update does not wait for
+    /// draws that are still reading the old contents.
+    pub fn orphan(&mut self) {
+        unsafe {
+            gl::NamedBufferData(
+                self.handle,
+                (std::mem::size_of::<T>() * self.capacity) as isize,
+                std::ptr::null(),
+                self.usage.gl_usage(),
+            );
+        }
+    }
+
+    /// Change the length of the buffer, keeping the existing elements that still fit.
+    /// Elements added past the old length are undefined until written.
+    /// The storage is reallocated into a new GL buffer if the new length exceeds the capacity.
+    pub fn resize(&mut self, length: usize) {
+        if length > self.capacity {
+            unsafe {
+                // Create the larger buffer.
+                let mut handle = 0;
+                gl::CreateBuffers(1, &mut handle);
+                gl::NamedBufferData(
+                    handle,
+                    (std::mem::size_of::<T>() * length) as isize,
+                    std::ptr::null(),
+                    self.usage.gl_usage(),
+                );
+
+                // Copy the existing elements into it.
+                gl::CopyNamedBufferSubData(
+                    self.handle,
+                    handle,
+                    0,
+                    0,
+                    (std::mem::size_of::<T>() * self.length) as isize,
+                );
+
+                // Set the buffer's label
+                let label = format!("Buffer<{}>", std::any::type_name::<T>());
+                gl::ObjectLabel(
+                    gl::BUFFER,
+                    handle,
+                    label.len() as i32,
+                    label.as_ptr() as *const _,
+                );
+
+                // Replace the old buffer.
+                gl::DeleteBuffers(1, &self.handle);
+                GlState::__forget_buffer(self.handle);
+                self.handle = handle;
+            }
+            self.capacity = length;
+        }
+
+        self.length = length;
+    }
 }
 
 impl<T> Drop for Buffer<T> {
@@ -79,5 +240,6 @@ impl<T> Drop for Buffer<T> {
         unsafe {
             gl::DeleteBuffers(1, &self.handle);
         }
+        GlState::__forget_buffer(self.handle);
     }
 }
@@ -0,0 +1,390 @@
+use std::f32::consts::PI;
+
+use anyhow::Result;
+use ggmath::prelude::*;
+use image::GenericImageView;
+
+use super::{
+    sampler::{MipmapMode, Mipmaps, SamplerSettings, TextureOptions},
+    texture_format::TextureFormat,
+};
+
+/// Represents a face of a cube map, in GL layer order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubemapFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubemapFace {
+    /// All faces in GL layer order.
+    pub const ALL: [CubemapFace; 6] = [
+        CubemapFace::PositiveX,
+        CubemapFace::NegativeX,
+        CubemapFace::PositiveY,
+        CubemapFace::NegativeY,
+        CubemapFace::PositiveZ,
+        CubemapFace::NegativeZ,
+    ];
+
+    /// Get the direction through a point on this face.
+    /// `s` and `t` range from -1.0 to 1.0, with `t` pointing down the face image.
+    const fn direction(self, s: f32, t: f32) -> [f32; 3] {
+        match self {
+            CubemapFace::PositiveX => [1.0, -t, -s],
+            CubemapFace::NegativeX => [-1.0, -t, s],
+            CubemapFace::PositiveY => [s, 1.0, t],
+            CubemapFace::NegativeY => [s, -1.0, -t],
+            CubemapFace::PositiveZ => [s, -t, 1.0],
+            CubemapFace::NegativeZ => [-s, -t, -1.0],
+        }
+    }
+
+    /// Get the cell of this face in a horizontal cross image, which is 4 faces wide and
+    /// 3 faces tall:
+    /// ```text
+    ///     +Y
+    /// -X  +Z  +X  -Z
+    ///     -Y
+    /// ```
+    const fn cross_cell(self) -> (u32, u32) {
+        match self {
+            CubemapFace::PositiveX => (2, 1),
+            CubemapFace::NegativeX => (0, 1),
+            CubemapFace::PositiveY => (1, 0),
+            CubemapFace::NegativeY => (1, 2),
+            CubemapFace::PositiveZ => (1, 1),
+            CubemapFace::NegativeZ => (3, 1),
+        }
+    }
+}
+
+/// Represents how the faces of a cube map are arranged in a single image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubemapLayout {
+    /// The faces are arranged in a horizontal cross, 4 faces wide and 3 faces tall.
+    HorizontalCross,
+    /// The image is an equirectangular (latitude/longitude) panorama,
+    /// converted to faces of the given size.
+    Equirectangular { face_size: u32 },
+}
+
+impl CubemapLayout {
+    /// Split an image into the six faces described by this layout, in GL layer order.
+    pub fn to_faces(self, image: &image::DynamicImage) -> Result<Vec<image::DynamicImage>> {
+        match self {
+            CubemapLayout::HorizontalCross => cross_to_faces(image),
+            CubemapLayout::Equirectangular { face_size } => {
+                equirectangular_to_faces(image, face_size)
+            }
+        }
+    }
+}
+
+/// A GL cube map texture, sampled by direction.
+pub struct Cubemap {
+    handle: u32,
+    format: TextureFormat,
+    size: u32,
+    lod_count: usize,
+    sampler: SamplerSettings,
+}
+
+impl !Send for Cubemap {}
+impl !Sync for Cubemap {}
+
+impl Cubemap {
+    /// Create a new cube map from six square faces of equal size, in GL layer order.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_faces(
+        name: impl AsRef<str>,
+        faces: &[image::DynamicImage],
+        options: TextureOptions,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+
+        // Ensure that there are six square faces of equal size.
+        if faces.len() != 6 {
+            anyhow::bail!(
+                "Failed to load {}: expected 6 cube map faces, got {}",
+                name,
+                faces.len()
+            );
+        }
+        let size = faces[0].width();
+        for (face, image) in CubemapFace::ALL.iter().zip(faces) {
+            if image.dimensions() != (size, size) {
+                anyhow::bail!(
+                    "Failed to load {}: face {:?} is {}x{}, expected {}x{}",
+                    name,
+                    face,
+                    image.width(),
+                    image.height(),
+                    size,
+                    size
+                );
+            }
+        }
+
+        // Get the number of LODs.
+        let format = options.format;
+        let lod_count = match options.mipmaps {
+            Mipmaps::None => 1,
+            Mipmaps::Gpu | Mipmaps::Cpu(_) => Mipmaps::full_chain_length(vector!(size, size)),
+        };
+
+        // Convert each face (and its LODs if generated on the CPU) to the texture format.
+        let (pixel_format, pixel_type) = format.upload_format().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Failed to load {}: cannot load an image into format {:?}",
+                name,
+                format
+            )
+        })?;
+        let face_lods = faces
+            .iter()
+            .map(|face| {
+                let lods = match options.mipmaps {
+                    Mipmaps::Cpu(filter) => Mipmaps::generate_cpu(face.clone(), filter),
+                    Mipmaps::None | Mipmaps::Gpu => vec![face.clone()],
+                };
+                lods.iter()
+                    .map(|lod| Ok((lod.width(), format.image_to_bytes(lod)?)))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Create the cube map.
+        unsafe {
+            let mut handle = 0;
+            gl::CreateTextures(gl::TEXTURE_CUBE_MAP, 1, &mut handle);
+
+            // Set the cube map's label
+            gl::ObjectLabel(
+                gl::TEXTURE,
+                handle,
+                name.len() as i32,
+                name.as_ptr() as *const _,
+            );
+
+            gl::TextureStorage2D(
+                handle,
+                lod_count as i32,
+                format.internal_format(),
+                size as i32,
+                size as i32,
+            );
+
+            // Upload each face into its layer.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (layer, lods) in face_lods.iter().enumerate() {
+                for (level, (lod_size, bytes)) in lods.iter().enumerate() {
+                    gl::TextureSubImage3D(
+                        handle,
+                        level as i32,
+                        0,
+                        0,
+                        layer as i32,
+                        *lod_size as i32,
+                        *lod_size as i32,
+                        1,
+                        pixel_format,
+                        pixel_type,
+                        bytes.as_ptr() as *const _,
+                    );
+                }
+            }
+
+            // Generate the rest of the mip chain on the GPU if requested.
+            if options.mipmaps == Mipmaps::Gpu {
+                gl::GenerateTextureMipmap(handle);
+            }
+
+            let mut cubemap = Self {
+                handle,
+                format,
+                size,
+                lod_count,
+                sampler: options.sampler,
+            };
+            cubemap.set_sampler(options.sampler);
+
+            Ok(cubemap)
+        }
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Get the format the texels are stored in.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Get the width and height of each face at LOD 0, in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get the number of LODs in this cube map.
+    pub fn lod_count(&self) -> usize {
+        self.lod_count
+    }
+
+    /// Get the sampler settings used by views that do not override them.
+    pub fn sampler(&self) -> &SamplerSettings {
+        &self.sampler
+    }
+
+    /// Set the sampler settings used by views that do not override them.
+    /// If the cube map has a single LOD, the mipmap mode is ignored so it stays complete.
+    pub fn set_sampler(&mut self, sampler: SamplerSettings) {
+        self.sampler = sampler;
+
+        let mut effective = sampler;
+        if self.lod_count == 1 {
+            effective.mipmap_mode = MipmapMode::None;
+        }
+        unsafe {
+            effective.__apply_to_texture(self.handle);
+        }
+    }
+
+    /// Get a `CubemapView` for sampling this cube map.
+    pub fn view(&self) -> CubemapView {
+        CubemapView {
+            cubemap_handle: self.handle,
+            sampler: None,
+        }
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.handle);
+        }
+    }
+}
+
+/// Represents a view of a cube map, for sampling by direction.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CubemapView {
+    cubemap_handle: u32,
+    sampler: Option<SamplerSettings>,
+}
+
+impl !Send for CubemapView {}
+impl !Sync for CubemapView {}
+
+impl CubemapView {
+    /// Get the cube map handle.
+    pub fn handle(&self) -> u32 {
+        self.cubemap_handle
+    }
+
+    /// Sample this view with the given sampler settings instead of the cube map's.
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Get the sampler settings that override the cube map's, if any.
+    pub fn sampler(&self) -> Option<&SamplerSettings> {
+        self.sampler.as_ref()
+    }
+}
+
+/// Split a horizontal cross image into six faces, in GL layer order.
+fn cross_to_faces(image: &image::DynamicImage) -> Result<Vec<image::DynamicImage>> {
+    let (width, height) = image.dimensions();
+    let size = width / 4;
+    if size == 0 || width != size * 4 || height != size * 3 {
+        anyhow::bail!(
+            "Horizontal cross image is {}x{}, expected 4:3 with square faces",
+            width,
+            height
+        );
+    }
+
+    Ok(CubemapFace::ALL
+        .iter()
+        .map(|face| {
+            let (column, row) = face.cross_cell();
+            image.crop_imm(column * size, row * size, size, size)
+        })
+        .collect())
+}
+
+/// Convert an equirectangular panorama into six faces of the given size, in GL layer order.
+/// The panorama is sampled bilinearly in 32-bit float, so HDR values are preserved.
+fn equirectangular_to_faces(
+    image: &image::DynamicImage,
+    face_size: u32,
+) -> Result<Vec<image::DynamicImage>> {
+    if face_size == 0 {
+        anyhow::bail!("Cube map face size must not be zero");
+    }
+
+    let panorama = image.to_rgba32f();
+    let (width, height) = panorama.dimensions();
+
+    // Get a texel, wrapping horizontally and clamping vertically.
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        panorama.get_pixel(x, y).0
+    };
+
+    // Sample the panorama bilinearly at the given pixel coordinates.
+    let sample = |x: f32, y: f32| {
+        let x = x - 0.5;
+        let y = y - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let corners = [
+            (texel(x0, y0), (1.0 - fx) * (1.0 - fy)),
+            (texel(x0 + 1, y0), fx * (1.0 - fy)),
+            (texel(x0, y0 + 1), (1.0 - fx) * fy),
+            (texel(x0 + 1, y0 + 1), fx * fy),
+        ];
+        let mut result = [0.0; 4];
+        for (color, weight) in corners {
+            for k in 0..4 {
+                result[k] += color[k] * weight;
+            }
+        }
+        image::Rgba(result)
+    };
+
+    Ok(CubemapFace::ALL
+        .iter()
+        .map(|face| {
+            let face_image = image::Rgba32FImage::from_fn(face_size, face_size, |x, y| {
+                // Get the direction through the pixel center.
+                let s = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let t = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let [dx, dy, dz] = face.direction(s, t);
+
+                // Convert the direction to longitude and latitude.
+                let longitude = dx.atan2(-dz);
+                let latitude = (dy / (dx * dx + dy * dy + dz * dz).sqrt()).asin();
+
+                // Convert longitude and latitude to panorama pixel coordinates.
+                let u = 0.5 + longitude / (2.0 * PI);
+                let v = 0.5 - latitude / PI;
+                sample(u * width as f32, v * height as f32)
+            });
+            image::DynamicImage::ImageRgba32F(face_image)
+        })
+        .collect())
+}
@@ -0,0 +1,395 @@
+use anyhow::Result;
+use ggmath::prelude::*;
+
+use super::{
+    gl_state::GlState,
+    target_buffer::TargetBuffer,
+    texture::{Texture, TextureType},
+    texture_format::TextureFormat,
+};
+
+/// Represents the depth and/or stencil attachment of a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepthStencilAttachment {
+    /// No depth or stencil attachment.
+    None,
+    /// A 24-bit depth attachment.
+    Depth,
+    /// A 24-bit depth attachment combined with an 8-bit stencil attachment.
+    DepthStencil,
+    /// A depth texture with the given depth format, which can be sampled after rendering.
+    Texture(TextureFormat),
+}
+
+impl DepthStencilAttachment {
+    /// Get the GL internal format and attachment point of the renderbuffer.
+    /// Returns `None` if there is no renderbuffer attachment.
+    const fn to_gl_enums(self) -> Option<(u32, u32)> {
+        match self {
+            DepthStencilAttachment::None | DepthStencilAttachment::Texture(_) => None,
+            DepthStencilAttachment::Depth => Some((gl::DEPTH_COMPONENT24, gl::DEPTH_ATTACHMENT)),
+            DepthStencilAttachment::DepthStencil => {
+                Some((gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL_ATTACHMENT))
+            }
+        }
+    }
+}
+
+/// Describes the attachments of a framebuffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramebufferLayout {
+    color_attachments: Vec<(TextureType, TextureFormat)>,
+    depth_stencil: DepthStencilAttachment,
+}
+
+impl FramebufferLayout {
+    /// Create a new empty framebuffer layout.
+    /// # Safety
+    /// This function is unsafe because it creates a new framebuffer layout without validating it.
+    pub(crate) unsafe fn __new() -> Self {
+        Self {
+            color_attachments: Vec::new(),
+            depth_stencil: DepthStencilAttachment::None,
+        }
+    }
+
+    /// Push a new RGBA8 color attachment with the given texture type.
+    /// Color attachments are bound to fragment outputs in the order they are added.
+    pub fn with_color(self, texture_type: TextureType) -> Self {
+        self.with_color_format(texture_type, TextureFormat::Rgba8)
+    }
+
+    /// Push a new color attachment with the given texture type and format.
+    /// Color attachments are bound to fragment outputs in the order they are added.
+    pub fn with_color_format(mut self, texture_type: TextureType, format: TextureFormat) -> Self {
+        self.color_attachments.push((texture_type, format));
+        self
+    }
+
+    /// Add a depth attachment.
+    pub fn with_depth(mut self) -> Self {
+        self.depth_stencil = DepthStencilAttachment::Depth;
+        self
+    }
+
+    /// Add a combined depth and stencil attachment.
+    pub fn with_depth_stencil(mut self) -> Self {
+        self.depth_stencil = DepthStencilAttachment::DepthStencil;
+        self
+    }
+
+    /// Add a depth attachment as a texture with the given depth format, for sampling later.
+    pub fn with_depth_texture(mut self, format: TextureFormat) -> Self {
+        self.depth_stencil = DepthStencilAttachment::Texture(format);
+        self
+    }
+
+    /// Get the texture types and formats of the color attachments.
+    pub fn color_attachments(&self) -> &[(TextureType, TextureFormat)] {
+        &self.color_attachments
+    }
+
+    /// Get the depth and/or stencil attachment.
+    pub fn depth_stencil(&self) -> DepthStencilAttachment {
+        self.depth_stencil
+    }
+
+    /// Validate the layout for correctness.
+    pub fn validate(&self) -> Result<()> {
+        // Check that there is at least one attachment.
+        if self.color_attachments.is_empty() && self.depth_stencil == DepthStencilAttachment::None {
+            anyhow::bail!("Framebuffer layout has no attachments.");
+        }
+
+        // Check that the color attachments have valid texture types and color formats.
+        for (texture_type, format) in &self.color_attachments {
+            if *texture_type == TextureType::Invalid {
+                anyhow::bail!("Framebuffer layout has a color attachment with an invalid type.");
+            }
+            if format.is_depth() {
+                anyhow::bail!(
+                    "Framebuffer layout has a color attachment with depth format {:?}.",
+                    format
+                );
+            }
+        }
+
+        // Check that a depth texture has a depth format.
+        if let DepthStencilAttachment::Texture(format) = self.depth_stencil {
+            if !format.is_depth() {
+                anyhow::bail!(
+                    "Framebuffer layout has a depth texture with color format {:?}.",
+                    format
+                );
+            }
+        }
+
+        // Check that the number of color attachments is supported.
+        let mut max_color_attachments = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max_color_attachments);
+        }
+        if self.color_attachments.len() > max_color_attachments as usize {
+            anyhow::bail!(
+                "Framebuffer layout has {} color attachments, but only {} are supported.",
+                self.color_attachments.len(),
+                max_color_attachments
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// An offscreen framebuffer that can be rendered to.
+/// The color attachments are textures that can be sampled after rendering.
+pub struct Framebuffer {
+    handle: u32,
+    name: String,
+    size: Vector2<u32>,
+    layout: FramebufferLayout,
+    color_attachments: Vec<Texture>,
+    depth_texture: Option<Texture>,
+    depth_stencil_handle: u32,
+}
+
+impl !Send for Framebuffer {}
+impl !Sync for Framebuffer {}
+
+impl Framebuffer {
+    /// Create a new framebuffer with the given layout and size.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new(
+        name: impl AsRef<str>,
+        layout: FramebufferLayout,
+        size: Vector2<u32>,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+
+        // Validate the layout.
+        layout.validate()?;
+
+        // Create the framebuffer.
+        let mut handle = 0;
+        unsafe {
+            gl::CreateFramebuffers(1, &mut handle);
+
+            // Set the framebuffer's label
+            gl::ObjectLabel(
+                gl::FRAMEBUFFER,
+                handle,
+                name.len() as i32,
+                name.as_ptr() as *const _,
+            );
+        }
+
+        let mut framebuffer = Self {
+            handle,
+            name: name.to_string(),
+            size,
+            layout,
+            color_attachments: Vec::new(),
+            depth_texture: None,
+            depth_stencil_handle: 0,
+        };
+
+        // Create and attach the attachments.
+        unsafe {
+            framebuffer.__create_attachments(size)?;
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Create the attachments with the given size and attach them to the framebuffer.
+    /// Any existing attachments are deleted.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    unsafe fn __create_attachments(&mut self, size: Vector2<u32>) -> Result<()> {
+        // Ensure that the size is valid.
+        if size.x() == 0 || size.y() == 0 {
+            anyhow::bail!(
+                "Failed to create framebuffer {}: size ({}, {}) must not be zero",
+                self.name,
+                size.x(),
+                size.y()
+            );
+        }
+
+        // Delete the existing attachments.
+        self.color_attachments.clear();
+        self.depth_texture = None;
+        if self.depth_stencil_handle != 0 {
+            unsafe {
+                gl::DeleteRenderbuffers(1, &self.depth_stencil_handle);
+            }
+            self.depth_stencil_handle = 0;
+        }
+
+        // Create and attach the color attachments.
+        let mut draw_buffers = Vec::new();
+        for (i, (texture_type, format)) in self.layout.color_attachments.iter().enumerate() {
+            let texture = unsafe {
+                Texture::__new_empty(
+                    format!("{} color {}", self.name, i),
+                    *texture_type,
+                    *format,
+                    size,
+                )?
+            };
+            let attachment = gl::COLOR_ATTACHMENT0 + i as u32;
+            unsafe {
+                gl::NamedFramebufferTexture(self.handle, attachment, texture.handle(), 0);
+            }
+            draw_buffers.push(attachment);
+            self.color_attachments.push(texture);
+        }
+
+        // Route the fragment outputs to the color attachments.
+        unsafe {
+            if draw_buffers.is_empty() {
+                gl::NamedFramebufferDrawBuffer(self.handle, gl::NONE);
+                gl::NamedFramebufferReadBuffer(self.handle, gl::NONE);
+            } else {
+                gl::NamedFramebufferDrawBuffers(
+                    self.handle,
+                    draw_buffers.len() as i32,
+                    draw_buffers.as_ptr(),
+                );
+                gl::NamedFramebufferReadBuffer(self.handle, gl::COLOR_ATTACHMENT0);
+            }
+        }
+
+        // Create and attach the depth/stencil attachment.
+        if let Some((internal_format, attachment)) = self.layout.depth_stencil.to_gl_enums() {
+            unsafe {
+                gl::CreateRenderbuffers(1, &mut self.depth_stencil_handle);
+                gl::NamedRenderbufferStorage(
+                    self.depth_stencil_handle,
+                    internal_format,
+                    size.x() as i32,
+                    size.y() as i32,
+                );
+                gl::NamedFramebufferRenderbuffer(
+                    self.handle,
+                    attachment,
+                    gl::RENDERBUFFER,
+                    self.depth_stencil_handle,
+                );
+            }
+        }
+
+        // Create and attach the depth texture.
+        if let DepthStencilAttachment::Texture(format) = self.layout.depth_stencil {
+            let texture = unsafe {
+                Texture::__new_empty(
+                    format!("{} depth", self.name),
+                    TextureType::Color,
+                    format,
+                    size,
+                )?
+            };
+            let attachment = format
+                .depth_attachment()
+                .expect("Depth texture format was validated");
+            unsafe {
+                gl::NamedFramebufferTexture(self.handle, attachment, texture.handle(), 0);
+            }
+            self.depth_texture = Some(texture);
+        }
+
+        self.size = size;
+
+        // Ensure that the framebuffer is complete.
+        unsafe { self.__validate() }
+    }
+
+    /// Check that the framebuffer is complete.
+    /// Returns an error describing the problem if it is not.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    unsafe fn __validate(&self) -> Result<()> {
+        let status = unsafe { gl::CheckNamedFramebufferStatus(self.handle, gl::FRAMEBUFFER) };
+        let problem = match status {
+            gl::FRAMEBUFFER_COMPLETE => return Ok(()),
+            gl::FRAMEBUFFER_UNDEFINED => "undefined",
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "incomplete attachment",
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "missing attachment",
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "incomplete draw buffer",
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "incomplete read buffer",
+            gl::FRAMEBUFFER_UNSUPPORTED => "unsupported combination of formats",
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "incomplete multisample",
+            gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "incomplete layer targets",
+            _ => "unknown status",
+        };
+        anyhow::bail!(
+            "Framebuffer {} is not complete: {} (0x{:X})",
+            self.name,
+            problem,
+            status
+        )
+    }
+
+    /// Resize the framebuffer, recreating all attachments.
+    /// The contents of the attachments are lost, and `TextureView`s of the old
+    /// color attachments must be recreated.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __resize(&mut self, size: Vector2<u32>) -> Result<()> {
+        if size == self.size {
+            return Ok(());
+        }
+        unsafe { self.__create_attachments(size) }
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Get the size of the framebuffer in pixels.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Get the layout of the framebuffer.
+    pub fn layout(&self) -> &FramebufferLayout {
+        &self.layout
+    }
+
+    /// Get the color attachment at the given index.
+    /// Returns `None` if the attachment does not exist.
+    pub fn color_attachment(&self, index: usize) -> Option<&Texture> {
+        self.color_attachments.get(index)
+    }
+
+    /// Get the color attachments.
+    pub fn color_attachments(&self) -> &[Texture] {
+        &self.color_attachments
+    }
+
+    /// Get the depth texture.
+    /// Returns `None` if the depth attachment is not a texture.
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+
+    /// Get a `TargetBuffer` for rendering to this framebuffer.
+    pub fn target_buffer(&self) -> TargetBuffer {
+        TargetBuffer::__from_handle(self.handle)
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            // Delete the depth/stencil renderbuffer only if it exists.
+            if self.depth_stencil_handle != 0 {
+                gl::DeleteRenderbuffers(1, &self.depth_stencil_handle);
+            }
+            gl::DeleteFramebuffers(1, &self.handle);
+        }
+        GlState::__forget_framebuffer(self.handle);
+    }
+}
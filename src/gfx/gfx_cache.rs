@@ -1,20 +1,32 @@
 use std::{any::Any, collections::HashMap, path::Path, rc::Rc};
 
 use anyhow::Result;
+use ggmath::prelude::*;
 use ggutil::prelude::*;
 
 use crate::app::app_prelude::ShaderParameters;
 
 use super::{
-    buffer::Buffer,
+    buffer::{Buffer, BufferUsage},
+    cubemap::{Cubemap, CubemapLayout},
+    framebuffer::{Framebuffer, FramebufferLayout},
+    indirect_batch::IndirectBatch,
     input_layout::InputLayout,
-    mesh::Mesh,
+    material::{Material, ResolvedMaterial},
+    mesh::{Mesh, MeshOptions, MeshRange},
+    mesh_pool::MeshPool,
     program::Program,
+    ring_buffer::RingBuffer,
+    sampler::TextureOptions,
     shader::{Shader, ShaderStage},
     shader_gen::{shader_inputs::ShaderInputs, shader_outputs::ShaderOutputs},
     texture::{Texture, TextureGlyph, TextureRegion, TextureType},
+    texture_array::{Texture3D, TextureArray},
+    texture_atlas::AtlasBuilder,
+    texture_format::TextureFormat,
+    texture_metadata::TextureMetadata,
     vertex_layout::VertexLayout,
-    vertex_list::IntoVertexList,
+    vertex_list::{IntoVertexList, VertexList},
 };
 
 /// A handle pointing to an object in the `GfxCache`.
@@ -70,6 +82,18 @@ impl GfxCache {
             .and_then(|v| v.object.downcast_ref())
     }
 
+    /// Get a mutable reference to an object in the cache.
+    /// Returns `None` if the object does not exist.
+    pub fn get_mut<T: Any>(&mut self, name_or_handle: impl CacheRef) -> Option<&mut T> {
+        // Get the handle of the object.
+        let handle = name_or_handle.handle(self);
+
+        // Get the value from the hashmap
+        self.objects
+            .get_mut(&handle)
+            .and_then(|v| v.object.downcast_mut())
+    }
+
     /// Get an object's handle.
     pub fn handle(&self, name_or_handle: impl CacheRef) -> CacheHandle {
         name_or_handle.handle(self)
@@ -157,9 +181,54 @@ impl GfxCache {
         self.get::<Buffer<T>>(name_or_handle)
     }
 
+    /// Get a buffer from the cache mutably, for updating its contents.
+    pub fn get_buffer_mut<T: 'static>(
+        &mut self,
+        name_or_handle: impl CacheRef,
+    ) -> Option<&mut Buffer<T>> {
+        self.get_mut::<Buffer<T>>(name_or_handle)
+    }
+
+    /// Create a new persistently mapped ring buffer in the cache, for streaming data every frame.
+    /// The buffer holds `segment_count` segments of `segment_length` elements.
+    /// Returns an error if the buffer could not be created or mapped.
+    pub fn create_ring_buffer<T: Copy + 'static>(
+        &mut self,
+        name: Option<String>,
+        segment_length: usize,
+        segment_count: usize,
+    ) -> Result<CacheHandle> {
+        // Create the ring buffer.
+        let ring_buffer = unsafe { RingBuffer::<T>::__new(segment_length, segment_count)? };
+
+        // Insert the ring buffer into the cache.
+        let handle = self.insert(name, ring_buffer);
+
+        Ok(handle)
+    }
+
+    /// Get a ring buffer from the cache.
+    pub fn get_ring_buffer<T: Copy + 'static>(
+        &self,
+        name_or_handle: impl CacheRef,
+    ) -> Option<&RingBuffer<T>> {
+        self.get::<RingBuffer<T>>(name_or_handle)
+    }
+
+    /// Get a ring buffer from the cache mutably, for writing.
+    pub fn get_ring_buffer_mut<T: Copy + 'static>(
+        &mut self,
+        name_or_handle: impl CacheRef,
+    ) -> Option<&mut RingBuffer<T>> {
+        self.get_mut::<RingBuffer<T>>(name_or_handle)
+    }
+
     /// Create a new texture in the cache from the given file path.
-    /// Returns an error if the file could not be loaded.
-    // TODO: Implement LODs
+    /// HDR and EXR files can be loaded into float formats such as `TextureFormat::Rgba16F`.
+    /// The format, mip chain and sampler are set as described by `options`.
+    /// If `regions` or `glyphs` is `None`, they are loaded from the image's sidecar file
+    /// if one exists (see `TextureMetadata::find_sidecar`).
+    /// Returns an error if the file or its sidecar file could not be loaded.
     pub fn create_texture_from_file(
         &mut self,
         name: Option<String>,
@@ -167,6 +236,7 @@ impl GfxCache {
         path: impl AsRef<Path>,
         regions: Option<HashMap<String, TextureRegion>>,
         glyphs: Option<HashMap<char, TextureGlyph>>,
+        options: TextureOptions,
     ) -> Result<CacheHandle> {
         let path = path.as_ref();
 
@@ -182,21 +252,366 @@ impl GfxCache {
         let image = image::open(path)
             .map_err(|e| anyhow::anyhow!("Failed to open image file {:?}: {:?}", path, e))?;
 
+        // Fill in the regions and glyphs that were not given from the sidecar file.
+        let (regions, glyphs) = match TextureMetadata::find_sidecar(path) {
+            Some(sidecar) if regions.is_none() || glyphs.is_none() => {
+                let metadata = TextureMetadata::load(sidecar)?;
+                (
+                    regions.or_else(|| metadata.regions().cloned()),
+                    glyphs.or_else(|| metadata.glyphs().cloned()),
+                )
+            }
+            _ => (regions, glyphs),
+        };
+
+        // Create the texture from the image.
+        self.create_texture_from_image(Some(name), texture_type, image, regions, glyphs, options)
+    }
+
+    /// Create a new texture in the cache from an image in memory.
+    /// This can be used with images generated on the CPU, such as with `noise::generate_image`.
+    pub fn create_texture_from_image(
+        &mut self,
+        name: Option<String>,
+        texture_type: TextureType,
+        image: impl Into<image::DynamicImage>,
+        regions: Option<HashMap<String, TextureRegion>>,
+        glyphs: Option<HashMap<char, TextureGlyph>>,
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Create the texture.
+        let label = name.as_deref().unwrap_or("texture");
+        let texture = unsafe {
+            Texture::__from_image(
+                label,
+                texture_type,
+                &[image.into()],
+                regions,
+                glyphs,
+                options,
+            )?
+        };
+
+        // Insert the texture into the cache.
+        let handle = self.insert(name, texture);
+
+        Ok(handle)
+    }
+
+    /// Create a new empty texture in the cache with the given format and size, for render targets.
+    /// The texture has a single LOD and its contents are undefined until it is written to.
+    pub fn create_empty_texture(
+        &mut self,
+        name: Option<String>,
+        texture_type: TextureType,
+        format: TextureFormat,
+        size: Vector2<u32>,
+    ) -> Result<CacheHandle> {
         // Create the texture.
-        let texture =
-            unsafe { Texture::__from_image(&name, texture_type, &[image], regions, glyphs)? };
+        let label = name.as_deref().unwrap_or("texture");
+        let texture = unsafe { Texture::__new_empty(label, texture_type, format, size)? };
 
         // Insert the texture into the cache.
-        let handle = self.insert(Some(name), texture);
+        let handle = self.insert(name, texture);
 
         Ok(handle)
     }
 
+    /// Pack the images in `atlas` and create a texture in the cache for each page.
+    /// Each texture holds the regions of its page, so entries can be addressed with
+    /// `Texture::region_view`. If a name is given, page `i` is named `"{name}_{i}"`.
+    /// Returns the handle and regions of each page.
+    pub fn create_texture_atlas(
+        &mut self,
+        name: Option<String>,
+        texture_type: TextureType,
+        atlas: &AtlasBuilder,
+        options: TextureOptions,
+    ) -> Result<Vec<(CacheHandle, HashMap<String, TextureRegion>)>> {
+        // Pack the images into pages.
+        let pages = atlas.pack()?;
+
+        // Create a texture for each page.
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let page_name = name.as_ref().map(|name| format!("{}_{}", name, i));
+                let handle = self.create_texture_from_image(
+                    page_name,
+                    texture_type,
+                    page.image,
+                    Some(page.regions.clone()),
+                    None,
+                    options,
+                )?;
+                Ok((handle, page.regions))
+            })
+            .collect()
+    }
+
     /// Get a texture from the cache.
     pub fn get_texture(&self, name_or_handle: impl CacheRef) -> Option<&Texture> {
         self.get::<Texture>(name_or_handle)
     }
 
+    /// Get a texture from the cache mutably, for updating its contents.
+    pub fn get_texture_mut(&mut self, name_or_handle: impl CacheRef) -> Option<&mut Texture> {
+        self.get_mut::<Texture>(name_or_handle)
+    }
+
+    /// Copy a region between two different textures in the cache on the GPU.
+    /// See `Texture::copy_from`.
+    /// Returns an error if either texture does not exist or the copy is invalid.
+    pub fn copy_texture_region(
+        &mut self,
+        source: impl CacheRef,
+        source_lod: usize,
+        source_min: Vector2<u32>,
+        target: impl CacheRef,
+        lod: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+    ) -> Result<()> {
+        let source = self.handle(source);
+        let target = self.handle(target);
+        if source == target {
+            anyhow::bail!("Cannot copy a texture region into the same texture");
+        }
+
+        // Take the target out of the cache so the source can be borrowed at the same time.
+        let mut target_object = match self.objects.get_mut(&target) {
+            Some(cached) => std::mem::replace(&mut cached.object, Box::new(())),
+            None => anyhow::bail!("Target texture not found in cache"),
+        };
+
+        let result = match (
+            target_object.downcast_mut::<Texture>(),
+            self.get_texture(&source),
+        ) {
+            (Some(target), Some(source)) => {
+                target.copy_from(source, source_lod, source_min, lod, min, size)
+            }
+            (None, _) => Err(anyhow::anyhow!("Target is not a texture")),
+            (_, None) => Err(anyhow::anyhow!("Source texture not found in cache")),
+        };
+
+        // Put the target back.
+        self.objects.get_mut(&target).unwrap().object = target_object;
+
+        result
+    }
+
+    /// Create a new cube map in the cache from six image files, one per face.
+    /// The faces are given in the order +X, -X, +Y, -Y, +Z, -Z.
+    /// Returns an error if a file could not be loaded or the faces are not equally sized squares.
+    pub fn create_cubemap_from_files(
+        &mut self,
+        name: Option<String>,
+        paths: [impl AsRef<Path>; 6],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Open the image files.
+        let faces = open_images(&paths)?;
+
+        // Create the cube map.
+        let label = name.as_deref().unwrap_or("cubemap");
+        let cubemap = unsafe { Cubemap::__from_faces(label, &faces, options)? };
+
+        // Insert the cube map into the cache.
+        let handle = self.insert(name, cubemap);
+
+        Ok(handle)
+    }
+
+    /// Create a new cube map in the cache from a single image file
+    /// with the faces arranged as described by `layout`.
+    /// Returns an error if the file could not be loaded or does not match the layout.
+    pub fn create_cubemap_from_file(
+        &mut self,
+        name: Option<String>,
+        path: impl AsRef<Path>,
+        layout: CubemapLayout,
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        let path = path.as_ref();
+
+        // Get the file name from the path without the extension.
+        let name = name.unwrap_or_else(|| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "cubemap".to_string())
+        });
+
+        // Open the image file and split it into faces.
+        let image = image::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open image file {:?}: {:?}", path, e))?;
+        let faces = layout.to_faces(&image)?;
+
+        // Create the cube map.
+        let cubemap = unsafe { Cubemap::__from_faces(&name, &faces, options)? };
+
+        // Insert the cube map into the cache.
+        let handle = self.insert(Some(name), cubemap);
+
+        Ok(handle)
+    }
+
+    /// Get a `Cubemap` from the cache.
+    pub fn get_cubemap(&self, name_or_handle: impl CacheRef) -> Option<&Cubemap> {
+        self.get::<Cubemap>(name_or_handle)
+    }
+
+    /// Create a new texture array in the cache from image files, one layer per file.
+    /// Returns an error if a file could not be loaded or the images are not equally sized.
+    pub fn create_texture_array_from_files(
+        &mut self,
+        name: Option<String>,
+        paths: &[impl AsRef<Path>],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Open the image files.
+        let layers = open_images(paths)?;
+
+        // Create the texture array from the images.
+        self.create_texture_array_from_images(name, &layers, options)
+    }
+
+    /// Create a new texture array in the cache from equally sized images in memory.
+    pub fn create_texture_array_from_images(
+        &mut self,
+        name: Option<String>,
+        layers: &[image::DynamicImage],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Create the texture array.
+        let label = name.as_deref().unwrap_or("texture_array");
+        let texture_array = unsafe { TextureArray::__from_images(label, layers, options)? };
+
+        // Insert the texture array into the cache.
+        let handle = self.insert(name, texture_array);
+
+        Ok(handle)
+    }
+
+    /// Create a new texture array in the cache from regions of a single image file,
+    /// one layer per region. Use `TextureRegion::grid` for sprite sheets and tile sets.
+    /// Returns an error if the file could not be loaded or the regions are not equally sized.
+    pub fn create_texture_array_from_file(
+        &mut self,
+        name: Option<String>,
+        path: impl AsRef<Path>,
+        regions: &[TextureRegion],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        let path = path.as_ref();
+
+        // Get the file name from the path without the extension.
+        let name = name.unwrap_or_else(|| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "texture_array".to_string())
+        });
+
+        // Open the image file.
+        let image = image::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open image file {:?}: {:?}", path, e))?;
+
+        // Create the texture array from the regions.
+        let texture_array =
+            unsafe { TextureArray::__from_regions(&name, &image, regions, options)? };
+
+        // Insert the texture array into the cache.
+        let handle = self.insert(Some(name), texture_array);
+
+        Ok(handle)
+    }
+
+    /// Get a `TextureArray` from the cache.
+    pub fn get_texture_array(&self, name_or_handle: impl CacheRef) -> Option<&TextureArray> {
+        self.get::<TextureArray>(name_or_handle)
+    }
+
+    /// Create a new 3D texture in the cache from image files, one slice per file, ordered by depth.
+    /// Returns an error if a file could not be loaded or the images are not equally sized.
+    pub fn create_texture_3d_from_files(
+        &mut self,
+        name: Option<String>,
+        paths: &[impl AsRef<Path>],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Open the image files.
+        let slices = open_images(paths)?;
+
+        // Create the 3D texture from the images.
+        self.create_texture_3d_from_images(name, &slices, options)
+    }
+
+    /// Create a new 3D texture in the cache from equally sized images in memory, ordered by depth.
+    pub fn create_texture_3d_from_images(
+        &mut self,
+        name: Option<String>,
+        slices: &[image::DynamicImage],
+        options: TextureOptions,
+    ) -> Result<CacheHandle> {
+        // Create the 3D texture.
+        let label = name.as_deref().unwrap_or("texture_3d");
+        let texture = unsafe { Texture3D::__from_slices(label, slices, options)? };
+
+        // Insert the 3D texture into the cache.
+        let handle = self.insert(name, texture);
+
+        Ok(handle)
+    }
+
+    /// Get a `Texture3D` from the cache.
+    pub fn get_texture_3d(&self, name_or_handle: impl CacheRef) -> Option<&Texture3D> {
+        self.get::<Texture3D>(name_or_handle)
+    }
+
+    /// Create a new framebuffer in the cache with the given size.
+    /// The framebuffer's attachments are described using the given function.
+    /// Returns an error if the framebuffer is not complete.
+    pub fn create_framebuffer(
+        &mut self,
+        name: Option<String>,
+        size: Vector2<u32>,
+        f: impl FnOnce(FramebufferLayout) -> FramebufferLayout,
+    ) -> Result<CacheHandle> {
+        // Create the framebuffer layout.
+        let layout = f(unsafe { FramebufferLayout::__new() });
+
+        // Create the framebuffer.
+        let label = name.as_deref().unwrap_or("framebuffer");
+        let framebuffer = unsafe { Framebuffer::__new(label, layout, size)? };
+
+        // Insert the framebuffer into the cache.
+        let handle = self.insert(name, framebuffer);
+
+        Ok(handle)
+    }
+
+    /// Get a `Framebuffer` from the cache.
+    pub fn get_framebuffer(&self, name_or_handle: impl CacheRef) -> Option<&Framebuffer> {
+        self.get::<Framebuffer>(name_or_handle)
+    }
+
+    /// Resize a framebuffer in the cache, recreating its attachments.
+    /// `TextureView`s of the old color attachments must be recreated afterwards.
+    pub fn resize_framebuffer(
+        &mut self,
+        name_or_handle: impl CacheRef,
+        size: Vector2<u32>,
+    ) -> Result<()> {
+        let framebuffer = self
+            .get_mut::<Framebuffer>(name_or_handle)
+            .ok_or_else(|| anyhow::anyhow!("Framebuffer not found"))?;
+
+        unsafe { framebuffer.__resize(size) }
+    }
+
     /// Create a new mesh in the cache from the given vertex list.
     pub fn create_mesh<'a>(
         &mut self,
@@ -204,23 +619,110 @@ impl GfxCache {
         vertex_layout: impl CacheRef,
         vertex_list: impl IntoVertexList<'a>,
     ) -> CacheHandle {
+        self.create_mesh_with_usage(name, vertex_layout, vertex_list, BufferUsage::Static)
+    }
+
+    /// Create a new mesh in the cache whose buffers have the given usage.
+    /// Use `BufferUsage::Dynamic` or `BufferUsage::Stream` for meshes updated with `update_mesh`.
+    pub fn create_mesh_with_usage<'a>(
+        &mut self,
+        name: Option<String>,
+        vertex_layout: impl CacheRef,
+        vertex_list: impl IntoVertexList<'a>,
+        usage: BufferUsage,
+    ) -> CacheHandle {
+        self.create_mesh_with_options(
+            name,
+            vertex_layout,
+            vertex_list,
+            MeshOptions::new().with_usage(usage),
+        )
+        .unwrap()
+    }
+
+    /// Create a new mesh in the cache with the given topology, index format, usage and
+    /// primitive restart.
+    /// Returns an error if the vertex layout does not exist or an index does not fit in the
+    /// requested index format.
+    pub fn create_mesh_with_options<'a>(
+        &mut self,
+        name: Option<String>,
+        vertex_layout: impl CacheRef,
+        vertex_list: impl IntoVertexList<'a>,
+        options: MeshOptions,
+    ) -> Result<CacheHandle> {
         // Get the vertex layout from the cache.
-        let vertex_layout = self.get_vertex_layout(vertex_layout).unwrap();
+        let vertex_layout = self
+            .get_vertex_layout(vertex_layout)
+            .ok_or_else(|| anyhow::anyhow!("Vertex layout not found in cache"))?;
 
         // Get the vertex list.
         let vertex_list = vertex_list.into_vertex_list(self, vertex_layout.clone());
 
-        // Create the vertex buffer.
-        let vertex_buffer =
-            unsafe { Buffer::__from_slice(vertex_list.vertex_data(), Some(vertex_layout.clone())) };
+        // Create the mesh with a vertex buffer for each stream and its index buffer.
+        let mesh =
+            unsafe { Mesh::__from_vertex_list(&vertex_list, vertex_layout.clone(), options)? };
 
-        // Create the index buffer.
-        let index_buffer = unsafe { Buffer::__from_slice(vertex_list.indices(), None) };
+        // Insert the mesh into the cache.
+        let handle = self.insert(name, mesh);
+
+        Ok(handle)
+    }
 
-        // Create the mesh into the cache.
-        let handle = self.insert(name, Mesh::new(vertex_buffer, index_buffer));
+    /// Rewrite the vertices and indices of a mesh in place, keeping its `CacheHandle`.
+    /// The vertex list is built with the mesh's vertex layout.
+    /// Returns an error if the mesh does not exist, or the vertex list has indices and the
+    /// mesh is not indexed (or the other way around).
+    pub fn update_mesh<'a>(
+        &mut self,
+        mesh: impl CacheRef,
+        vertex_list: impl IntoVertexList<'a>,
+    ) -> Result<()> {
+        let handle = self.handle(mesh);
 
-        handle
+        // Get the mesh's vertex layout.
+        let vertex_layout = self
+            .get_mesh(&handle)
+            .ok_or_else(|| anyhow::anyhow!("Mesh not found in cache"))?
+            .vertex_buffer()
+            .vertex_layout()
+            .ok_or_else(|| anyhow::anyhow!("Mesh has no vertex layout"))?;
+
+        // Build the vertex list, then write it into the mesh's buffers.
+        let vertex_list = vertex_list.into_vertex_list(self, vertex_layout);
+        let vertex_list: &VertexList = &vertex_list;
+        self.get_mut::<Mesh>(&handle)
+            .ok_or_else(|| anyhow::anyhow!("Mesh not found in cache"))?
+            .set_vertex_list(vertex_list)
+    }
+
+    /// Rewrite the vertices of a single stream of a mesh in place, keeping its `CacheHandle`.
+    /// This updates dynamic inputs without re-uploading the streams that hold static ones.
+    /// The vertex list is built with the mesh's vertex layout, and its other streams and
+    /// indices are ignored.
+    /// Returns an error if the mesh or stream does not exist.
+    pub fn update_mesh_stream<'a>(
+        &mut self,
+        mesh: impl CacheRef,
+        stream: usize,
+        vertex_list: impl IntoVertexList<'a>,
+    ) -> Result<()> {
+        let handle = self.handle(mesh);
+
+        // Get the mesh's vertex layout.
+        let vertex_layout = self
+            .get_mesh(&handle)
+            .ok_or_else(|| anyhow::anyhow!("Mesh not found in cache"))?
+            .vertex_buffer()
+            .vertex_layout()
+            .ok_or_else(|| anyhow::anyhow!("Mesh has no vertex layout"))?;
+
+        // Build the vertex list, then write the stream into the mesh's buffer.
+        let vertex_list = vertex_list.into_vertex_list(self, vertex_layout);
+        let vertex_list: &VertexList = &vertex_list;
+        self.get_mut::<Mesh>(&handle)
+            .ok_or_else(|| anyhow::anyhow!("Mesh not found in cache"))?
+            .set_vertex_stream(stream, vertex_list)
     }
 
     /// Get a `Mesh` from the cache.
@@ -228,6 +730,82 @@ impl GfxCache {
         self.get::<Mesh>(name_or_handle)
     }
 
+    /// Get a mesh from the cache mutably, for partial updates of its buffers.
+    pub fn get_mesh_mut(&mut self, name_or_handle: impl CacheRef) -> Option<&mut Mesh> {
+        self.get_mut::<Mesh>(name_or_handle)
+    }
+
+    /// Create a new empty mesh pool in the cache with the given topology, usage and
+    /// primitive restart. Meshes are added with `add_to_mesh_pool`.
+    pub fn create_mesh_pool(
+        &mut self,
+        name: Option<String>,
+        vertex_layout: impl CacheRef,
+        options: MeshOptions,
+    ) -> CacheHandle {
+        // Get the vertex layout from the cache.
+        let vertex_layout = self.get_vertex_layout(vertex_layout).unwrap().clone();
+
+        // Create the mesh pool.
+        let pool = unsafe { MeshPool::__new(vertex_layout, options) };
+
+        // Insert the mesh pool into the cache.
+        self.insert(name, pool)
+    }
+
+    /// Append a mesh to a mesh pool.
+    /// The vertex list is built with the pool's vertex layout.
+    /// Returns the range that draws the added mesh.
+    /// Returns an error if the mesh pool does not exist or the vertex list is not indexed.
+    pub fn add_to_mesh_pool<'a>(
+        &mut self,
+        pool: impl CacheRef,
+        vertex_list: impl IntoVertexList<'a>,
+    ) -> Result<MeshRange> {
+        let handle = self.handle(pool);
+
+        // Get the pool's vertex layout.
+        let vertex_layout = self
+            .get_mesh_pool(&handle)
+            .ok_or_else(|| anyhow::anyhow!("Mesh pool not found in cache"))?
+            .vertex_layout()
+            .clone();
+
+        // Build the vertex list, then append it to the pool's buffers.
+        let vertex_list = vertex_list.into_vertex_list(self, vertex_layout);
+        let vertex_list: &VertexList = &vertex_list;
+        self.get_mut::<MeshPool>(&handle).unwrap().add(vertex_list)
+    }
+
+    /// Get a `MeshPool` from the cache.
+    pub fn get_mesh_pool(&self, name_or_handle: impl CacheRef) -> Option<&MeshPool> {
+        self.get::<MeshPool>(name_or_handle)
+    }
+
+    /// Get a mutable `MeshPool` from the cache.
+    pub fn get_mesh_pool_mut(&mut self, name_or_handle: impl CacheRef) -> Option<&mut MeshPool> {
+        self.get_mut::<MeshPool>(name_or_handle)
+    }
+
+    /// Create a new empty indirect batch in the cache.
+    pub fn create_indirect_batch(&mut self, name: Option<String>) -> CacheHandle {
+        let batch = unsafe { IndirectBatch::__new() };
+        self.insert(name, batch)
+    }
+
+    /// Get an `IndirectBatch` from the cache.
+    pub fn get_indirect_batch(&self, name_or_handle: impl CacheRef) -> Option<&IndirectBatch> {
+        self.get::<IndirectBatch>(name_or_handle)
+    }
+
+    /// Get a mutable `IndirectBatch` from the cache.
+    pub fn get_indirect_batch_mut(
+        &mut self,
+        name_or_handle: impl CacheRef,
+    ) -> Option<&mut IndirectBatch> {
+        self.get_mut::<IndirectBatch>(name_or_handle)
+    }
+
     /// Create a new program in the cache using the given input layout.
     /// The program's vertex and fragment shaders are generated using the callbacks.
     pub fn create_program_vertex_fragment(
@@ -264,6 +842,73 @@ impl GfxCache {
         self.get::<Program>(name_or_handle)
     }
 
+    /// Create a new material in the cache.
+    /// Returns an error if the material's program or parent material does not exist.
+    pub fn create_material(
+        &mut self,
+        name: Option<String>,
+        material: Material,
+    ) -> Result<CacheHandle> {
+        if let Some(program) = material.program() {
+            if !self.contains::<Program>(program) {
+                anyhow::bail!("Material program not found in cache");
+            }
+        }
+        if let Some(parent) = material.parent() {
+            if !self.contains::<Material>(parent) {
+                anyhow::bail!("Parent material not found in cache");
+            }
+        }
+
+        // Insert the material into the cache.
+        Ok(self.insert(name, material))
+    }
+
+    /// Get a `Material` from the cache.
+    pub fn get_material(&self, name_or_handle: impl CacheRef) -> Option<&Material> {
+        self.get::<Material>(name_or_handle)
+    }
+
+    /// Get a `Material` from the cache mutably.
+    /// Changes are seen by every renderer using the material or its instances.
+    pub fn get_material_mut(&mut self, name_or_handle: impl CacheRef) -> Option<&mut Material> {
+        self.get_mut::<Material>(name_or_handle)
+    }
+
+    /// Resolve a material and its parents into the program, pipeline state and default
+    /// parameters to render with.
+    /// The program and pipeline state come from the nearest material that sets them.
+    /// Returns an error if the material, a parent material or the program does not exist.
+    pub fn resolve_material(&self, material: impl CacheRef) -> Result<ResolvedMaterial<'_>> {
+        let mut program = None;
+        let mut pipeline_state = None;
+        let mut parameters = Vec::new();
+
+        // Walk from the material up to its root parent.
+        let mut next = Some(self.handle(material));
+        while let Some(handle) = next {
+            let material = self
+                .get_material(&handle)
+                .ok_or_else(|| anyhow::anyhow!("Material not found in cache"))?;
+
+            program = program.or(material.program());
+            pipeline_state = pipeline_state.or(material.pipeline_state().copied());
+            parameters.push(material.parameters());
+            next = material.parent().cloned();
+        }
+
+        // Get the program from the cache.
+        let program = program
+            .and_then(|program| self.get_program(program))
+            .ok_or_else(|| anyhow::anyhow!("Material program not found in cache"))?;
+
+        Ok(ResolvedMaterial::new(
+            program,
+            pipeline_state.unwrap_or_default(),
+            parameters,
+        ))
+    }
+
     /// Create a new input layout in the cache from the given vertex layout.
     pub fn create_input_layout_from_vertex_layout(
         &mut self,
@@ -282,12 +927,88 @@ impl GfxCache {
         handle
     }
 
+    /// Create a new input layout in the cache from the given vertex layout and instance layout.
+    /// The instance layout's inputs advance once per instance, and must be in a single stream.
+    /// Returns an error if either layout does not exist or the instance layout has more than
+    /// one stream.
+    pub fn create_input_layout_with_instances(
+        &mut self,
+        name: Option<String>,
+        vertex_layout: impl CacheRef,
+        instance_layout: impl CacheRef,
+    ) -> Result<CacheHandle> {
+        // Get the vertex and instance layouts from the cache
+        let vertex_layout = self
+            .get_vertex_layout(vertex_layout)
+            .ok_or_else(|| anyhow::anyhow!("Vertex layout not found in cache"))?
+            .clone();
+        let instance_layout = self
+            .get_vertex_layout(instance_layout)
+            .ok_or_else(|| anyhow::anyhow!("Instance layout not found in cache"))?
+            .clone();
+        if instance_layout.stream_count() != 1 {
+            anyhow::bail!("Instance layout must have a single stream.");
+        }
+
+        // Create the input layout
+        let input_layout = unsafe {
+            InputLayout::__from_vertex_and_instance_layouts(vertex_layout, Some(instance_layout))
+        };
+
+        // Insert the input layout into the cache
+        let handle = self.insert(name, input_layout);
+
+        Ok(handle)
+    }
+
+    /// Create a new instance buffer in the cache from a list of per-instance inputs.
+    /// The list is built with the given instance layout, and its indices are ignored.
+    /// The buffer can be rewritten with `Buffer::set_data` through `get_buffer_mut::<u8>`.
+    /// Returns an error if the instance layout does not exist.
+    pub fn create_instance_buffer<'a>(
+        &mut self,
+        name: Option<String>,
+        instance_layout: impl CacheRef,
+        instances: impl IntoVertexList<'a>,
+        usage: BufferUsage,
+    ) -> Result<CacheHandle> {
+        // Get the instance layout from the cache.
+        let instance_layout = self
+            .get_vertex_layout(instance_layout)
+            .ok_or_else(|| anyhow::anyhow!("Instance layout not found in cache"))?
+            .clone();
+
+        // Build the instance list and upload it.
+        let instances = instances.into_vertex_list(self, instance_layout.clone());
+        let buffer = unsafe {
+            Buffer::__from_slice_with_usage(instances.vertex_data(), Some(instance_layout), usage)
+        };
+
+        // Insert the buffer into the cache.
+        let handle = self.insert(name, buffer);
+
+        Ok(handle)
+    }
+
     /// Get an `InputLayout` from the cache.
     pub fn get_input_layout(&self, name_or_handle: impl CacheRef) -> Option<&InputLayout> {
         self.get::<InputLayout>(name_or_handle)
     }
 }
 
+/// Open each of the given image files.
+/// Returns an error if any file could not be loaded.
+fn open_images(paths: &[impl AsRef<Path>]) -> Result<Vec<image::DynamicImage>> {
+    paths
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            image::open(path)
+                .map_err(|e| anyhow::anyhow!("Failed to open image file {:?}: {:?}", path, e))
+        })
+        .collect()
+}
+
 /// Trait for types that point to an object in the `GfxCache`.
 pub trait CacheRef: Clone {
     /// Get the equivalent `CacheHandle` from the cache.
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+
+#[cfg(debug_assertions)]
+use super::pipeline_state::CullMode;
+use super::{pipeline_state::PipelineState, GL_STATE};
+
+/// A shadow of the GL binding and pipeline state, so GL is only called when something changes.
+/// A binding of `None` is unknown, and is always bound the next time it is used.
+#[derive(Debug, Default)]
+pub(crate) struct GlState {
+    draw_framebuffer: Option<u32>,
+    read_framebuffer: Option<u32>,
+    program: Option<u32>,
+    vertex_array: Option<u32>,
+    draw_indirect_buffer: Option<u32>,
+    pixel_pack_buffer: Option<u32>,
+    shader_storage_buffers: HashMap<u32, u32>,
+    vertex_arrays: HashMap<u32, VertexArrayState>,
+    pipeline_state: Option<PipelineState>,
+    primitive_restart: Option<bool>,
+}
+
+/// The buffers attached to a vertex array object.
+#[derive(Debug, Default)]
+struct VertexArrayState {
+    vertex_buffers: HashMap<u32, (u32, isize, i32)>,
+    element_buffer: Option<u32>,
+}
+
+impl GlState {
+    /// Bind a framebuffer for drawing.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_draw_framebuffer(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.draw_framebuffer, handle, || unsafe {
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, handle);
+            })
+        });
+    }
+
+    /// Bind a framebuffer for reading pixels.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_read_framebuffer(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.read_framebuffer, handle, || unsafe {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, handle);
+            })
+        });
+    }
+
+    /// Use a program for drawing and setting uniforms.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __use_program(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.program, handle, || unsafe {
+                gl::UseProgram(handle);
+            })
+        });
+    }
+
+    /// Bind a vertex array object.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_vertex_array(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.vertex_array, handle, || unsafe {
+                gl::BindVertexArray(handle);
+            })
+        });
+    }
+
+    /// Attach a vertex buffer to a binding of a vertex array object, starting at the given
+    /// byte offset.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __vertex_array_vertex_buffer(
+        vertex_array: u32,
+        binding: u32,
+        buffer: u32,
+        offset: isize,
+        stride: i32,
+    ) {
+        Self::with(|state| {
+            let vertex_buffers = &mut state
+                .vertex_arrays
+                .entry(vertex_array)
+                .or_default()
+                .vertex_buffers;
+            if vertex_buffers.get(&binding) != Some(&(buffer, offset, stride)) {
+                unsafe {
+                    gl::VertexArrayVertexBuffer(vertex_array, binding, buffer, offset, stride);
+                }
+                vertex_buffers.insert(binding, (buffer, offset, stride));
+            }
+        });
+    }
+
+    /// Attach an index buffer to a vertex array object.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __vertex_array_element_buffer(vertex_array: u32, buffer: u32) {
+        Self::with(|state| {
+            let vertex_array_state = state.vertex_arrays.entry(vertex_array).or_default();
+            update(&mut vertex_array_state.element_buffer, buffer, || unsafe {
+                gl::VertexArrayElementBuffer(vertex_array, buffer);
+            })
+        });
+    }
+
+    /// Bind the buffer that indirect draw commands are read from.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_draw_indirect_buffer(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.draw_indirect_buffer, handle, || unsafe {
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, handle);
+            })
+        });
+    }
+
+    /// Bind the buffer that pixels are read into, or 0 to read pixels into client memory.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_pixel_pack_buffer(handle: u32) {
+        Self::with(|state| {
+            update(&mut state.pixel_pack_buffer, handle, || unsafe {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, handle);
+            })
+        });
+    }
+
+    /// Bind a buffer to an indexed shader storage buffer binding.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __bind_shader_storage_buffer(index: u32, handle: u32) {
+        Self::with(|state| {
+            if state.shader_storage_buffers.get(&index) != Some(&handle) {
+                unsafe {
+                    gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, index, handle);
+                }
+                state.shader_storage_buffers.insert(index, handle);
+            }
+        });
+    }
+
+    /// Get the pipeline state that was last applied.
+    /// Returns None if the GL state is unknown and must be fully applied.
+    pub(crate) fn __pipeline_state() -> Option<PipelineState> {
+        Self::with(|state| state.pipeline_state)
+    }
+
+    /// Record the pipeline state that was applied, or None to make it unknown.
+    pub(crate) fn __set_pipeline_state(pipeline_state: Option<PipelineState>) {
+        Self::with(|state| state.pipeline_state = pipeline_state);
+    }
+
+    /// Enable or disable restarting strips and fans at the maximum value of the index type.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __set_primitive_restart(enabled: bool) {
+        Self::with(|state| {
+            if state.primitive_restart != Some(enabled) {
+                unsafe {
+                    if enabled {
+                        gl::Enable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+                    } else {
+                        gl::Disable(gl::PRIMITIVE_RESTART_FIXED_INDEX);
+                    }
+                }
+                state.primitive_restart = Some(enabled);
+            }
+        });
+    }
+
+    /// Forget the bindings of a deleted buffer.
+    /// A deleted buffer stays attached to vertex arrays that are not bound, so its name
+    /// could be reused while the shadow still thinks it is attached.
+    pub(crate) fn __forget_buffer(handle: u32) {
+        Self::with(|state| {
+            for vertex_array_state in state.vertex_arrays.values_mut() {
+                vertex_array_state
+                    .vertex_buffers
+                    .retain(|_, (buffer, _, _)| *buffer != handle);
+                if vertex_array_state.element_buffer == Some(handle) {
+                    vertex_array_state.element_buffer = None;
+                }
+            }
+            if state.draw_indirect_buffer == Some(handle) {
+                state.draw_indirect_buffer = None;
+            }
+            if state.pixel_pack_buffer == Some(handle) {
+                state.pixel_pack_buffer = None;
+            }
+            state
+                .shader_storage_buffers
+                .retain(|_, buffer| *buffer != handle);
+        });
+    }
+
+    /// Forget the bindings of a deleted vertex array object.
+    pub(crate) fn __forget_vertex_array(handle: u32) {
+        Self::with(|state| {
+            state.vertex_arrays.remove(&handle);
+            if state.vertex_array == Some(handle) {
+                state.vertex_array = Some(0);
+            }
+        });
+    }
+
+    /// Forget the binding of a deleted program.
+    pub(crate) fn __forget_program(handle: u32) {
+        Self::with(|state| {
+            if state.program == Some(handle) {
+                state.program = None;
+            }
+        });
+    }
+
+    /// Forget the bindings of a deleted framebuffer.
+    pub(crate) fn __forget_framebuffer(handle: u32) {
+        Self::with(|state| {
+            if state.draw_framebuffer == Some(handle) {
+                state.draw_framebuffer = Some(0);
+            }
+            if state.read_framebuffer == Some(handle) {
+                state.read_framebuffer = Some(0);
+            }
+        });
+    }
+
+    /// Mark every binding and the pipeline state as unknown, so the next use of each
+    /// is always sent to GL.
+    pub(crate) fn __invalidate() {
+        Self::with(|state| *state = GlState::default());
+    }
+
+    /// Panic if the shadow does not match the real GL state.
+    /// This only checks in debug builds, as reading back GL state stalls the pipeline.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __verify() {
+        #[cfg(debug_assertions)]
+        Self::with(|state| unsafe { state.verify() });
+    }
+
+    /// Panic if any known binding does not match the real GL state.
+    #[cfg(debug_assertions)]
+    unsafe fn verify(&self) {
+        unsafe {
+            check(
+                "draw framebuffer",
+                self.draw_framebuffer,
+                get(gl::DRAW_FRAMEBUFFER_BINDING),
+            );
+            check(
+                "read framebuffer",
+                self.read_framebuffer,
+                get(gl::READ_FRAMEBUFFER_BINDING),
+            );
+            check("program", self.program, get(gl::CURRENT_PROGRAM));
+            check(
+                "vertex array",
+                self.vertex_array,
+                get(gl::VERTEX_ARRAY_BINDING),
+            );
+            check(
+                "draw indirect buffer",
+                self.draw_indirect_buffer,
+                get(gl::DRAW_INDIRECT_BUFFER_BINDING),
+            );
+            check(
+                "pixel pack buffer",
+                self.pixel_pack_buffer,
+                get(gl::PIXEL_PACK_BUFFER_BINDING),
+            );
+            for (&index, &buffer) in &self.shader_storage_buffers {
+                check(
+                    "shader storage buffer",
+                    Some(buffer),
+                    get_indexed(gl::SHADER_STORAGE_BUFFER_BINDING, index),
+                );
+            }
+
+            if let Some(pipeline_state) = &self.pipeline_state {
+                verify_pipeline_state(pipeline_state);
+            }
+            check(
+                "primitive restart",
+                self.primitive_restart.map(u32::from),
+                gl::IsEnabled(gl::PRIMITIVE_RESTART_FIXED_INDEX) as u32,
+            );
+
+            for (&vertex_array, vertex_array_state) in &self.vertex_arrays {
+                let mut element_buffer = 0;
+                gl::GetVertexArrayiv(
+                    vertex_array,
+                    gl::ELEMENT_ARRAY_BUFFER_BINDING,
+                    &mut element_buffer,
+                );
+                check(
+                    "element buffer",
+                    vertex_array_state.element_buffer,
+                    element_buffer as u32,
+                );
+
+                // Vertex buffer bindings can only be read from the bound vertex array.
+                if self.vertex_array != Some(vertex_array) {
+                    continue;
+                }
+                for (&binding, &(buffer, offset, stride)) in &vertex_array_state.vertex_buffers {
+                    check(
+                        "vertex buffer",
+                        Some(buffer),
+                        get_indexed(gl::VERTEX_BINDING_BUFFER, binding),
+                    );
+                    check(
+                        "vertex buffer offset",
+                        Some(offset as u32),
+                        get_indexed(gl::VERTEX_BINDING_OFFSET, binding),
+                    );
+                    check(
+                        "vertex buffer stride",
+                        Some(stride as u32),
+                        get_indexed(gl::VERTEX_BINDING_STRIDE, binding),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Call `f` with the GL state shadow of this thread.
+    fn with<R>(f: impl FnOnce(&mut GlState) -> R) -> R {
+        GL_STATE.with(|state| f(&mut state.borrow_mut()))
+    }
+}
+
+/// Call `bind` and update the shadow if `binding` is not already `handle`.
+fn update(binding: &mut Option<u32>, handle: u32, bind: impl FnOnce()) {
+    if *binding != Some(handle) {
+        bind();
+        *binding = Some(handle);
+    }
+}
+
+/// Get an integer GL state value.
+#[cfg(debug_assertions)]
+unsafe fn get(name: u32) -> u32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value as u32
+}
+
+/// Get an indexed integer GL state value.
+#[cfg(debug_assertions)]
+unsafe fn get_indexed(name: u32, index: u32) -> u32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegeri_v(name, index, &mut value);
+    }
+    value as u32
+}
+
+/// Panic if the capabilities and write masks of a pipeline state do not match the GL state.
+#[cfg(debug_assertions)]
+unsafe fn verify_pipeline_state(pipeline_state: &PipelineState) {
+    unsafe {
+        let enabled = |capability| gl::IsEnabled(capability) as u32;
+        check(
+            "blending",
+            Some(pipeline_state.blend.is_some() as u32),
+            enabled(gl::BLEND),
+        );
+        check(
+            "depth test",
+            Some(pipeline_state.depth.test as u32),
+            enabled(gl::DEPTH_TEST),
+        );
+        check(
+            "face culling",
+            Some((pipeline_state.cull_mode != CullMode::None) as u32),
+            enabled(gl::CULL_FACE),
+        );
+        check(
+            "scissor test",
+            Some(pipeline_state.scissor.is_some() as u32),
+            enabled(gl::SCISSOR_TEST),
+        );
+        check(
+            "stencil test",
+            Some(pipeline_state.stencil.is_some() as u32),
+            enabled(gl::STENCIL_TEST),
+        );
+
+        let mut depth_write = 0;
+        gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_write);
+        check(
+            "depth write",
+            Some(pipeline_state.depth.write as u32),
+            depth_write as u32,
+        );
+
+        let mut color_write = [0; 4];
+        gl::GetBooleanv(gl::COLOR_WRITEMASK, color_write.as_mut_ptr());
+        let mask = pipeline_state.color_write_mask;
+        for (name, expected, actual) in [
+            ("red write", mask.red, color_write[0]),
+            ("green write", mask.green, color_write[1]),
+            ("blue write", mask.blue, color_write[2]),
+            ("alpha write", mask.alpha, color_write[3]),
+        ] {
+            check(name, Some(expected as u32), actual as u32);
+        }
+    }
+}
+
+/// Panic if a known binding of the shadow does not match the real GL state.
+#[cfg(debug_assertions)]
+fn check(name: &str, shadow: Option<u32>, actual: u32) {
+    if let Some(shadow) = shadow {
+        if shadow != actual {
+            panic!(
+                "GL state shadow is out of sync: {} is {} but the shadow has {}",
+                name, actual, shadow
+            );
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use ggmath::prelude::*;
+
+use super::{
+    buffer::{Buffer, BufferUsage},
+    mesh::MeshRange,
+};
+
+/// The shader storage buffer binding of the per-draw data.
+pub(crate) const _DRAW_DATA_BUFFER_BINDING: u32 = 0;
+
+/// The arguments of one draw of `glMultiDrawElementsIndirect`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DrawElementsIndirectCommand {
+    /// The number of indices to draw.
+    pub count: u32,
+    /// The number of instances to draw.
+    pub instance_count: u32,
+    /// The first index to draw.
+    pub first_index: u32,
+    /// The value added to each index before reading the vertex buffer.
+    pub base_vertex: i32,
+    /// The first instance to draw.
+    pub base_instance: u32,
+}
+
+/// The data of one draw, read in shaders with `ShaderInputs::draw_model_matrix` and
+/// `ShaderInputs::draw_parameters`.
+/// This matches the std430 layout of the storage buffer declared in the shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DrawData {
+    /// The model matrix, in column-major order.
+    pub model_matrix: [f32; 16],
+    /// Free parameters, such as a color or material index.
+    pub parameters: [f32; 4],
+}
+
+/// A list of draws of a `MeshPool` that is rendered with a single indirect draw call.
+/// Fill the batch every frame with `push`, then `upload` it before rendering it with
+/// `TargetBuffer::render_indirect`.
+pub struct IndirectBatch {
+    commands: Vec<DrawElementsIndirectCommand>,
+    draw_data: Vec<DrawData>,
+    command_buffer: Buffer<DrawElementsIndirectCommand>,
+    draw_data_buffer: Buffer<DrawData>,
+    uploaded: bool,
+}
+
+impl !Send for IndirectBatch {}
+impl !Sync for IndirectBatch {}
+
+impl IndirectBatch {
+    /// Create a new empty batch.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new() -> Self {
+        unsafe {
+            Self {
+                commands: Vec::new(),
+                draw_data: Vec::new(),
+                command_buffer: Buffer::__from_slice_with_usage(&[], None, BufferUsage::Stream),
+                draw_data_buffer: Buffer::__from_slice_with_usage(&[], None, BufferUsage::Stream),
+                uploaded: true,
+            }
+        }
+    }
+
+    /// Add a draw of a range of the mesh pool with its model matrix and parameters.
+    pub fn push(
+        &mut self,
+        range: MeshRange,
+        model_matrix: Matrix4x4<f32>,
+        parameters: Vector4<f32>,
+    ) {
+        self.commands.push(DrawElementsIndirectCommand {
+            count: range.count as u32,
+            instance_count: 1,
+            first_index: range.first as u32,
+            base_vertex: range.base_vertex,
+            base_instance: 0,
+        });
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(unsafe { std::slice::from_raw_parts(model_matrix.as_ptr(), 16) });
+        self.draw_data.push(DrawData {
+            model_matrix: matrix,
+            parameters: [
+                parameters.x(),
+                parameters.y(),
+                parameters.z(),
+                parameters.w(),
+            ],
+        });
+
+        self.uploaded = false;
+    }
+
+    /// Remove all draws, keeping the buffers' storage.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.draw_data.clear();
+        self.uploaded = false;
+    }
+
+    /// Upload the draws to the command and draw data buffers.
+    /// The buffers are orphaned, so draws of the previous frame do not stall.
+    pub fn upload(&mut self) {
+        self.command_buffer.set_data(&self.commands);
+        self.draw_data_buffer.set_data(&self.draw_data);
+        self.uploaded = true;
+    }
+
+    /// Check if the buffers hold the current draws.
+    pub fn is_uploaded(&self) -> bool {
+        self.uploaded
+    }
+
+    /// Get the number of draws.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Check if the batch has no draws.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Get the draw commands.
+    pub fn commands(&self) -> &[DrawElementsIndirectCommand] {
+        &self.commands
+    }
+
+    /// Get the per-draw data.
+    pub fn draw_data(&self) -> &[DrawData] {
+        &self.draw_data
+    }
+
+    /// Get the buffer of draw commands.
+    pub fn command_buffer(&self) -> &Buffer<DrawElementsIndirectCommand> {
+        &self.command_buffer
+    }
+
+    /// Get the buffer of per-draw data.
+    pub fn draw_data_buffer(&self) -> &Buffer<DrawData> {
+        &self.draw_data_buffer
+    }
+}
@@ -4,8 +4,11 @@ use anyhow::Result;
 
 use super::{
     buffer::VertexBuffer,
+    gl_state::GlState,
+    mesh::Mesh,
     shader::ShaderStage,
     shader_gen::{
+        shader_functions::ShaderFunctions,
         shader_inputs::{ShaderInput, ShaderInputs, SHADER_INPUT_PREFIX},
         shader_outputs::{ShaderOutputs, SHADER_OUTPUT_PREFIX},
         shader_parameters::{ShaderParameters, SHADER_UNIFORM_PREFIX},
@@ -15,13 +18,16 @@ use super::{
 };
 
 // The location the vertex buffer should be bound to.
+// Each additional vertex stream is bound to the following location.
 pub(crate) const _VERTEX_BUFFER_LOCATION: u32 = 0;
 // The location the instance buffer should be bound to.
-pub(crate) const _INSTANCE_BUFFER_LOCATION: u32 = 1;
+// This is the last binding GL guarantees (16 in total), leaving the others to vertex streams.
+pub(crate) const _INSTANCE_BUFFER_LOCATION: u32 = 15;
 
 /// Layout describing a set of vertex and instance inputs for rendering.
 pub struct InputLayout {
     layout: Rc<VertexLayout>,
+    instance_layout: Option<Rc<VertexLayout>>,
     handle: u32,
 }
 
@@ -32,8 +38,18 @@ impl InputLayout {
     /// Create a new vertex array from the given vertex layout.
     /// # Safety
     /// This function is unsafe because it should only be used on the main thread.
-    // TODO: Add instancing support.
     pub(crate) unsafe fn __from_vertex_layout(layout: Rc<VertexLayout>) -> Self {
+        unsafe { Self::__from_vertex_and_instance_layouts(layout, None) }
+    }
+
+    /// Create a new vertex array from the given vertex layout and an optional instance layout.
+    /// The inputs of the instance layout advance once per instance instead of once per vertex.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_vertex_and_instance_layouts(
+        layout: Rc<VertexLayout>,
+        instance_layout: Option<Rc<VertexLayout>>,
+    ) -> Self {
         let mut handle = 0;
 
         unsafe {
@@ -41,25 +57,74 @@ impl InputLayout {
             gl::CreateVertexArrays(1, &mut handle);
         }
 
-        Self { layout, handle }
+        Self {
+            layout,
+            instance_layout,
+            handle,
+        }
     }
 
     pub(crate) unsafe fn __enable_attributes(&self) {
-        // Enable the vertex attributes
-        let mut offset = 0;
-        for (index, input) in self.layout.inputs().iter().enumerate() {
-            gl::EnableVertexArrayAttrib(self.handle, index as u32);
-            gl::VertexArrayAttribBinding(self.handle, index as u32, _VERTEX_BUFFER_LOCATION);
-            gl::VertexArrayAttribFormat(
-                self.handle,
-                index as u32,
-                input.component_count() as i32,
-                gl::FLOAT,
-                gl::FALSE,
-                offset as u32,
-            );
-            gl::VertexArrayBindingDivisor(self.handle, index as u32, 0);
-            offset += input.byte_size();
+        // Enable the vertex attributes, then the instance attributes after them.
+        let vertex_attributes = self.layout.attributes().iter().map(|attribute| {
+            let binding = _VERTEX_BUFFER_LOCATION + attribute.stream() as u32;
+            (attribute, binding)
+        });
+        let instance_attributes = self
+            .instance_layout
+            .iter()
+            .flat_map(|layout| layout.attributes())
+            .map(|attribute| (attribute, _INSTANCE_BUFFER_LOCATION));
+
+        let mut location = 0;
+        for (attribute, binding) in vertex_attributes.chain(instance_attributes) {
+            let format = attribute.format();
+            let location_count = attribute.location_count();
+            let component_count = format
+                .gl_component_count(attribute.input().component_count() / location_count)
+                as i32;
+
+            // Matrices are set up as one attribute per column.
+            for column in 0..location_count {
+                let index = (location + column) as u32;
+                let offset = (attribute.byte_offset()
+                    + column * format.byte_size(component_count as usize))
+                    as u32;
+
+                gl::EnableVertexArrayAttrib(self.handle, index);
+                gl::VertexArrayAttribBinding(self.handle, index, binding);
+                if format.is_integer() {
+                    gl::VertexArrayAttribIFormat(
+                        self.handle,
+                        index,
+                        component_count,
+                        format.gl_type(),
+                        offset,
+                    );
+                } else {
+                    gl::VertexArrayAttribFormat(
+                        self.handle,
+                        index,
+                        component_count,
+                        format.gl_type(),
+                        if format.is_normalized() {
+                            gl::TRUE
+                        } else {
+                            gl::FALSE
+                        },
+                        offset,
+                    );
+                }
+            }
+            location += location_count;
+        }
+
+        // Vertex streams advance per vertex and the instance buffer advances per instance.
+        for stream in 0..self.layout.stream_count() {
+            gl::VertexArrayBindingDivisor(self.handle, _VERTEX_BUFFER_LOCATION + stream as u32, 0);
+        }
+        if self.instance_layout.is_some() {
+            gl::VertexArrayBindingDivisor(self.handle, _INSTANCE_BUFFER_LOCATION, 1);
         }
     }
 
@@ -73,9 +138,32 @@ impl InputLayout {
         &self.layout
     }
 
-    /// Get the vertex stride.
-    pub fn byte_stride(&self) -> usize {
-        self.layout.byte_stride()
+    /// Get the vertex stride of the given stream.
+    pub fn stream_byte_stride(&self, stream: usize) -> usize {
+        self.layout.stream_byte_stride(stream)
+    }
+
+    /// Get the instance layout.
+    /// Returns `None` if the input layout has no per-instance inputs.
+    pub fn instance_layout(&self) -> Option<&VertexLayout> {
+        self.instance_layout.as_deref()
+    }
+
+    /// Get the instance stride.
+    /// Returns 0 if the input layout has no per-instance inputs.
+    pub fn instance_byte_stride(&self) -> usize {
+        self.instance_layout
+            .as_ref()
+            .map_or(0, |layout| layout.stream_byte_stride(0))
+    }
+
+    /// Validate an instance buffer for this input layout.
+    /// Returns an error if the buffer was not created with the layout's instance layout.
+    pub fn validate_instance_buffer(&self, buffer: &VertexBuffer) -> Result<()> {
+        if self.instance_layout.is_none() || buffer.vertex_layout() != self.instance_layout {
+            anyhow::bail!("Instance buffer is not compatible with the input layout.");
+        }
+        Ok(())
     }
 
     /// Validate a vertex buffer for this input layout.
@@ -87,6 +175,26 @@ impl InputLayout {
         Ok(())
     }
 
+    /// Find the vertex buffer of the mesh that each stream of this input layout reads from.
+    /// The mesh's layout may have more inputs or streams than this layout, as long as each
+    /// stream of this layout matches one of the mesh's streams exactly.
+    /// Returns an error if the mesh is not compatible with the layout.
+    pub fn match_mesh_streams<'a>(&self, mesh: &'a Mesh) -> Result<Vec<&'a VertexBuffer>> {
+        let mesh_layout = mesh
+            .vertex_buffer()
+            .vertex_layout()
+            .ok_or_else(|| anyhow::anyhow!("Mesh has no vertex layout."))?;
+        let streams = self
+            .layout
+            .match_streams(&mesh_layout)
+            .map_err(|e| anyhow::anyhow!("Mesh is not compatible with the input layout: {}", e))?;
+
+        Ok(streams
+            .into_iter()
+            .map(|stream| &mesh.vertex_streams()[stream])
+            .collect())
+    }
+
     /// Generate GLSL vertex and fragment shader code for the input layout.
     pub(crate) fn generate_vertex_fragment_shaders(
         &self,
@@ -115,18 +223,23 @@ impl InputLayout {
         &self,
         f: impl FnOnce(&ShaderInputs, &mut ShaderParameters, &mut ShaderOutputs) -> Result<()>,
     ) -> Result<(String, ShaderInputs, ShaderParameters)> {
-        // Create the shader inputs from the vertex layout's inputs.
+        // Create the shader inputs from the vertex and instance layouts' inputs.
         let mut location = 0;
         let inputs = ShaderInputs::with_inputs(
             self.layout
-                .inputs()
+                .attributes()
                 .iter()
-                .map(|input| {
-                    // Get the shader type for the input.
-                    let shader_type = input.shader_type();
+                .chain(
+                    self.instance_layout
+                        .iter()
+                        .flat_map(|layout| layout.attributes()),
+                )
+                .map(|attribute| {
+                    // Get the shader type for the attribute's format.
+                    let shader_type = attribute.shader_type();
 
                     // Create a new shader input.
-                    let input = ShaderInput::new(input.name(), shader_type, location);
+                    let input = ShaderInput::new(attribute.input().name(), shader_type, location);
 
                     // Increment the binding location.
                     location += shader_type.location_count();
@@ -145,9 +258,16 @@ impl InputLayout {
         // Call the closure to generate the shader code.
         f(&inputs, &mut parameters, &mut outputs)?;
 
+        // Collect the helper functions used by the outputs.
+        let mut functions = ShaderFunctions::new();
+        outputs.collect_functions(&mut functions);
+
         // Generate the shader code.
         let mut code = "#version 450\n".to_string();
 
+        // Add the extensions required by the helper functions.
+        code += &functions.glsl_extensions();
+
         // Add the inputs.
         for input in inputs.iter() {
             code += &format!(
@@ -180,6 +300,9 @@ impl InputLayout {
             );
         }
 
+        // Add the helper functions used by the outputs.
+        code += &functions.glsl_source();
+
         // Add the gl_PerVertex block.
         code += "out gl_PerVertex {\n";
         code += "vec4 gl_Position;\n";
@@ -245,9 +368,16 @@ impl InputLayout {
         // Call the closure to generate the shader code.
         f(&inputs, &mut parameters, &mut outputs)?;
 
+        // Collect the helper functions used by the outputs.
+        let mut functions = ShaderFunctions::new();
+        outputs.collect_functions(&mut functions);
+
         // Generate the shader code.
         let mut code = "#version 450\n".to_string();
 
+        // Add the extensions required by the helper functions.
+        code += &functions.glsl_extensions();
+
         // Add the inputs.
         for input in inputs.iter() {
             code += &format!(
@@ -283,6 +413,15 @@ impl InputLayout {
                     parameter.name()
                 );
             }
+
+            // Add a layer uniform if this is a texture array type.
+            if parameter_type == ShaderType::Sampler2DArray {
+                code += &format!(
+                    "uniform float {}{}_layer;\n",
+                    SHADER_UNIFORM_PREFIX,
+                    parameter.name()
+                );
+            }
         }
 
         // Add the fragment color output.
@@ -299,6 +438,9 @@ impl InputLayout {
             );
         }
 
+        // Add the helper functions used by the outputs.
+        code += &functions.glsl_source();
+
         // Begin the main function.
         code += "void main() {\n";
 
@@ -334,5 +476,6 @@ impl Drop for InputLayout {
         unsafe {
             gl::DeleteVertexArrays(1, &self.handle);
         }
+        GlState::__forget_vertex_array(self.handle);
     }
 }
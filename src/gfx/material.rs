@@ -0,0 +1,159 @@
+use super::{
+    gfx_cache::CacheHandle,
+    pipeline_state::PipelineState,
+    program::{Program, UniformValue},
+    render_parameters::RenderParameters,
+};
+
+/// A program, pipeline state and default parameters that many renderers can share.
+/// A material instance has a parent material, and inherits everything it does not override.
+#[derive(Clone)]
+pub struct Material {
+    parent: Option<CacheHandle>,
+    program: Option<CacheHandle>,
+    pipeline_state: Option<PipelineState>,
+    parameters: RenderParameters,
+}
+
+impl Material {
+    /// Create a new material that renders with the given program and the default pipeline state.
+    pub fn new(program: CacheHandle) -> Self {
+        Self {
+            parent: None,
+            program: Some(program),
+            pipeline_state: Some(PipelineState::default()),
+            parameters: RenderParameters::new(),
+        }
+    }
+
+    /// Create a new material instance that inherits from the given parent material.
+    pub fn instance_of(parent: CacheHandle) -> Self {
+        Self {
+            parent: Some(parent),
+            program: None,
+            pipeline_state: None,
+            parameters: RenderParameters::new(),
+        }
+    }
+
+    /// Set the program, such as a variant of the parent material's program.
+    pub fn with_program(mut self, program: CacheHandle) -> Self {
+        self.program = Some(program);
+        self
+    }
+
+    /// Set the pipeline state.
+    pub fn with_pipeline_state(mut self, pipeline_state: PipelineState) -> Self {
+        self.pipeline_state = Some(pipeline_state);
+        self
+    }
+
+    /// Set the default parameters.
+    /// Parameters that are not set are inherited from the parent material.
+    pub fn with_parameters(mut self, parameters: RenderParameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Set a default parameter by name.
+    pub fn with_parameter<T: UniformValue + 'static>(
+        mut self,
+        name: impl Into<String>,
+        value: T,
+    ) -> Self {
+        self.parameters.set(name, value);
+        self
+    }
+
+    /// Get the parent material.
+    /// Returns None if this is not a material instance.
+    pub fn parent(&self) -> Option<&CacheHandle> {
+        self.parent.as_ref()
+    }
+
+    /// Get the program.
+    /// Returns None if the program is inherited from the parent material.
+    pub fn program(&self) -> Option<&CacheHandle> {
+        self.program.as_ref()
+    }
+
+    /// Set the program.
+    pub fn set_program(&mut self, program: CacheHandle) {
+        self.program = Some(program);
+    }
+
+    /// Get the pipeline state.
+    /// Returns None if the pipeline state is inherited from the parent material.
+    pub fn pipeline_state(&self) -> Option<&PipelineState> {
+        self.pipeline_state.as_ref()
+    }
+
+    /// Set the pipeline state.
+    pub fn set_pipeline_state(&mut self, pipeline_state: PipelineState) {
+        self.pipeline_state = Some(pipeline_state);
+    }
+
+    /// Get the default parameters set on this material.
+    pub fn parameters(&self) -> &RenderParameters {
+        &self.parameters
+    }
+
+    /// Get the default parameters set on this material mutably.
+    /// Changes are seen by every renderer using this material or its instances.
+    pub fn parameters_mut(&mut self) -> &mut RenderParameters {
+        &mut self.parameters
+    }
+}
+
+/// A material with its parents resolved, borrowing from the `GfxCache`.
+/// Created with `GfxCache::resolve_material`.
+pub struct ResolvedMaterial<'a> {
+    program: &'a Program,
+    pipeline_state: PipelineState,
+    parameters: Vec<&'a RenderParameters>,
+}
+
+impl<'a> ResolvedMaterial<'a> {
+    /// Create a resolved material.
+    /// `parameters` are ordered from the material itself to its root parent.
+    pub(crate) fn new(
+        program: &'a Program,
+        pipeline_state: PipelineState,
+        parameters: Vec<&'a RenderParameters>,
+    ) -> Self {
+        Self {
+            program,
+            pipeline_state,
+            parameters,
+        }
+    }
+
+    /// Get the program of the nearest material that sets one.
+    pub fn program(&self) -> &'a Program {
+        self.program
+    }
+
+    /// Get the pipeline state of the nearest material that sets one.
+    pub fn pipeline_state(&self) -> &PipelineState {
+        &self.pipeline_state
+    }
+
+    /// Replace the pipeline state, such as with a renderer's override.
+    pub fn with_pipeline_state(mut self, pipeline_state: PipelineState) -> Self {
+        self.pipeline_state = pipeline_state;
+        self
+    }
+
+    /// Get the default parameters of the material and each of its parents,
+    /// ordered from the material itself to its root parent.
+    pub fn parameters(&self) -> &[&'a RenderParameters] {
+        &self.parameters
+    }
+
+    /// Get a default parameter by name from the nearest material that sets it.
+    pub fn get(&self, name: &str) -> Option<&'a dyn UniformValue> {
+        self.parameters
+            .iter()
+            .find_map(|parameters| parameters.get(name))
+    }
+}
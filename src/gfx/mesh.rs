@@ -1,32 +1,571 @@
-use super::buffer::{IndexBuffer, VertexBuffer};
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use super::{
+    buffer::{Buffer, BufferUsage, VertexBuffer},
+    vertex_layout::VertexLayout,
+    vertex_list::VertexList,
+};
 
 /// A mesh for rendering.
 pub struct Mesh {
-    vertex_buffer: VertexBuffer,
-    index_buffer: IndexBuffer,
+    vertex_buffers: Vec<VertexBuffer>,
+    index_buffer: Option<MeshIndices>,
+    topology: PrimitiveTopology,
+    primitive_restart: bool,
 }
 
 impl Mesh {
-    /// Create a new `Mesh` with the given buffers.
-    pub(crate) fn new(vertex_buffer: VertexBuffer, index_buffer: IndexBuffer) -> Self {
-        Self {
-            vertex_buffer,
+    /// Create a new `Mesh` from a vertex list with the given options.
+    /// Each stream of the vertex list gets its own vertex buffer.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_vertex_list(
+        vertex_list: &VertexList,
+        vertex_layout: Rc<VertexLayout>,
+        options: MeshOptions,
+    ) -> Result<Self> {
+        // Create a vertex buffer for each stream.
+        let vertex_buffers = (0..vertex_list.stream_count())
+            .map(|stream| unsafe {
+                Buffer::__from_slice_with_usage(
+                    vertex_list.stream_data(stream).unwrap(),
+                    Some(vertex_layout.clone()),
+                    options.usage,
+                )
+            })
+            .collect();
+
+        // Create the index buffer in the requested format.
+        let index_type = match options.index_format {
+            IndexFormat::NonIndexed => None,
+            _ if vertex_list.indices().is_empty() => None,
+            IndexFormat::Auto => Some(IndexType::smallest_for(vertex_list.indices())),
+            IndexFormat::U8 => Some(IndexType::U8),
+            IndexFormat::U16 => Some(IndexType::U16),
+            IndexFormat::U32 => Some(IndexType::U32),
+        };
+        let index_buffer = match index_type {
+            Some(index_type) => Some(unsafe {
+                MeshIndices::__from_indices(vertex_list.indices(), index_type, options.usage)?
+            }),
+            None => None,
+        };
+
+        Ok(Self {
+            vertex_buffers,
             index_buffer,
+            topology: options.topology,
+            primitive_restart: options.primitive_restart,
+        })
+    }
+
+    /// Create a new empty `Mesh` with a vertex buffer for each stream of the layout
+    /// and a `u32` index buffer, for packing many meshes into.
+    /// The index format of the options is ignored.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __empty(vertex_layout: Rc<VertexLayout>, options: MeshOptions) -> Self {
+        let vertex_buffers = (0..vertex_layout.stream_count())
+            .map(|_| unsafe {
+                Buffer::__from_slice_with_usage(&[], Some(vertex_layout.clone()), options.usage)
+            })
+            .collect();
+        let index_buffer =
+            MeshIndices::U32(unsafe { Buffer::__from_slice_with_usage(&[], None, options.usage) });
+
+        Self {
+            vertex_buffers,
+            index_buffer: Some(index_buffer),
+            topology: options.topology,
+            primitive_restart: options.primitive_restart,
         }
     }
 
-    /// Get the vertex buffer.
+    /// Get the vertex buffer of the first stream.
+    /// This holds all of the vertices if the layout has a single stream.
     pub fn vertex_buffer(&self) -> &VertexBuffer {
-        &self.vertex_buffer
+        &self.vertex_buffers[0]
+    }
+
+    /// Get the vertex buffer of the given stream.
+    /// Returns `None` if the stream does not exist.
+    pub fn vertex_stream(&self, stream: usize) -> Option<&VertexBuffer> {
+        self.vertex_buffers.get(stream)
+    }
+
+    /// Get the vertex buffers of all streams.
+    pub fn vertex_streams(&self) -> &[VertexBuffer] {
+        &self.vertex_buffers
     }
 
     /// Get the index buffer.
-    pub fn index_buffer(&self) -> &IndexBuffer {
-        &self.index_buffer
+    /// Returns `None` if the mesh is not indexed.
+    pub fn index_buffer(&self) -> Option<&MeshIndices> {
+        self.index_buffer.as_ref()
+    }
+
+    /// Get the vertex buffer of the first stream mutably, for partial updates.
+    pub fn vertex_buffer_mut(&mut self) -> &mut VertexBuffer {
+        &mut self.vertex_buffers[0]
+    }
+
+    /// Get the vertex buffer of the given stream mutably, for partial updates.
+    /// Returns `None` if the stream does not exist.
+    pub fn vertex_stream_mut(&mut self, stream: usize) -> Option<&mut VertexBuffer> {
+        self.vertex_buffers.get_mut(stream)
+    }
+
+    /// Get the index buffer mutably, for partial updates.
+    /// Returns `None` if the mesh is not indexed.
+    pub fn index_buffer_mut(&mut self) -> Option<&mut MeshIndices> {
+        self.index_buffer.as_mut()
+    }
+
+    /// Replace the vertices and indices of this mesh with those of the given vertex list.
+    /// The buffers' storage is reused if it is large enough.
+    /// Returns an error if the vertex list has indices and the mesh is not indexed,
+    /// or the mesh is indexed and the vertex list has vertices but no indices.
+    pub(crate) fn set_vertex_list(&mut self, vertex_list: &VertexList) -> Result<()> {
+        let has_indices = !vertex_list.indices().is_empty();
+        let has_vertices = !vertex_list.vertex_data().is_empty();
+        match &mut self.index_buffer {
+            Some(_) if has_vertices && !has_indices => {
+                anyhow::bail!("Mesh is indexed but the vertex list has no indices")
+            }
+            Some(index_buffer) => index_buffer.set_indices(vertex_list.indices())?,
+            None if has_indices => {
+                anyhow::bail!("Mesh is not indexed but the vertex list has indices")
+            }
+            None => {}
+        }
+        for (stream, vertex_buffer) in self.vertex_buffers.iter_mut().enumerate() {
+            vertex_buffer.set_data(vertex_list.stream_data(stream).unwrap());
+        }
+        Ok(())
+    }
+
+    /// Replace the vertices of a single stream with those of the given vertex list,
+    /// leaving the other streams and the indices untouched.
+    pub(crate) fn set_vertex_stream(
+        &mut self,
+        stream: usize,
+        vertex_list: &VertexList,
+    ) -> Result<()> {
+        let vertex_buffer = self
+            .vertex_buffers
+            .get_mut(stream)
+            .ok_or_else(|| anyhow::anyhow!("Mesh has no vertex stream {}", stream))?;
+        vertex_buffer.set_data(vertex_list.stream_data(stream).unwrap());
+        Ok(())
+    }
+
+    /// Get the primitive topology the mesh is drawn with.
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// Check if the maximum value of the index type restarts strips and fans.
+    pub fn primitive_restart(&self) -> bool {
+        self.primitive_restart
     }
 
     /// Get the index count.
+    /// Returns 0 if the mesh is not indexed.
     pub fn index_count(&self) -> usize {
-        self.index_buffer.len()
+        self.index_buffer.as_ref().map_or(0, MeshIndices::len)
+    }
+
+    /// Get the number of vertices in the vertex buffer.
+    pub fn vertex_count(&self) -> usize {
+        let stride = self
+            .vertex_buffer()
+            .vertex_layout()
+            .map_or(1, |layout| layout.stream_byte_stride(0).max(1));
+        self.vertex_buffer().len() / stride
+    }
+
+    /// Get the number of elements a full draw reads.
+    /// This is the index count, or the vertex count if the mesh is not indexed.
+    pub fn draw_count(&self) -> usize {
+        match &self.index_buffer {
+            Some(index_buffer) => index_buffer.len(),
+            None => self.vertex_count(),
+        }
+    }
+
+    /// Get the range that draws the entire mesh.
+    pub fn full_range(&self) -> MeshRange {
+        MeshRange::new(0, self.draw_count())
+    }
+}
+
+/// Represents how the vertices of a mesh are assembled into primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PrimitiveTopology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    /// Patches of the given number of vertices, for tessellation shaders.
+    Patches(u32),
+}
+
+impl PrimitiveTopology {
+    /// Get the GL primitive mode.
+    pub(crate) const fn gl_mode(self) -> u32 {
+        match self {
+            PrimitiveTopology::Points => gl::POINTS,
+            PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+            PrimitiveTopology::LineLoop => gl::LINE_LOOP,
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveTopology::TriangleFan => gl::TRIANGLE_FAN,
+            PrimitiveTopology::Patches(_) => gl::PATCHES,
+        }
+    }
+
+    /// Returns an error if `count` elements do not form whole primitives.
+    /// Strips, loops and fans accept any count, as do lists with primitive restart.
+    pub fn validate_count(self, count: usize, primitive_restart: bool) -> Result<()> {
+        let multiple = match self {
+            PrimitiveTopology::Lines if !primitive_restart => 2,
+            PrimitiveTopology::Triangles if !primitive_restart => 3,
+            PrimitiveTopology::Patches(vertices) => {
+                if vertices == 0 {
+                    anyhow::bail!("Patches must have at least one vertex.");
+                }
+                vertices as usize
+            }
+            _ => 1,
+        };
+        if count % multiple != 0 {
+            anyhow::bail!(
+                "Element count {} is not a multiple of {} for {:?}.",
+                count,
+                multiple,
+                self
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Represents the integer type of the indices in an index buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexType {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexType {
+    /// Get the GL type.
+    pub(crate) const fn gl_type(self) -> u32 {
+        match self {
+            IndexType::U8 => gl::UNSIGNED_BYTE,
+            IndexType::U16 => gl::UNSIGNED_SHORT,
+            IndexType::U32 => gl::UNSIGNED_INT,
+        }
+    }
+
+    /// Get the size of one index in bytes.
+    pub const fn byte_size(self) -> usize {
+        match self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+
+    /// Get the index that restarts strips and fans when primitive restart is enabled.
+    /// This is the maximum value of the type.
+    pub const fn restart_index(self) -> u32 {
+        match self {
+            IndexType::U8 => u8::MAX as u32,
+            IndexType::U16 => u16::MAX as u32,
+            IndexType::U32 => u32::MAX,
+        }
+    }
+
+    /// Get the smallest type that can hold the given indices.
+    /// `u32::MAX` is treated as a restart index and does not count towards the maximum.
+    pub fn smallest_for(indices: &[u32]) -> Self {
+        let max = indices
+            .iter()
+            .copied()
+            .filter(|&index| index != u32::MAX)
+            .max()
+            .unwrap_or(0);
+
+        // The maximum value of each type is reserved for primitive restart.
+        if max < IndexType::U8.restart_index() {
+            IndexType::U8
+        } else if max < IndexType::U16.restart_index() {
+            IndexType::U16
+        } else {
+            IndexType::U32
+        }
+    }
+}
+
+/// Represents the index format of a mesh.
+/// The mesh is not indexed if the vertex list has no indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IndexFormat {
+    /// Use the smallest index type that holds every index.
+    Auto,
+    U8,
+    U16,
+    #[default]
+    U32,
+    /// Ignore the vertex list's indices and draw the vertices in order.
+    NonIndexed,
+}
+
+/// An index buffer with any index type.
+pub enum MeshIndices {
+    U8(Buffer<u8>),
+    U16(Buffer<u16>),
+    U32(Buffer<u32>),
+}
+
+impl MeshIndices {
+    /// Create a new index buffer of the given type from `u32` indices.
+    /// `u32::MAX` is converted to the restart index of the type.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_indices(
+        indices: &[u32],
+        index_type: IndexType,
+        usage: BufferUsage,
+    ) -> Result<Self> {
+        unsafe {
+            Ok(match index_type {
+                IndexType::U8 => MeshIndices::U8(Buffer::__from_slice_with_usage(
+                    &convert_indices(indices)?,
+                    None,
+                    usage,
+                )),
+                IndexType::U16 => MeshIndices::U16(Buffer::__from_slice_with_usage(
+                    &convert_indices(indices)?,
+                    None,
+                    usage,
+                )),
+                IndexType::U32 => {
+                    MeshIndices::U32(Buffer::__from_slice_with_usage(indices, None, usage))
+                }
+            })
+        }
+    }
+
+    /// Get the index type.
+    pub fn index_type(&self) -> IndexType {
+        match self {
+            MeshIndices::U8(_) => IndexType::U8,
+            MeshIndices::U16(_) => IndexType::U16,
+            MeshIndices::U32(_) => IndexType::U32,
+        }
+    }
+
+    /// Get the number of indices.
+    pub fn len(&self) -> usize {
+        match self {
+            MeshIndices::U8(buffer) => buffer.len(),
+            MeshIndices::U16(buffer) => buffer.len(),
+            MeshIndices::U32(buffer) => buffer.len(),
+        }
+    }
+
+    /// Check if there are no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        match self {
+            MeshIndices::U8(buffer) => buffer.handle(),
+            MeshIndices::U16(buffer) => buffer.handle(),
+            MeshIndices::U32(buffer) => buffer.handle(),
+        }
+    }
+
+    /// Get the usage hint the buffer was created with.
+    pub fn usage(&self) -> BufferUsage {
+        match self {
+            MeshIndices::U8(buffer) => buffer.usage(),
+            MeshIndices::U16(buffer) => buffer.usage(),
+            MeshIndices::U32(buffer) => buffer.usage(),
+        }
+    }
+
+    /// Replace all indices, converting them to this buffer's index type.
+    /// `u32::MAX` is converted to the restart index of the type.
+    /// If an index does not fit, the buffer is recreated with the smallest index type that fits.
+    pub fn set_indices(&mut self, indices: &[u32]) -> Result<()> {
+        let result = match self {
+            MeshIndices::U8(buffer) => convert_indices(indices).map(|data| buffer.set_data(&data)),
+            MeshIndices::U16(buffer) => convert_indices(indices).map(|data| buffer.set_data(&data)),
+            MeshIndices::U32(buffer) => {
+                buffer.set_data(indices);
+                Ok(())
+            }
+        };
+
+        if result.is_err() {
+            let index_type = IndexType::smallest_for(indices);
+            *self = unsafe { Self::__from_indices(indices, index_type, self.usage())? };
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert `u32` indices to a smaller type, mapping `u32::MAX` to the type's restart index.
+fn convert_indices<T: TryFrom<u32> + Bounded>(indices: &[u32]) -> Result<Vec<T>> {
+    indices
+        .iter()
+        .map(|&index| {
+            if index == u32::MAX {
+                return Ok(T::MAX);
+            }
+            T::try_from(index)
+                .ok()
+                .filter(|converted| *converted != T::MAX)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Index {} does not fit in {}",
+                        index,
+                        std::any::type_name::<T>()
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Integer types with a maximum value, used as the restart index.
+trait Bounded: PartialEq + Sized {
+    const MAX: Self;
+}
+
+impl Bounded for u8 {
+    const MAX: Self = u8::MAX;
+}
+
+impl Bounded for u16 {
+    const MAX: Self = u16::MAX;
+}
+
+/// Options used when creating a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MeshOptions {
+    pub topology: PrimitiveTopology,
+    pub index_format: IndexFormat,
+    pub usage: BufferUsage,
+    pub primitive_restart: bool,
+}
+
+impl MeshOptions {
+    /// Create new mesh options for static, indexed triangles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the primitive topology.
+    pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Set the index format.
+    pub fn with_index_format(mut self, index_format: IndexFormat) -> Self {
+        self.index_format = index_format;
+        self
+    }
+
+    /// Set the buffer usage.
+    pub fn with_usage(mut self, usage: BufferUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Enable primitive restart, so `u32::MAX` in the vertex list's indices starts a new strip
+    /// or fan.
+    pub fn with_primitive_restart(mut self, primitive_restart: bool) -> Self {
+        self.primitive_restart = primitive_restart;
+        self
+    }
+}
+
+/// Represents a range of a mesh to draw, so one buffer can hold many sub-meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MeshRange {
+    /// The first index (or vertex, if not indexed) to draw.
+    pub first: usize,
+    /// The number of indices (or vertices, if not indexed) to draw.
+    pub count: usize,
+    /// The value added to each index before reading the vertex buffer.
+    pub base_vertex: i32,
+}
+
+impl MeshRange {
+    /// Create a new range with the given first element and count.
+    pub const fn new(first: usize, count: usize) -> Self {
+        Self {
+            first,
+            count,
+            base_vertex: 0,
+        }
+    }
+
+    /// Set the value added to each index before reading the vertex buffer.
+    pub const fn with_base_vertex(mut self, base_vertex: i32) -> Self {
+        self.base_vertex = base_vertex;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smallest_for_reserves_the_restart_index() {
+        assert_eq!(IndexType::smallest_for(&[]), IndexType::U8);
+        assert_eq!(IndexType::smallest_for(&[0, 254]), IndexType::U8);
+        assert_eq!(IndexType::smallest_for(&[255]), IndexType::U16);
+        assert_eq!(IndexType::smallest_for(&[65534]), IndexType::U16);
+        assert_eq!(IndexType::smallest_for(&[65535]), IndexType::U32);
+    }
+
+    #[test]
+    fn smallest_for_ignores_restart_markers() {
+        assert_eq!(IndexType::smallest_for(&[1, u32::MAX, 2]), IndexType::U8);
+        assert_eq!(IndexType::smallest_for(&[u32::MAX]), IndexType::U8);
+    }
+
+    #[test]
+    fn convert_indices_maps_restart_markers() {
+        let converted: Vec<u8> = convert_indices(&[0, 254, u32::MAX]).unwrap();
+        assert_eq!(converted, [0, 254, u8::MAX]);
+
+        let converted: Vec<u16> = convert_indices(&[255, 65534, u32::MAX]).unwrap();
+        assert_eq!(converted, [255, 65534, u16::MAX]);
+    }
+
+    #[test]
+    fn convert_indices_rejects_indices_that_do_not_fit() {
+        // The maximum value of each type is the restart index, so it cannot be a vertex.
+        assert!(convert_indices::<u8>(&[255]).is_err());
+        assert!(convert_indices::<u8>(&[1000]).is_err());
+        assert!(convert_indices::<u16>(&[65535]).is_err());
+        assert!(convert_indices::<u16>(&[70000]).is_err());
     }
 }
@@ -0,0 +1,107 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use super::{
+    buffer::Buffer,
+    mesh::{Mesh, MeshIndices, MeshOptions, MeshRange},
+    vertex_layout::VertexLayout,
+    vertex_list::VertexList,
+};
+
+/// Many meshes with the same vertex layout packed into shared vertex and index buffers.
+/// Each added mesh is drawn with the `MeshRange` returned by `add`, which allows all of
+/// them to be drawn with a single indirect draw call.
+pub struct MeshPool {
+    mesh: Mesh,
+    vertex_layout: Rc<VertexLayout>,
+}
+
+impl !Send for MeshPool {}
+impl !Sync for MeshPool {}
+
+impl MeshPool {
+    /// Create a new empty mesh pool with the given topology, usage and primitive restart.
+    /// Indices are always stored as `u32`, so the index format of the options is ignored.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new(vertex_layout: Rc<VertexLayout>, options: MeshOptions) -> Self {
+        Self {
+            mesh: unsafe { Mesh::__empty(vertex_layout.clone(), options) },
+            vertex_layout,
+        }
+    }
+
+    /// Append the vertices and indices of a vertex list to the pool.
+    /// Returns the range that draws the added mesh.
+    /// Returns an error if the vertex list has a different layout or is not indexed.
+    pub fn add(&mut self, vertex_list: &VertexList) -> Result<MeshRange> {
+        if *vertex_list.layout() != *self.vertex_layout {
+            anyhow::bail!("Vertex list layout does not match the mesh pool layout.");
+        }
+        if vertex_list.indices().is_empty() {
+            anyhow::bail!("Meshes added to a mesh pool must be indexed.");
+        }
+
+        let base_vertex = self.vertex_count();
+        let first = self.index_count();
+
+        // Append the vertices of each stream.
+        for stream in 0..self.vertex_layout.stream_count() {
+            let data = vertex_list.stream_data(stream).unwrap();
+            let vertex_buffer = self.mesh.vertex_stream_mut(stream).unwrap();
+            let offset = vertex_buffer.len();
+            grow(vertex_buffer, offset + data.len());
+            vertex_buffer.update(offset, data)?;
+        }
+
+        // Append the indices.
+        let Some(MeshIndices::U32(index_buffer)) = self.mesh.index_buffer_mut() else {
+            unreachable!("Mesh pools always have u32 indices");
+        };
+        let indices = vertex_list.indices();
+        grow(index_buffer, first + indices.len());
+        index_buffer.update(first, indices)?;
+
+        Ok(MeshRange::new(first, indices.len()).with_base_vertex(base_vertex as i32))
+    }
+
+    /// Remove all meshes from the pool, keeping the buffers' storage.
+    /// Ranges returned by `add` are no longer valid afterwards.
+    pub fn clear(&mut self) {
+        for stream in 0..self.vertex_layout.stream_count() {
+            self.mesh.vertex_stream_mut(stream).unwrap().resize(0);
+        }
+        if let Some(MeshIndices::U32(index_buffer)) = self.mesh.index_buffer_mut() {
+            index_buffer.resize(0);
+        }
+    }
+
+    /// Get the mesh holding the shared buffers.
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// Get the vertex layout of the pool.
+    pub fn vertex_layout(&self) -> &Rc<VertexLayout> {
+        &self.vertex_layout
+    }
+
+    /// Get the total number of vertices in the pool.
+    pub fn vertex_count(&self) -> usize {
+        self.mesh.vertex_count()
+    }
+
+    /// Get the total number of indices in the pool.
+    pub fn index_count(&self) -> usize {
+        self.mesh.index_count()
+    }
+}
+
+/// Change the length of a buffer, at least doubling its capacity when it needs to grow.
+fn grow<T>(buffer: &mut Buffer<T>, length: usize) {
+    if length > buffer.capacity() {
+        buffer.resize(length.max(buffer.capacity() * 2));
+    }
+    buffer.resize(length);
+}
@@ -1,14 +1,29 @@
 pub mod buffer;
+pub mod cubemap;
+pub mod framebuffer;
 pub mod gfx_cache;
+pub mod gl_state;
+pub mod indirect_batch;
 pub mod input_layout;
+pub mod material;
 pub mod mesh;
+pub mod mesh_pool;
+pub mod pipeline_state;
 pub mod program;
+pub mod readback;
 pub mod render_camera;
 pub mod render_parameters;
+pub mod render_queue;
+pub mod ring_buffer;
+pub mod sampler;
 pub mod shader;
 pub mod shader_gen;
 pub mod target_buffer;
 pub mod texture;
+pub mod texture_array;
+pub mod texture_atlas;
+pub mod texture_format;
+pub mod texture_metadata;
 pub mod vertex_layout;
 pub mod vertex_list;
 
@@ -19,7 +34,9 @@ use std::{
 
 use gfx_cache::GfxCache;
 use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+use gl_state::GlState;
 use glfw::Window;
+use pipeline_state::PipelineState;
 use target_buffer::TargetBuffer;
 
 thread_local! {
@@ -27,6 +44,8 @@ thread_local! {
     pub static GFX: Cell<Option<Gfx>> = None.into();
     /// The graphics cache. Should only be used in the main thread.
     pub static CACHE: RefCell<Option<GfxCache>> = None.into();
+    /// The shadow of the GL binding state. Should only be used in the main thread.
+    pub(crate) static GL_STATE: RefCell<GlState> = RefCell::new(GlState::default());
 }
 
 /// The debug message callback for OpenGL.
@@ -106,6 +125,15 @@ impl Gfx {
     /// This should only be called once in the main thread.
     /// This function will panic if called more than once.
     pub fn init(window: &mut Window) {
+        Gfx::init_with_loader(|symbol| window.get_proc_address(symbol) as *const _);
+    }
+
+    /// Initialize the graphics controller on the current OpenGL context,
+    /// loading function pointers with the given loader.
+    /// This allows using a context that was not created by GLFW, such as a surfaceless EGL context.
+    /// This should only be called once in the main thread.
+    /// This function will panic if called more than once.
+    pub fn init_with_loader(loader: impl FnMut(&'static str) -> *const c_void) {
         // Get a thread-local reference to the graphics controller.
         GFX.with(|gfx| {
             // Panic if the graphics controller has already been initialized.
@@ -114,7 +142,7 @@ impl Gfx {
             }
 
             // Load the OpenGL function pointers.
-            gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+            gl::load_with(loader);
 
             // Enable debug output if debug assertions are enabled.
             #[cfg(debug_assertions)]
@@ -132,10 +160,14 @@ impl Gfx {
                 );
             }
 
-            // Enable depth testing and face culling.
+            // Apply the default pipeline state (depth testing and back-face culling).
             unsafe {
-                gl::Enable(gl::DEPTH_TEST);
-                gl::Enable(gl::CULL_FACE);
+                PipelineState::default().__apply();
+            }
+
+            // Filter across cube map face edges.
+            unsafe {
+                gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
             }
 
             // Initialize the graphics controller.
@@ -181,6 +213,12 @@ impl Gfx {
         })
     }
 
+    /// Forget the cached GL bindings and pipeline state, so each is set again on its next use.
+    /// Call this after making GL calls outside of this crate that bind objects or change state.
+    pub fn invalidate_state(&self) {
+        GlState::__invalidate();
+    }
+
     /// Get the default framebuffer as a `TargetBuffer`.
     pub fn default_framebuffer(&self) -> TargetBuffer {
         TargetBuffer::DEFAULT
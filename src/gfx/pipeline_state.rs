@@ -0,0 +1,563 @@
+use ggmath::prelude::*;
+
+use super::gl_state::GlState;
+
+/// Represents a factor that the source or destination color is multiplied by when blending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SourceColor,
+    OneMinusSourceColor,
+    DestinationColor,
+    OneMinusDestinationColor,
+    SourceAlpha,
+    OneMinusSourceAlpha,
+    DestinationAlpha,
+    OneMinusDestinationAlpha,
+}
+
+impl BlendFactor {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SourceColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSourceColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DestinationColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDestinationColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SourceAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSourceAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DestinationAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDestinationAlpha => gl::ONE_MINUS_DST_ALPHA,
+        }
+    }
+}
+
+/// Represents how the weighted source and destination colors are combined when blending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendEquation {
+    /// Source + destination.
+    Add,
+    /// Source - destination.
+    Subtract,
+    /// Destination - source.
+    ReverseSubtract,
+    /// Component-wise minimum, ignoring the factors.
+    Min,
+    /// Component-wise maximum, ignoring the factors.
+    Max,
+}
+
+impl BlendEquation {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            BlendEquation::Add => gl::FUNC_ADD,
+            BlendEquation::Subtract => gl::FUNC_SUBTRACT,
+            BlendEquation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendEquation::Min => gl::MIN,
+            BlendEquation::Max => gl::MAX,
+        }
+    }
+}
+
+/// Describes how fragment colors are blended with the colors already in the target buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub source_color: BlendFactor,
+    pub destination_color: BlendFactor,
+    pub color_equation: BlendEquation,
+    pub source_alpha: BlendFactor,
+    pub destination_alpha: BlendFactor,
+    pub alpha_equation: BlendEquation,
+}
+
+impl BlendState {
+    /// Standard alpha blending for straight (non-premultiplied) alpha.
+    pub const ALPHA: BlendState = BlendState {
+        source_color: BlendFactor::SourceAlpha,
+        destination_color: BlendFactor::OneMinusSourceAlpha,
+        color_equation: BlendEquation::Add,
+        source_alpha: BlendFactor::One,
+        destination_alpha: BlendFactor::OneMinusSourceAlpha,
+        alpha_equation: BlendEquation::Add,
+    };
+
+    /// Alpha blending for colors that are already multiplied by their alpha.
+    pub const PREMULTIPLIED: BlendState = BlendState {
+        source_color: BlendFactor::One,
+        destination_color: BlendFactor::OneMinusSourceAlpha,
+        color_equation: BlendEquation::Add,
+        source_alpha: BlendFactor::One,
+        destination_alpha: BlendFactor::OneMinusSourceAlpha,
+        alpha_equation: BlendEquation::Add,
+    };
+
+    /// Additive blending weighted by the source alpha, for glows and particles.
+    pub const ADDITIVE: BlendState = BlendState {
+        source_color: BlendFactor::SourceAlpha,
+        destination_color: BlendFactor::One,
+        color_equation: BlendEquation::Add,
+        source_alpha: BlendFactor::Zero,
+        destination_alpha: BlendFactor::One,
+        alpha_equation: BlendEquation::Add,
+    };
+
+    /// Multiplies the destination color by the source color.
+    pub const MULTIPLY: BlendState = BlendState {
+        source_color: BlendFactor::DestinationColor,
+        destination_color: BlendFactor::Zero,
+        color_equation: BlendEquation::Add,
+        source_alpha: BlendFactor::Zero,
+        destination_alpha: BlendFactor::One,
+        alpha_equation: BlendEquation::Add,
+    };
+}
+
+/// Represents a comparison used for depth and stencil testing.
+/// A fragment passes if `incoming <op> stored` is true.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            CompareFunction::Never => gl::NEVER,
+            CompareFunction::Less => gl::LESS,
+            CompareFunction::Equal => gl::EQUAL,
+            CompareFunction::LessEqual => gl::LEQUAL,
+            CompareFunction::Greater => gl::GREATER,
+            CompareFunction::NotEqual => gl::NOTEQUAL,
+            CompareFunction::GreaterEqual => gl::GEQUAL,
+            CompareFunction::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// Describes depth testing and depth writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthState {
+    /// Whether fragments are tested against the depth buffer.
+    pub test: bool,
+    /// Whether fragments write their depth to the depth buffer.
+    pub write: bool,
+    /// The comparison used for depth testing.
+    pub compare: CompareFunction,
+}
+
+impl DepthState {
+    /// Depth testing and writes are enabled, with nearer fragments passing.
+    pub const DEFAULT: DepthState = DepthState {
+        test: true,
+        write: true,
+        compare: CompareFunction::Less,
+    };
+
+    /// Depth testing is enabled but depth is not written, for transparent geometry.
+    pub const READ_ONLY: DepthState = DepthState {
+        test: true,
+        write: false,
+        compare: CompareFunction::LessEqual,
+    };
+
+    /// Depth testing and writes are disabled.
+    pub const DISABLED: DepthState = DepthState {
+        test: false,
+        write: false,
+        compare: CompareFunction::Always,
+    };
+}
+
+/// Represents which faces are culled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    /// No faces are culled.
+    None,
+    /// Front (counter-clockwise) faces are culled.
+    Front,
+    /// Back (clockwise) faces are culled.
+    Back,
+}
+
+/// Represents how polygons are rasterized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    /// Polygons are filled.
+    Fill,
+    /// Only polygon edges are drawn (wireframe).
+    Line,
+    /// Only polygon vertices are drawn.
+    Point,
+}
+
+impl PolygonMode {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+/// Represents a rectangle of the target buffer that fragments are limited to, in pixels.
+/// `min` is the bottom-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub min: Vector2<u32>,
+    pub size: Vector2<u32>,
+}
+
+/// Represents what happens to the stored stencil value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StencilOperation {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOperation {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            StencilOperation::Keep => gl::KEEP,
+            StencilOperation::Zero => gl::ZERO,
+            StencilOperation::Replace => gl::REPLACE,
+            StencilOperation::Increment => gl::INCR,
+            StencilOperation::IncrementWrap => gl::INCR_WRAP,
+            StencilOperation::Decrement => gl::DECR,
+            StencilOperation::DecrementWrap => gl::DECR_WRAP,
+            StencilOperation::Invert => gl::INVERT,
+        }
+    }
+}
+
+/// Describes stencil testing and stencil writes, applied to both front and back faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StencilState {
+    /// The comparison between the reference value and the stored value.
+    pub compare: CompareFunction,
+    /// The reference value.
+    pub reference: i32,
+    /// The mask applied to both values before comparing.
+    pub read_mask: u32,
+    /// The mask applied to values written to the stencil buffer.
+    pub write_mask: u32,
+    /// The operation when the stencil test fails.
+    pub fail: StencilOperation,
+    /// The operation when the stencil test passes but the depth test fails.
+    pub depth_fail: StencilOperation,
+    /// The operation when both tests pass.
+    pub pass: StencilOperation,
+}
+
+/// Represents which color channels are written to the target buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorWriteMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColorWriteMask {
+    /// All channels are written.
+    pub const ALL: ColorWriteMask = ColorWriteMask {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: true,
+    };
+
+    /// No channels are written.
+    pub const NONE: ColorWriteMask = ColorWriteMask {
+        red: false,
+        green: false,
+        blue: false,
+        alpha: false,
+    };
+}
+
+/// Describes the fixed-function GL state used for a draw call.
+/// Only the parts that differ from the previously applied state are sent to GL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PipelineState {
+    /// The blend state, or `None` to disable blending.
+    pub blend: Option<BlendState>,
+    /// The depth state.
+    pub depth: DepthState,
+    /// Which faces are culled.
+    pub cull_mode: CullMode,
+    /// How polygons are rasterized.
+    pub polygon_mode: PolygonMode,
+    /// The scissor rectangle, or `None` to disable scissor testing.
+    pub scissor: Option<ScissorRect>,
+    /// The stencil state, or `None` to disable stencil testing.
+    pub stencil: Option<StencilState>,
+    /// Which color channels are written.
+    pub color_write_mask: ColorWriteMask,
+}
+
+impl Default for PipelineState {
+    /// Opaque rendering with depth testing and back-face culling.
+    fn default() -> Self {
+        Self {
+            blend: None,
+            depth: DepthState::DEFAULT,
+            cull_mode: CullMode::Back,
+            polygon_mode: PolygonMode::Fill,
+            scissor: None,
+            stencil: None,
+            color_write_mask: ColorWriteMask::ALL,
+        }
+    }
+}
+
+impl PipelineState {
+    /// Create the default pipeline state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a pipeline state for transparent geometry with straight alpha.
+    /// Depth is tested but not written.
+    pub fn transparent() -> Self {
+        Self::new()
+            .with_blend(BlendState::ALPHA)
+            .with_depth(DepthState::READ_ONLY)
+    }
+
+    /// Set the blend state.
+    pub fn with_blend(mut self, blend: BlendState) -> Self {
+        self.blend = Some(blend);
+        self
+    }
+
+    /// Disable blending.
+    pub fn without_blend(mut self) -> Self {
+        self.blend = None;
+        self
+    }
+
+    /// Set the depth state.
+    pub fn with_depth(mut self, depth: DepthState) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set which faces are culled.
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    /// Set how polygons are rasterized.
+    pub fn with_polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// Limit fragments to a rectangle of the target buffer.
+    pub fn with_scissor(mut self, min: Vector2<u32>, size: Vector2<u32>) -> Self {
+        self.scissor = Some(ScissorRect { min, size });
+        self
+    }
+
+    /// Set the stencil state.
+    pub fn with_stencil(mut self, stencil: StencilState) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    /// Set which color channels are written.
+    pub fn with_color_write_mask(mut self, color_write_mask: ColorWriteMask) -> Self {
+        self.color_write_mask = color_write_mask;
+        self
+    }
+
+    /// Apply this state to the GL context, skipping anything that is already set.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __apply(&self) {
+        let previous = GlState::__pipeline_state();
+        unsafe {
+            self.__apply_changes(previous.as_ref());
+        }
+        GlState::__set_pipeline_state(Some(*self));
+    }
+
+    /// Apply the state needed for clearing the target buffer:
+    /// all color channels and depth are writable and the scissor test is disabled,
+    /// so the whole buffer is cleared.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __apply_for_clear() {
+        let mut state = GlState::__pipeline_state().unwrap_or_default();
+        state.color_write_mask = ColorWriteMask::ALL;
+        state.depth.write = true;
+        state.scissor = None;
+        if let Some(stencil) = &mut state.stencil {
+            stencil.write_mask = !0;
+        }
+        unsafe {
+            state.__apply();
+        }
+    }
+
+    /// Forget the tracked GL state, so the next draw applies its state in full.
+    /// This should be called after modifying pipeline state with raw GL calls.
+    /// `Gfx::invalidate_state` also forgets the pipeline state.
+    pub fn invalidate_current() {
+        GlState::__set_pipeline_state(None);
+    }
+
+    /// Set the GL state that differs from `previous`, or all state if `previous` is `None`.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    unsafe fn __apply_changes(&self, previous: Option<&PipelineState>) {
+        // Returns true if the given part of the state has changed.
+        let changed = |f: &dyn Fn(&PipelineState) -> bool| previous.is_none_or(|p| !f(p));
+
+        unsafe {
+            // Blending.
+            if changed(&|p| p.blend == self.blend) {
+                match &self.blend {
+                    Some(blend) => {
+                        gl::Enable(gl::BLEND);
+                        gl::BlendFuncSeparate(
+                            blend.source_color.to_gl_enum(),
+                            blend.destination_color.to_gl_enum(),
+                            blend.source_alpha.to_gl_enum(),
+                            blend.destination_alpha.to_gl_enum(),
+                        );
+                        gl::BlendEquationSeparate(
+                            blend.color_equation.to_gl_enum(),
+                            blend.alpha_equation.to_gl_enum(),
+                        );
+                    }
+                    None => gl::Disable(gl::BLEND),
+                }
+            }
+
+            // Depth testing and writes.
+            if changed(&|p| p.depth.test == self.depth.test) {
+                set_capability(gl::DEPTH_TEST, self.depth.test);
+            }
+            if changed(&|p| p.depth.write == self.depth.write) {
+                gl::DepthMask(gl_bool(self.depth.write));
+            }
+            if changed(&|p| p.depth.compare == self.depth.compare) {
+                gl::DepthFunc(self.depth.compare.to_gl_enum());
+            }
+
+            // Face culling.
+            if changed(&|p| p.cull_mode == self.cull_mode) {
+                match self.cull_mode {
+                    CullMode::None => gl::Disable(gl::CULL_FACE),
+                    CullMode::Front => {
+                        gl::Enable(gl::CULL_FACE);
+                        gl::CullFace(gl::FRONT);
+                    }
+                    CullMode::Back => {
+                        gl::Enable(gl::CULL_FACE);
+                        gl::CullFace(gl::BACK);
+                    }
+                }
+            }
+
+            // Polygon mode.
+            if changed(&|p| p.polygon_mode == self.polygon_mode) {
+                gl::PolygonMode(gl::FRONT_AND_BACK, self.polygon_mode.to_gl_enum());
+            }
+
+            // Scissor testing.
+            if changed(&|p| p.scissor == self.scissor) {
+                match &self.scissor {
+                    Some(scissor) => {
+                        gl::Enable(gl::SCISSOR_TEST);
+                        gl::Scissor(
+                            scissor.min.x() as i32,
+                            scissor.min.y() as i32,
+                            scissor.size.x() as i32,
+                            scissor.size.y() as i32,
+                        );
+                    }
+                    None => gl::Disable(gl::SCISSOR_TEST),
+                }
+            }
+
+            // Stencil testing.
+            if changed(&|p| p.stencil == self.stencil) {
+                match &self.stencil {
+                    Some(stencil) => {
+                        gl::Enable(gl::STENCIL_TEST);
+                        gl::StencilFunc(
+                            stencil.compare.to_gl_enum(),
+                            stencil.reference,
+                            stencil.read_mask,
+                        );
+                        gl::StencilMask(stencil.write_mask);
+                        gl::StencilOp(
+                            stencil.fail.to_gl_enum(),
+                            stencil.depth_fail.to_gl_enum(),
+                            stencil.pass.to_gl_enum(),
+                        );
+                    }
+                    None => {
+                        gl::Disable(gl::STENCIL_TEST);
+                        gl::StencilMask(!0);
+                    }
+                }
+            }
+
+            // Color write mask.
+            if changed(&|p| p.color_write_mask == self.color_write_mask) {
+                let mask = self.color_write_mask;
+                gl::ColorMask(
+                    gl_bool(mask.red),
+                    gl_bool(mask.green),
+                    gl_bool(mask.blue),
+                    gl_bool(mask.alpha),
+                );
+            }
+        }
+    }
+}
+
+/// Enable or disable a GL capability.
+unsafe fn set_capability(capability: u32, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(capability);
+        } else {
+            gl::Disable(capability);
+        }
+    }
+}
+
+/// Convert a bool to a GL boolean.
+const fn gl_bool(value: bool) -> u8 {
+    if value {
+        gl::TRUE
+    } else {
+        gl::FALSE
+    }
+}
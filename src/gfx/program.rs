@@ -1,4 +1,9 @@
-use std::{any::Any, ffi::CString};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ffi::CString,
+};
 
 use anyhow::Result;
 use ggmath::prelude::*;
@@ -6,16 +11,21 @@ use ggmath::prelude::*;
 use crate::app::app_prelude::ShaderParameters;
 
 use super::{
+    cubemap::CubemapView,
+    gl_state::GlState,
     render_parameters::RenderParameters,
     shader::Shader,
     shader_gen::{shader_parameters::SHADER_UNIFORM_PREFIX, shader_type::ShaderType},
     texture::TextureView,
+    texture_array::{Texture3DView, TextureArrayView},
 };
 
 /// Represents a GL program
 pub struct Program {
     handle: u32,
     parameters: ShaderParameters,
+    locations: Vec<UniformLocation>,
+    uniform_cache: UniformCache,
 }
 
 impl !Send for Program {}
@@ -86,7 +96,29 @@ impl Program {
             },
         );
 
-        Ok(Self { handle, parameters })
+        // Resolve the uniform locations of each parameter.
+        let locations = parameters
+            .iter()
+            .map(|parameter| unsafe {
+                UniformLocation::__resolve(handle, parameter.name(), parameter.value_type())
+            })
+            .collect::<Result<_>>();
+        let locations = match locations {
+            Ok(locations) => locations,
+            Err(e) => {
+                unsafe {
+                    gl::DeleteProgram(handle);
+                }
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            handle,
+            parameters,
+            locations,
+            uniform_cache: UniformCache::new(),
+        })
     }
 
     /// Get the GL handle
@@ -94,25 +126,35 @@ impl Program {
         self.handle
     }
 
-    /// Set the value of a uniform
-    pub(crate) fn set_uniform(&self, name: &str, value: &dyn UniformValue) -> Result<()> {
-        // Set the uniform
-        unsafe { value.set_uniform(self.handle, name) }
-    }
-
     /// Get the parameters
     pub fn parameters(&self) -> &ShaderParameters {
         &self.parameters
     }
 
-    /// Use the given render parameters
-    pub(crate) fn use_parameters(&self, parameters: &RenderParameters) -> Result<()> {
+    /// Get the uniform locations of the given parameter.
+    /// Returns None if the program has no such parameter.
+    pub fn uniform_location(&self, name: impl AsRef<str>) -> Option<&UniformLocation> {
+        let name = name.as_ref();
+        self.parameters
+            .iter()
+            .position(|parameter| parameter.name() == name)
+            .map(|index| &self.locations[index])
+    }
+
+    /// Use the given layers of render parameters
+    /// Each parameter is taken from the first layer that sets it, so earlier layers override
+    /// later ones.
+    /// Texture units are allocated to sampler parameters in order for each draw.
+    /// Values that have not changed since the last draw with this program are not uploaded.
+    pub(crate) fn use_parameters(&self, parameters: &[&RenderParameters]) -> Result<()> {
         let expected_parameters = self.parameters();
+        let mut texture_units = TextureUnits::new();
         // Loop through the expected parameters
-        for parameter in expected_parameters.iter() {
+        for (parameter, location) in expected_parameters.iter().zip(&self.locations) {
             // Get the corresponding input parameter value
             let value = parameters
-                .get(parameter.name())
+                .iter()
+                .find_map(|parameters| parameters.get(parameter.name()))
                 .ok_or_else(|| anyhow::anyhow!("Expected input parameter {}", parameter.name()))?;
 
             // Verify that the types match
@@ -126,7 +168,14 @@ impl Program {
             }
 
             // Set the uniform
-            self.set_uniform(parameter.name(), value)?;
+            unsafe {
+                value.set_uniform(
+                    parameter.name(),
+                    location,
+                    &self.uniform_cache,
+                    &mut texture_units,
+                )?;
+            }
         }
 
         Ok(())
@@ -138,6 +187,62 @@ impl Drop for Program {
         unsafe {
             gl::DeleteProgram(self.handle);
         }
+        GlState::__forget_program(self.handle);
+    }
+}
+
+thread_local! {
+    /// The number of texture units supported by the hardware, queried on first use.
+    static MAX_TEXTURE_UNITS: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Allocates texture units to the sampler parameters of a single draw.
+#[derive(Debug)]
+pub struct TextureUnits {
+    next: u32,
+    max: u32,
+}
+
+impl TextureUnits {
+    /// Create a new allocator with all texture units free.
+    pub(crate) fn new() -> Self {
+        let max = MAX_TEXTURE_UNITS.get().unwrap_or_else(|| {
+            let mut max = 0;
+            unsafe {
+                gl::GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, &mut max);
+            }
+            let max = max as u32;
+            MAX_TEXTURE_UNITS.set(Some(max));
+            max
+        });
+
+        Self { next: 0, max }
+    }
+
+    /// Allocate the next free texture unit for the named sampler.
+    /// Returns an error if all texture units are in use.
+    pub fn allocate(&mut self, name: &str) -> Result<u32> {
+        if self.next >= self.max {
+            anyhow::bail!(
+                "Cannot bind texture parameter {}: all {} texture units are in use",
+                name,
+                self.max
+            );
+        }
+
+        let unit = self.next;
+        self.next += 1;
+        Ok(unit)
+    }
+
+    /// Get the number of texture units allocated so far.
+    pub fn used(&self) -> u32 {
+        self.next
+    }
+
+    /// Get the number of texture units supported by the hardware.
+    pub fn max(&self) -> u32 {
+        self.max
     }
 }
 
@@ -158,13 +263,187 @@ unsafe fn get_uniform_location(program: u32, name: &str) -> Result<i32> {
     }
 }
 
+/// The locations of the uniforms of a single parameter in a program.
+/// These are resolved once when the program is linked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UniformLocation {
+    location: i32,
+    min_location: Option<i32>,
+    max_location: Option<i32>,
+    layer_location: Option<i32>,
+}
+
+impl UniformLocation {
+    /// Resolve the locations of the uniforms of a parameter.
+    /// Returns an error if the parameter's uniform is not in the program.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    unsafe fn __resolve(program: u32, name: &str, value_type: ShaderType) -> Result<Self> {
+        unsafe {
+            let location = get_uniform_location(program, name)?;
+
+            // Texture views have extra uniforms for their region and layer (if they exist).
+            let (min_location, max_location, layer_location) = match value_type {
+                ShaderType::Sampler2D => (
+                    get_uniform_location(program, &format!("{}_min", name)).ok(),
+                    get_uniform_location(program, &format!("{}_max", name)).ok(),
+                    None,
+                ),
+                ShaderType::Sampler2DArray => (
+                    None,
+                    None,
+                    get_uniform_location(program, &format!("{}_layer", name)).ok(),
+                ),
+                _ => (None, None, None),
+            };
+
+            Ok(Self {
+                location,
+                min_location,
+                max_location,
+                layer_location,
+            })
+        }
+    }
+
+    /// Get the location of the parameter's value.
+    pub fn location(&self) -> i32 {
+        self.location
+    }
+
+    /// Get the location of the region minimum of a texture view.
+    /// Returns None if the parameter is not a texture or the uniform is unused.
+    pub fn min_location(&self) -> Option<i32> {
+        self.min_location
+    }
+
+    /// Get the location of the region maximum of a texture view.
+    /// Returns None if the parameter is not a texture or the uniform is unused.
+    pub fn max_location(&self) -> Option<i32> {
+        self.max_location
+    }
+
+    /// Get the location of the layer of a texture array view.
+    /// Returns None if the parameter is not a texture array or the uniform is unused.
+    pub fn layer_location(&self) -> Option<i32> {
+        self.layer_location
+    }
+}
+
+/// The values last uploaded to the uniforms of a program.
+/// Uniform values are part of the program object, so uploads of unchanged values are skipped.
+#[derive(Debug, Default)]
+pub struct UniformCache {
+    values: RefCell<HashMap<i32, Vec<u32>>>,
+}
+
+impl UniformCache {
+    /// Create a new empty cache.
+    pub(crate) fn new() -> Self {
+        Self {
+            values: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Set an `int` or sampler uniform.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_i32(&self, location: i32, value: i32) {
+        if self.changed(location, &[value as u32]) {
+            unsafe { gl::Uniform1i(location, value) };
+        }
+    }
+
+    /// Set a `float` uniform.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_f32(&self, location: i32, value: f32) {
+        if self.changed(location, &[value.to_bits()]) {
+            unsafe { gl::Uniform1f(location, value) };
+        }
+    }
+
+    /// Set a `vec2` uniform.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_vec2(&self, location: i32, x: f32, y: f32) {
+        if self.changed(location, &[x.to_bits(), y.to_bits()]) {
+            unsafe { gl::Uniform2f(location, x, y) };
+        }
+    }
+
+    /// Set a `vec3` uniform.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_vec3(&self, location: i32, x: f32, y: f32, z: f32) {
+        if self.changed(location, &[x.to_bits(), y.to_bits(), z.to_bits()]) {
+            unsafe { gl::Uniform3f(location, x, y, z) };
+        }
+    }
+
+    /// Set a `vec4` uniform.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_vec4(&self, location: i32, x: f32, y: f32, z: f32, w: f32) {
+        if self.changed(
+            location,
+            &[x.to_bits(), y.to_bits(), z.to_bits(), w.to_bits()],
+        ) {
+            unsafe { gl::Uniform4f(location, x, y, z, w) };
+        }
+    }
+
+    /// Set a `mat3` uniform from 9 column-major values.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_mat3(&self, location: i32, values: &[f32; 9]) {
+        if self.changed(location, &values.map(f32::to_bits)) {
+            unsafe { gl::UniformMatrix3fv(location, 1, gl::FALSE, values.as_ptr()) };
+        }
+    }
+
+    /// Set a `mat4` uniform from 16 column-major values.
+    /// # Safety
+    /// This function is unsafe because the program must be in use on the main thread.
+    pub unsafe fn set_mat4(&self, location: i32, values: &[f32; 16]) {
+        if self.changed(location, &values.map(f32::to_bits)) {
+            unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, values.as_ptr()) };
+        }
+    }
+
+    /// Forget every uploaded value, so the next draw uploads all of them.
+    pub fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+
+    /// Record the value of a uniform, returning true if it differs from the last value.
+    fn changed(&self, location: i32, bits: &[u32]) -> bool {
+        let mut values = self.values.borrow_mut();
+        let value = values.entry(location).or_default();
+        if value.as_slice() == bits {
+            return false;
+        }
+
+        value.clear();
+        value.extend_from_slice(bits);
+        true
+    }
+}
+
 /// Represents a value that can be set as a uniform
 pub trait UniformValue: Any {
-    /// Copy this value to the uniform at the given location.
+    /// Copy this value to the uniforms at the given locations, skipping unchanged values.
+    /// Samplers allocate a texture unit from `texture_units`.
     /// # Safety
     /// This function is unsafe because it must be called on the main thread.
     /// It is also unsafe because it uses raw OpenGL functions.
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()>;
+    unsafe fn set_uniform(
+        &self,
+        name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        texture_units: &mut TextureUnits,
+    ) -> Result<()>;
     /// Get the `ShaderType` of the uniform
     fn value_type(&self) -> ShaderType;
     /// Get the value as an `Any` trait object
@@ -173,11 +452,41 @@ pub trait UniformValue: Any {
     fn boxed_clone(&self) -> Box<dyn UniformValue>;
 }
 
-impl UniformValue for f32 {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let location = get_uniform_location(program, name)?;
+impl UniformValue for i32 {
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        cache.set_i32(location.location(), *self);
 
-        gl::Uniform1f(location, *self);
+        Ok(())
+    }
+
+    fn value_type(&self) -> ShaderType {
+        ShaderType::I32
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn boxed_clone(&self) -> Box<dyn UniformValue> {
+        Box::new(*self)
+    }
+}
+
+impl UniformValue for f32 {
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        cache.set_f32(location.location(), *self);
 
         Ok(())
     }
@@ -196,10 +505,14 @@ impl UniformValue for f32 {
 }
 
 impl UniformValue for Vector2<f32> {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let location = get_uniform_location(program, name)?;
-
-        gl::Uniform2f(location, self.x(), self.y());
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        cache.set_vec2(location.location(), self.x(), self.y());
 
         Ok(())
     }
@@ -218,10 +531,14 @@ impl UniformValue for Vector2<f32> {
 }
 
 impl UniformValue for Vector3<f32> {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let location = get_uniform_location(program, name)?;
-
-        gl::Uniform3f(location, self.x(), self.y(), self.z());
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        cache.set_vec3(location.location(), self.x(), self.y(), self.z());
 
         Ok(())
     }
@@ -240,10 +557,14 @@ impl UniformValue for Vector3<f32> {
 }
 
 impl UniformValue for Vector4<f32> {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let location = get_uniform_location(program, name)?;
-
-        gl::Uniform4f(location, self.x(), self.y(), self.z(), self.w());
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        cache.set_vec4(location.location(), self.x(), self.y(), self.z(), self.w());
 
         Ok(())
     }
@@ -261,11 +582,46 @@ impl UniformValue for Vector4<f32> {
     }
 }
 
-impl UniformValue for Matrix4x4<f32> {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let location = get_uniform_location(program, name)?;
+// A 3x3 matrix as an array of columns, such as the normal matrix.
+impl UniformValue for [[f32; 3]; 3] {
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        let [x, y, z] = *self;
+        let values = [x[0], x[1], x[2], y[0], y[1], y[2], z[0], z[1], z[2]];
+        cache.set_mat3(location.location(), &values);
+
+        Ok(())
+    }
+
+    fn value_type(&self) -> ShaderType {
+        ShaderType::Mat3
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn boxed_clone(&self) -> Box<dyn UniformValue> {
+        Box::new(*self)
+    }
+}
 
-        gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr());
+impl UniformValue for Matrix4x4<f32> {
+    unsafe fn set_uniform(
+        &self,
+        _name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        _texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        let mut values = [0.0; 16];
+        values.copy_from_slice(std::slice::from_raw_parts(self.as_ptr(), 16));
+        cache.set_mat4(location.location(), &values);
 
         Ok(())
     }
@@ -284,27 +640,36 @@ impl UniformValue for Matrix4x4<f32> {
 }
 
 impl UniformValue for TextureView {
-    unsafe fn set_uniform(&self, program: u32, name: &str) -> Result<()> {
-        let texture_location = get_uniform_location(program, name)?;
-        let min_location = get_uniform_location(program, &format!("{}_min", name));
-        let max_location = get_uniform_location(program, &format!("{}_max", name));
-
-        // Get the appropriate texture unit
-        let texture_unit = self.texture_type().texture_unit_index();
+    unsafe fn set_uniform(
+        &self,
+        name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        // Allocate a texture unit for this sampler
+        let texture_unit = texture_units.allocate(name)?;
 
         // Bind the texture to the texture unit
         gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
         gl::BindTexture(gl::TEXTURE_2D, self.handle());
 
+        // Bind the view's sampler, or unbind to use the texture's own sampler settings
+        let sampler_handle = self
+            .sampler()
+            .map(|sampler| sampler.__sampler_object())
+            .unwrap_or(0);
+        gl::BindSampler(texture_unit, sampler_handle);
+
         // Set the texture uniform
-        gl::Uniform1i(texture_location, texture_unit as i32);
+        cache.set_i32(location.location(), texture_unit as i32);
 
         // Set the min and max uniforms (if they exist)
-        if let Ok(min_location) = min_location {
-            gl::Uniform3f(min_location, self.min().x(), self.min().y(), self.min().z());
+        if let Some(min_location) = location.min_location() {
+            cache.set_vec3(min_location, self.min().x(), self.min().y(), self.min().z());
         }
-        if let Ok(max_location) = max_location {
-            gl::Uniform3f(max_location, self.max().x(), self.max().y(), self.max().z());
+        if let Some(max_location) = location.max_location() {
+            cache.set_vec3(max_location, self.max().x(), self.max().y(), self.max().z());
         }
 
         Ok(())
@@ -323,11 +688,145 @@ impl UniformValue for TextureView {
     }
 }
 
+impl UniformValue for CubemapView {
+    unsafe fn set_uniform(
+        &self,
+        name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        // Allocate a texture unit for this sampler
+        let texture_unit = texture_units.allocate(name)?;
+
+        // Bind the cube map to the texture unit
+        gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.handle());
+
+        // Bind the view's sampler, or unbind to use the cube map's own sampler settings
+        let sampler_handle = self
+            .sampler()
+            .map(|sampler| sampler.__sampler_object())
+            .unwrap_or(0);
+        gl::BindSampler(texture_unit, sampler_handle);
+
+        // Set the sampler uniform
+        cache.set_i32(location.location(), texture_unit as i32);
+
+        Ok(())
+    }
+
+    fn value_type(&self) -> ShaderType {
+        ShaderType::SamplerCube
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn boxed_clone(&self) -> Box<dyn UniformValue> {
+        Box::new(*self)
+    }
+}
+
+impl UniformValue for TextureArrayView {
+    unsafe fn set_uniform(
+        &self,
+        name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        // Allocate a texture unit for this sampler
+        let texture_unit = texture_units.allocate(name)?;
+
+        // Bind the texture array to the texture unit
+        gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.handle());
+
+        // Bind the view's sampler, or unbind to use the texture array's own sampler settings
+        let sampler_handle = self
+            .sampler()
+            .map(|sampler| sampler.__sampler_object())
+            .unwrap_or(0);
+        gl::BindSampler(texture_unit, sampler_handle);
+
+        // Set the sampler uniform
+        cache.set_i32(location.location(), texture_unit as i32);
+
+        // Set the layer uniform (if it exists)
+        if let Some(layer_location) = location.layer_location() {
+            cache.set_f32(layer_location, self.layer() as f32);
+        }
+
+        Ok(())
+    }
+
+    fn value_type(&self) -> ShaderType {
+        ShaderType::Sampler2DArray
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn boxed_clone(&self) -> Box<dyn UniformValue> {
+        Box::new(*self)
+    }
+}
+
+impl UniformValue for Texture3DView {
+    unsafe fn set_uniform(
+        &self,
+        name: &str,
+        location: &UniformLocation,
+        cache: &UniformCache,
+        texture_units: &mut TextureUnits,
+    ) -> Result<()> {
+        // Allocate a texture unit for this sampler
+        let texture_unit = texture_units.allocate(name)?;
+
+        // Bind the 3D texture to the texture unit
+        gl::ActiveTexture(gl::TEXTURE0 + texture_unit);
+        gl::BindTexture(gl::TEXTURE_3D, self.handle());
+
+        // Bind the view's sampler, or unbind to use the texture's own sampler settings
+        let sampler_handle = self
+            .sampler()
+            .map(|sampler| sampler.__sampler_object())
+            .unwrap_or(0);
+        gl::BindSampler(texture_unit, sampler_handle);
+
+        // Set the sampler uniform
+        cache.set_i32(location.location(), texture_unit as i32);
+
+        Ok(())
+    }
+
+    fn value_type(&self) -> ShaderType {
+        ShaderType::Sampler3D
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn boxed_clone(&self) -> Box<dyn UniformValue> {
+        Box::new(*self)
+    }
+}
+
 /// Represents a value that can be set as a uniform with a default value
 pub trait UniformDefault {
     fn default_value() -> Self;
 }
 
+impl UniformDefault for i32 {
+    fn default_value() -> Self {
+        0
+    }
+}
+
 impl UniformDefault for f32 {
     fn default_value() -> Self {
         0.0
@@ -340,6 +839,12 @@ impl<const N: usize> UniformDefault for Vector<f32, N> {
     }
 }
 
+impl UniformDefault for [[f32; 3]; 3] {
+    fn default_value() -> Self {
+        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+    }
+}
+
 impl UniformDefault for Matrix4x4<f32> {
     fn default_value() -> Self {
         Matrix4x4::identity()
@@ -351,3 +856,21 @@ impl UniformDefault for TextureView {
         Self::default()
     }
 }
+
+impl UniformDefault for CubemapView {
+    fn default_value() -> Self {
+        Self::default()
+    }
+}
+
+impl UniformDefault for TextureArrayView {
+    fn default_value() -> Self {
+        Self::default()
+    }
+}
+
+impl UniformDefault for Texture3DView {
+    fn default_value() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use ggmath::prelude::*;
+use gl::types::GLsync;
+
+use super::gl_state::GlState;
+
+/// A pending asynchronous pixel readback from a `TargetBuffer`.
+/// The pixels are copied into a pixel buffer object on the GPU, so creating the readback
+/// does not stall; call `is_ready` on later frames and `take` once it is ready.
+pub struct PixelReadback {
+    buffer_handle: u32,
+    fence: GLsync,
+    size: Vector2<u32>,
+}
+
+impl !Send for PixelReadback {}
+impl !Sync for PixelReadback {}
+
+impl PixelReadback {
+    /// Create a new readback from a buffer that the pixels are being copied into.
+    /// A fence is inserted to track when the copy is complete.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new(buffer_handle: u32, size: Vector2<u32>) -> Self {
+        let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+
+        Self {
+            buffer_handle,
+            fence,
+            size,
+        }
+    }
+
+    /// Get the size of the region being read, in pixels.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Check if the pixels have finished copying, without blocking.
+    pub fn is_ready(&self) -> bool {
+        let status = unsafe { gl::ClientWaitSync(self.fence, 0, 0) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Take the pixels as an image with the top row first.
+    /// This will block until the pixels have finished copying if they are not ready yet.
+    pub fn take(self) -> Result<image::RgbaImage> {
+        unsafe {
+            // Wait for the copy to complete.
+            loop {
+                let status = gl::ClientWaitSync(self.fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000);
+                match status {
+                    gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => break,
+                    gl::TIMEOUT_EXPIRED => continue,
+                    _ => anyhow::bail!("Failed to wait for pixel readback"),
+                }
+            }
+
+            // Copy the pixels from the buffer.
+            let mut pixels = vec![0u8; byte_count(self.size)];
+            gl::GetNamedBufferSubData(
+                self.buffer_handle,
+                0,
+                pixels.len() as isize,
+                pixels.as_mut_ptr() as *mut _,
+            );
+
+            pixels_to_image(self.size, pixels)
+        }
+    }
+}
+
+impl Drop for PixelReadback {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.fence);
+            gl::DeleteBuffers(1, &self.buffer_handle);
+        }
+        GlState::__forget_buffer(self.buffer_handle);
+    }
+}
+
+/// Get the number of bytes of tightly packed RGBA8 pixels in a region.
+/// Computed as `usize`, so large regions do not overflow.
+pub(crate) fn byte_count(size: Vector2<u32>) -> usize {
+    size.x() as usize * size.y() as usize * 4
+}
+
+/// Convert tightly packed RGBA8 pixels with the bottom row first (as read from GL)
+/// into an image with the top row first.
+pub(crate) fn pixels_to_image(size: Vector2<u32>, pixels: Vec<u8>) -> Result<image::RgbaImage> {
+    let mut image = image::RgbaImage::from_raw(size.x(), size.y(), pixels)
+        .ok_or_else(|| anyhow::anyhow!("Pixel data does not match the image size"))?;
+    image::imageops::flip_vertical_in_place(&mut image);
+    Ok(image)
+}
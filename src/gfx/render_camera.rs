@@ -165,6 +165,13 @@ impl RenderCamera {
         Matrix::new_view(&position, &target, &up)
     }
 
+    /// Get the distance of a world space point from the camera along its view direction.
+    pub fn view_depth(&self, point: Vector3<f32>) -> f32 {
+        let offset = point - self.position();
+        let forward = self.get_rotation_matrix() * Vector3::unit_z();
+        offset.x() * forward.x() + offset.y() * forward.y() + offset.z() * forward.z()
+    }
+
     /// Calculate the projection matrix.
     /// If the camera is a perspective camera, the resolution is used to calculate the aspect ratio.
     pub fn get_projection_matrix(&self, viewport_size: Vector2<f32>) -> Matrix4x4<f32> {
@@ -1,11 +1,17 @@
 use ggmath::prelude::*;
 
+use crate::engine::FrameInfo;
+
 use super::{
     program::UniformValue,
     render_camera::RenderCamera,
     shader_gen::shader_parameters::{
-        PARAMETER_MODEL_MATRIX, PARAMETER_PROJECTION_MATRIX, PARAMETER_VIEW_MATRIX,
+        PARAMETER_CAMERA_POSITION, PARAMETER_DELTA_TIME, PARAMETER_FRAME_INDEX,
+        PARAMETER_INVERSE_PROJECTION_MATRIX, PARAMETER_INVERSE_VIEW_MATRIX, PARAMETER_MODEL_MATRIX,
+        PARAMETER_MODEL_VIEW_PROJECTION_MATRIX, PARAMETER_NORMAL_MATRIX,
+        PARAMETER_PROJECTION_MATRIX, PARAMETER_RESOLUTION, PARAMETER_TIME, PARAMETER_VIEW_MATRIX,
     },
+    texture::TextureView,
 };
 
 /// Represents a render parameter for the render pipeline.
@@ -63,10 +69,25 @@ impl RenderParameters {
             .map(|p| p.value.as_ref())
     }
 
+    /// Get the handle of the first texture parameter.
+    /// Used to group draws by texture.
+    pub fn texture_handle(&self) -> Option<u32> {
+        self.parameters.iter().find_map(|p| {
+            p.value
+                .as_any()
+                .downcast_ref::<TextureView>()
+                .map(TextureView::handle)
+        })
+    }
+
     /// Set the view matrix.
     /// The actual name of the parameter is `ShaderParameter::PARAMETER_VIEW_MATRIX`.
+    /// This also sets the inverse view matrix and the model-view-projection matrix.
+    /// The inverse is the identity matrix if the view matrix is not invertible.
     pub fn set_view_matrix(&mut self, matrix: Matrix4x4<f32>) {
+        self.set(PARAMETER_INVERSE_VIEW_MATRIX, invert_matrix(&matrix));
         self.set(PARAMETER_VIEW_MATRIX, matrix);
+        self.update_model_view_projection_matrix();
     }
 
     /// Get the view matrix.
@@ -78,8 +99,12 @@ impl RenderParameters {
     /// Set the projection matrix.
     /// The actual name of the parameter is `ShaderParameter::PARAMETER_PROJECTION_MATRIX`.
     /// You can also use `RenderParameters::set_camera` to set the view and projection matrices from a `RenderCamera`.
+    /// This also sets the inverse projection matrix and the model-view-projection matrix.
+    /// The inverse is the identity matrix if the projection matrix is not invertible.
     pub fn set_projection_matrix(&mut self, matrix: Matrix4x4<f32>) {
+        self.set(PARAMETER_INVERSE_PROJECTION_MATRIX, invert_matrix(&matrix));
         self.set(PARAMETER_PROJECTION_MATRIX, matrix);
+        self.update_model_view_projection_matrix();
     }
 
     /// Get the projection matrix.
@@ -92,8 +117,12 @@ impl RenderParameters {
     /// The actual name of the parameter is `ShaderParameter::PARAMETER_MODEL_MATRIX`.
     /// This is the matrix that transforms vertices from model space to world space.
     /// You can also use `RenderParameters::set_camera` to set the view and projection matrices from a `RenderCamera``.
+    /// This also sets the normal matrix and the model-view-projection matrix.
+    /// The normal matrix is the identity matrix if the model matrix is not invertible.
     pub fn set_model_matrix(&mut self, matrix: Matrix4x4<f32>) {
+        self.set(PARAMETER_NORMAL_MATRIX, normal_matrix(&matrix));
         self.set(PARAMETER_MODEL_MATRIX, matrix);
+        self.update_model_view_projection_matrix();
     }
 
     /// Get the model matrix.
@@ -102,10 +131,113 @@ impl RenderParameters {
             .map(|v| v.as_any().downcast_ref().unwrap())
     }
 
-    /// Set the view and projection matrices from a camera.
+    /// Get the inverse view matrix.
+    pub fn get_inverse_view_matrix(&self) -> Option<&Matrix4x4<f32>> {
+        self.get(PARAMETER_INVERSE_VIEW_MATRIX)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get the inverse projection matrix.
+    pub fn get_inverse_projection_matrix(&self) -> Option<&Matrix4x4<f32>> {
+        self.get(PARAMETER_INVERSE_PROJECTION_MATRIX)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get the model-view-projection matrix.
+    pub fn get_model_view_projection_matrix(&self) -> Option<&Matrix4x4<f32>> {
+        self.get(PARAMETER_MODEL_VIEW_PROJECTION_MATRIX)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get the normal matrix, as an array of columns.
+    pub fn get_normal_matrix(&self) -> Option<&[[f32; 3]; 3]> {
+        self.get(PARAMETER_NORMAL_MATRIX)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Set the model-view-projection matrix once the model, view and projection are all set.
+    fn update_model_view_projection_matrix(&mut self) {
+        let matrix = match (
+            self.get_model_matrix(),
+            self.get_view_matrix(),
+            self.get_projection_matrix(),
+        ) {
+            (Some(model), Some(view), Some(projection)) => *projection * *view * *model,
+            _ => return,
+        };
+        self.set(PARAMETER_MODEL_VIEW_PROJECTION_MATRIX, matrix);
+    }
+
+    /// Set the camera position.
+    /// The actual name of the parameter is `ShaderParameter::PARAMETER_CAMERA_POSITION`.
+    pub fn set_camera_position(&mut self, position: Vector3<f32>) {
+        self.set(PARAMETER_CAMERA_POSITION, position);
+    }
+
+    /// Get the camera position.
+    pub fn get_camera_position(&self) -> Option<&Vector3<f32>> {
+        self.get(PARAMETER_CAMERA_POSITION)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Set the view and projection matrices and the camera position from a camera.
     pub fn set_camera(&mut self, viewport_size: Vector2<f32>, camera: &RenderCamera) {
         self.set_view_matrix(camera.get_view_matrix());
         self.set_projection_matrix(camera.get_projection_matrix(viewport_size));
+        self.set_camera_position(camera.orientation().position());
+    }
+
+    /// Set the viewport resolution in pixels.
+    /// The actual name of the parameter is `ShaderParameter::PARAMETER_RESOLUTION`.
+    pub fn set_resolution(&mut self, resolution: Vector2<f32>) {
+        self.set(PARAMETER_RESOLUTION, resolution);
+    }
+
+    /// Get the viewport resolution in pixels.
+    pub fn get_resolution(&self) -> Option<&Vector2<f32>> {
+        self.get(PARAMETER_RESOLUTION)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Set the elapsed time, delta time and frame index from a `FrameInfo`.
+    /// The frame index wraps around when it does not fit in an `i32`.
+    pub fn set_frame_info(&mut self, frame_info: &FrameInfo) {
+        self.set(PARAMETER_TIME, frame_info.elapsed_time);
+        self.set(PARAMETER_DELTA_TIME, frame_info.delta_time);
+        self.set(PARAMETER_FRAME_INDEX, frame_info.frame_index as i32);
+    }
+
+    /// Get the elapsed time in seconds.
+    pub fn get_time(&self) -> Option<&f32> {
+        self.get(PARAMETER_TIME)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get the delta time in seconds.
+    pub fn get_delta_time(&self) -> Option<&f32> {
+        self.get(PARAMETER_DELTA_TIME)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Get the frame index.
+    pub fn get_frame_index(&self) -> Option<&i32> {
+        self.get(PARAMETER_FRAME_INDEX)
+            .map(|v| v.as_any().downcast_ref().unwrap())
+    }
+
+    /// Set all built-in parameters that the renderer provides automatically.
+    /// This sets the model matrix, camera parameters, resolution and frame timing.
+    pub fn set_builtins(
+        &mut self,
+        model_matrix: Matrix4x4<f32>,
+        viewport_size: Vector2<f32>,
+        camera: &RenderCamera,
+        frame_info: &FrameInfo,
+    ) {
+        self.set_model_matrix(model_matrix);
+        self.set_camera(viewport_size, camera);
+        self.set_resolution(viewport_size);
+        self.set_frame_info(frame_info);
     }
 
     /// Set the render parameter by name and return the RenderParameters instance for chaining.
@@ -137,4 +269,135 @@ impl RenderParameters {
         self.set_camera(viewport_size, camera);
         self
     }
+
+    /// Set the viewport resolution and return the RenderParameters instance for chaining.
+    pub fn with_resolution(mut self, resolution: Vector2<f32>) -> Self {
+        self.set_resolution(resolution);
+        self
+    }
+
+    /// Set the frame timing and return the RenderParameters instance for chaining.
+    pub fn with_frame_info(mut self, frame_info: &FrameInfo) -> Self {
+        self.set_frame_info(frame_info);
+        self
+    }
+}
+
+/// Get the components of a matrix in column-major order.
+fn matrix_components(matrix: &Matrix4x4<f32>) -> [f32; 16] {
+    let mut components = [0.0; 16];
+    components.copy_from_slice(unsafe { std::slice::from_raw_parts(matrix.as_ptr(), 16) });
+    components
+}
+
+/// Invert a matrix with its cofactors.
+/// Returns the identity matrix if the matrix is not invertible, such as a projection with a
+/// zero-sized viewport or a scale of zero. This is logged in debug builds.
+fn invert_matrix(matrix: &Matrix4x4<f32>) -> Matrix4x4<f32> {
+    let m = matrix_components(matrix);
+    let mut inv = [0.0; 16];
+
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+        + m[9] * m[7] * m[14]
+        + m[13] * m[6] * m[11]
+        - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+        - m[8] * m[7] * m[14]
+        - m[12] * m[6] * m[11]
+        + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+        + m[8] * m[7] * m[13]
+        + m[12] * m[5] * m[11]
+        - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+        - m[8] * m[6] * m[13]
+        - m[12] * m[5] * m[10]
+        + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+        - m[9] * m[3] * m[14]
+        - m[13] * m[2] * m[11]
+        + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+        + m[8] * m[3] * m[14]
+        + m[12] * m[2] * m[11]
+        - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+        - m[8] * m[3] * m[13]
+        - m[12] * m[1] * m[11]
+        + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+        + m[8] * m[2] * m[13]
+        + m[12] * m[1] * m[10]
+        - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+        + m[5] * m[3] * m[14]
+        + m[13] * m[2] * m[7]
+        - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+        - m[4] * m[3] * m[14]
+        - m[12] * m[2] * m[7]
+        + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+        + m[4] * m[3] * m[13]
+        + m[12] * m[1] * m[7]
+        - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+        - m[4] * m[2] * m[13]
+        - m[12] * m[1] * m[6]
+        + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+        - m[5] * m[3] * m[10]
+        - m[9] * m[2] * m[7]
+        + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+        + m[4] * m[3] * m[10]
+        + m[8] * m[2] * m[7]
+        - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+        - m[4] * m[3] * m[9]
+        - m[8] * m[1] * m[7]
+        + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+        + m[4] * m[2] * m[9]
+        + m[8] * m[1] * m[6]
+        - m[8] * m[2] * m[5];
+
+    let determinant = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if determinant == 0.0 {
+        #[cfg(debug_assertions)]
+        eprintln!("Matrix is not invertible, using the identity matrix as its inverse");
+        return Matrix4x4::identity();
+    }
+
+    // Build the matrix from the scaled cofactors, which are in column-major order.
+    let inv = inv.map(|cofactor| cofactor / determinant);
+    let column = |c: usize| vector!(inv[c * 4], inv[c * 4 + 1], inv[c * 4 + 2], inv[c * 4 + 3]);
+    Matrix4x4::from_columns([column(0), column(1), column(2), column(3)])
+}
+
+/// Get the inverse transpose of the 3x3 part of a matrix, as an array of columns.
+/// The columns are the cross products of the matrix's other two columns, divided by the
+/// determinant. Returns the identity matrix if the 3x3 part is not invertible.
+fn normal_matrix(matrix: &Matrix4x4<f32>) -> [[f32; 3]; 3] {
+    let m = matrix_components(matrix);
+    let x = [m[0], m[1], m[2]];
+    let y = [m[4], m[5], m[6]];
+    let z = [m[8], m[9], m[10]];
+
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    };
+    let yz = cross(y, z);
+    let zx = cross(z, x);
+    let xy = cross(x, y);
+
+    let determinant = x[0] * yz[0] + x[1] * yz[1] + x[2] * yz[2];
+    if determinant == 0.0 {
+        return [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    }
+    [yz, zx, xy].map(|column| column.map(|component| component / determinant))
 }
@@ -0,0 +1,259 @@
+use std::{
+    cmp::Ordering,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// Describes where a draw goes in a `RenderQueue`.
+/// Draws are sorted by layer, then by order, then opaque draws come before transparent ones.
+/// Opaque background draws, such as a skybox, come after the other opaque draws, so hidden
+/// parts of the background are rejected by the depth test.
+/// Opaque draws are grouped by program, material and texture to minimize state changes,
+/// and drawn front-to-back within a group. Transparent draws are drawn back-to-front.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SortKey {
+    layer: i32,
+    order: i32,
+    transparent: bool,
+    background: bool,
+    depth: f32,
+    program: u32,
+    material: u64,
+    texture: u32,
+}
+
+impl SortKey {
+    /// Create a new sort key for an opaque draw in layer 0 with order 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the render layer. Lower layers are drawn first.
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Set the sort order within the layer. Lower orders are drawn first.
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Set whether the draw blends with what is behind it.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Set whether the draw is an opaque background behind every other opaque draw.
+    pub fn with_background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Set the distance from the camera along its view direction.
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the handle of the program used by the draw.
+    pub fn with_program(mut self, program: u32) -> Self {
+        self.program = program;
+        self
+    }
+
+    /// Set the material used by the draw, such as its cache handle.
+    pub fn with_material(mut self, material: &impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        material.hash(&mut hasher);
+        self.material = hasher.finish();
+        self
+    }
+
+    /// Set the handle of the texture used by the draw.
+    pub fn with_texture(mut self, texture: u32) -> Self {
+        self.texture = texture;
+        self
+    }
+
+    /// Get the render layer.
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Get the sort order within the layer.
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+
+    /// Check if the draw blends with what is behind it.
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Check if the draw is an opaque background.
+    pub fn is_background(&self) -> bool {
+        self.background
+    }
+
+    /// Get the distance from the camera along its view direction.
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// Compare two keys in the order their draws are submitted.
+    pub fn draw_order(&self, other: &Self) -> Ordering {
+        let state = || {
+            self.program
+                .cmp(&other.program)
+                .then(self.material.cmp(&other.material))
+                .then(self.texture.cmp(&other.texture))
+        };
+
+        self.layer
+            .cmp(&other.layer)
+            .then(self.order.cmp(&other.order))
+            .then(self.transparent.cmp(&other.transparent))
+            .then_with(|| {
+                if self.transparent {
+                    // Back-to-front, so blending sees what is behind.
+                    other.depth.total_cmp(&self.depth).then_with(state)
+                } else {
+                    // Draw the background last, then group by state, then front-to-back to
+                    // reject hidden fragments early.
+                    self.background
+                        .cmp(&other.background)
+                        .then_with(state)
+                        .then(self.depth.total_cmp(&other.depth))
+                }
+            })
+    }
+}
+
+/// A list of draws that are sorted by their `SortKey` before being submitted.
+/// Draws with equal keys keep the order they were pushed in.
+pub struct RenderQueue<T> {
+    items: Vec<(SortKey, T)>,
+}
+
+impl<T> RenderQueue<T> {
+    /// Create a new empty queue.
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Add a draw to the queue.
+    pub fn push(&mut self, key: SortKey, item: T) {
+        self.items.push((key, item));
+    }
+
+    /// Sort the draws in the order they should be submitted.
+    pub fn sort(&mut self) {
+        self.items.sort_by(|(a, _), (b, _)| a.draw_order(b));
+    }
+
+    /// Get the draws with their keys, in the order they were pushed or sorted.
+    pub fn items(&self) -> &[(SortKey, T)] {
+        &self.items
+    }
+
+    /// Remove all draws, keeping the queue's storage.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Get the number of draws.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check if the queue has no draws.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<T> Default for RenderQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sort the keys in draw order and return the indices they were pushed at.
+    fn sorted(keys: &[SortKey]) -> Vec<usize> {
+        let mut queue = RenderQueue::new();
+        for (i, key) in keys.iter().enumerate() {
+            queue.push(*key, i);
+        }
+        queue.sort();
+        queue.items().iter().map(|(_, i)| *i).collect()
+    }
+
+    #[test]
+    fn layer_and_order_come_first() {
+        let keys = [
+            SortKey::new().with_layer(1),
+            SortKey::new().with_order(1).with_transparent(true),
+            SortKey::new().with_layer(-1).with_depth(100.0),
+            SortKey::new().with_order(-1),
+        ];
+        assert_eq!(sorted(&keys), [2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn opaque_draws_come_before_transparent_ones() {
+        let keys = [
+            SortKey::new().with_transparent(true).with_depth(1.0),
+            SortKey::new().with_depth(10.0),
+        ];
+        assert_eq!(sorted(&keys), [1, 0]);
+    }
+
+    #[test]
+    fn opaque_draws_group_by_state_then_go_front_to_back() {
+        let keys = [
+            SortKey::new().with_program(2).with_depth(1.0),
+            SortKey::new().with_program(1).with_depth(5.0),
+            SortKey::new().with_program(1).with_depth(2.0),
+            SortKey::new()
+                .with_program(1)
+                .with_texture(1)
+                .with_depth(0.0),
+        ];
+        assert_eq!(sorted(&keys), [2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn transparent_draws_go_back_to_front() {
+        let keys = [
+            SortKey::new().with_transparent(true).with_depth(1.0),
+            SortKey::new().with_transparent(true).with_depth(5.0),
+            SortKey::new()
+                .with_transparent(true)
+                .with_program(1)
+                .with_depth(3.0),
+        ];
+        assert_eq!(sorted(&keys), [1, 2, 0]);
+    }
+
+    #[test]
+    fn backgrounds_come_after_other_opaque_draws() {
+        let keys = [
+            SortKey::new().with_background(true),
+            SortKey::new().with_program(5).with_depth(100.0),
+            SortKey::new().with_transparent(true),
+        ];
+        assert_eq!(sorted(&keys), [1, 0, 2]);
+    }
+
+    #[test]
+    fn equal_keys_keep_their_push_order() {
+        let keys = [SortKey::new(); 3];
+        assert_eq!(sorted(&keys), [0, 1, 2]);
+    }
+}
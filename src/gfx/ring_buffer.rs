@@ -0,0 +1,162 @@
+use anyhow::Result;
+use gl::types::GLsync;
+
+use super::gl_state::GlState;
+
+/// A persistently mapped buffer split into segments, for streaming data every frame.
+///
+/// Each frame writes into the next segment while the GPU may still be reading the previous
+/// ones. A fence is placed after each frame's draws, and a segment is only reused once its
+/// fence has been signaled, so writes never race with reads and never stall on orphaning.
+pub struct RingBuffer<T: Copy> {
+    handle: u32,
+    mapping: *mut T,
+    segment_length: usize,
+    fences: Vec<Option<GLsync>>,
+    segment: usize,
+    written: usize,
+}
+
+impl<T: Copy> !Send for RingBuffer<T> {}
+impl<T: Copy> !Sync for RingBuffer<T> {}
+
+impl<T: Copy> RingBuffer<T> {
+    /// Create a new ring buffer with `segment_count` segments of `segment_length` elements.
+    /// Use at least as many segments as frames the GPU can be behind (usually 3).
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new(segment_length: usize, segment_count: usize) -> Result<Self> {
+        if segment_length == 0 || segment_count == 0 {
+            anyhow::bail!("Ring buffer segment length and count must not be zero");
+        }
+
+        let byte_length = (std::mem::size_of::<T>() * segment_length * segment_count) as isize;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+        unsafe {
+            // Create immutable storage that stays mapped for the buffer's lifetime.
+            let mut handle = 0;
+            gl::CreateBuffers(1, &mut handle);
+            gl::NamedBufferStorage(handle, byte_length, std::ptr::null(), flags);
+            let mapping = gl::MapNamedBufferRange(handle, 0, byte_length, flags) as *mut T;
+            if mapping.is_null() {
+                gl::DeleteBuffers(1, &handle);
+                anyhow::bail!("Failed to map ring buffer");
+            }
+
+            // Set the buffer's label
+            let label = format!("RingBuffer<{}>", std::any::type_name::<T>());
+            gl::ObjectLabel(
+                gl::BUFFER,
+                handle,
+                label.len() as i32,
+                label.as_ptr() as *const _,
+            );
+
+            Ok(Self {
+                handle,
+                mapping,
+                segment_length,
+                fences: vec![None; segment_count],
+                segment: 0,
+                written: 0,
+            })
+        }
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Get the number of elements in each segment.
+    pub fn segment_length(&self) -> usize {
+        self.segment_length
+    }
+
+    /// Get the number of segments.
+    pub fn segment_count(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Get the number of elements in the whole buffer.
+    pub fn capacity(&self) -> usize {
+        self.segment_length * self.segment_count()
+    }
+
+    /// Get the element offset of the current segment within the buffer.
+    pub fn segment_offset(&self) -> usize {
+        self.segment * self.segment_length
+    }
+
+    /// Get the number of elements written to the current segment this frame.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Append elements to the current segment.
+    /// Returns the element offset of the first written element within the buffer,
+    /// for use as a base vertex or as the offset of an instanced draw from this buffer.
+    /// Returns an error if the segment does not have room for `data`.
+    pub fn write(&mut self, data: &[T]) -> Result<usize> {
+        if self.written + data.len() > self.segment_length {
+            anyhow::bail!(
+                "Ring buffer segment of {} elements cannot fit {} more elements after {}",
+                self.segment_length,
+                data.len(),
+                self.written
+            );
+        }
+
+        let offset = self.segment_offset() + self.written;
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.mapping.add(offset), data.len());
+        }
+        self.written += data.len();
+
+        Ok(offset)
+    }
+
+    /// Finish the current frame and move to the next segment.
+    /// Call this after the draws that read this frame's data have been issued.
+    /// This blocks only if the GPU is still reading the next segment from an earlier frame.
+    pub fn next_frame(&mut self) -> Result<()> {
+        unsafe {
+            // Fence the draws that read the current segment.
+            if let Some(fence) = self.fences[self.segment].take() {
+                gl::DeleteSync(fence);
+            }
+            self.fences[self.segment] = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+
+            // Move to the next segment and wait until the GPU has finished reading it.
+            self.segment = (self.segment + 1) % self.fences.len();
+            self.written = 0;
+            if let Some(fence) = self.fences[self.segment].take() {
+                let result = loop {
+                    match gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000) {
+                        gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => break Ok(()),
+                        gl::TIMEOUT_EXPIRED => continue,
+                        _ => break Err(anyhow::anyhow!("Failed to wait for ring buffer segment")),
+                    }
+                };
+                gl::DeleteSync(fence);
+                result?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Copy> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            for fence in self.fences.iter().flatten() {
+                gl::DeleteSync(*fence);
+            }
+            gl::UnmapNamedBuffer(self.handle);
+            gl::DeleteBuffers(1, &self.handle);
+        }
+        GlState::__forget_buffer(self.handle);
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use ggmath::prelude::*;
+
+use super::texture_format::TextureFormat;
+
+thread_local! {
+    /// GL sampler objects created for `TextureView`s with their own sampler settings.
+    static SAMPLER_OBJECTS: RefCell<HashMap<SamplerSettings, u32>> = RefCell::new(HashMap::new());
+}
+
+/// Represents how texels are filtered when a texture is magnified or minified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    /// Use the nearest texel.
+    Nearest,
+    /// Interpolate between the nearest texels.
+    Linear,
+}
+
+/// Represents how LODs are selected when a texture is minified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MipmapMode {
+    /// Only LOD 0 is sampled.
+    None,
+    /// Use the nearest LOD.
+    Nearest,
+    /// Interpolate between the nearest LODs.
+    Linear,
+}
+
+/// Represents how texture coordinates outside of 0.0 to 1.0 are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    /// The texture repeats.
+    Repeat,
+    /// The texture repeats, mirrored every other repetition.
+    MirroredRepeat,
+    /// The coordinates are clamped to the edge texels.
+    ClampToEdge,
+    /// Coordinates outside the texture use the border color.
+    ClampToBorder,
+    /// The texture is mirrored once, then clamped to the edge texels.
+    MirrorClampToEdge,
+}
+
+impl WrapMode {
+    /// Get the GL enum.
+    const fn to_gl_enum(self) -> u32 {
+        match self {
+            WrapMode::Repeat => gl::REPEAT,
+            WrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+            WrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            WrapMode::ClampToBorder => gl::CLAMP_TO_BORDER,
+            WrapMode::MirrorClampToEdge => gl::MIRROR_CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// Describes how a texture is sampled.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerSettings {
+    /// The filter used when the texture is magnified.
+    pub mag_filter: FilterMode,
+    /// The filter used when the texture is minified.
+    pub min_filter: FilterMode,
+    /// How LODs are selected when the texture is minified.
+    pub mipmap_mode: MipmapMode,
+    /// How horizontal texture coordinates outside of 0.0 to 1.0 are handled.
+    pub wrap_u: WrapMode,
+    /// How vertical texture coordinates outside of 0.0 to 1.0 are handled.
+    pub wrap_v: WrapMode,
+    /// How depth texture coordinates outside of 0.0 to 1.0 are handled.
+    pub wrap_w: WrapMode,
+    /// The maximum anisotropy, where 1.0 disables anisotropic filtering.
+    /// This is clamped to the maximum supported by the hardware.
+    pub max_anisotropy: f32,
+    /// The color used outside the texture with `WrapMode::ClampToBorder`.
+    pub border_color: Vector4<f32>,
+    /// The bias added to the selected LOD.
+    pub lod_bias: f32,
+}
+
+impl Default for SamplerSettings {
+    /// Trilinear filtering with repeating texture coordinates.
+    fn default() -> Self {
+        Self {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_mode: MipmapMode::Linear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            wrap_w: WrapMode::Repeat,
+            max_anisotropy: 1.0,
+            border_color: Vector::zero(),
+            lod_bias: 0.0,
+        }
+    }
+}
+
+impl SamplerSettings {
+    /// Create the default sampler settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create sampler settings for pixel art and glyph atlases:
+    /// nearest filtering without mipmaps, clamped to the edges.
+    pub fn pixelated() -> Self {
+        Self::new()
+            .with_filter(FilterMode::Nearest)
+            .with_mipmap_mode(MipmapMode::None)
+            .with_wrap(WrapMode::ClampToEdge)
+    }
+
+    /// Set both the magnification and minification filters.
+    pub fn with_filter(mut self, filter: FilterMode) -> Self {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self
+    }
+
+    /// Set how LODs are selected when the texture is minified.
+    pub fn with_mipmap_mode(mut self, mipmap_mode: MipmapMode) -> Self {
+        self.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    /// Set how texture coordinates outside of 0.0 to 1.0 are handled on all axes.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap_u = wrap;
+        self.wrap_v = wrap;
+        self.wrap_w = wrap;
+        self
+    }
+
+    /// Set the maximum anisotropy.
+    pub fn with_anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    /// Set the border color used with `WrapMode::ClampToBorder`.
+    pub fn with_border_color(mut self, border_color: Vector4<f32>) -> Self {
+        self.border_color = border_color;
+        self
+    }
+
+    /// Set the bias added to the selected LOD.
+    pub fn with_lod_bias(mut self, lod_bias: f32) -> Self {
+        self.lod_bias = lod_bias;
+        self
+    }
+
+    /// Get the GL minification filter enum.
+    const fn min_filter_gl_enum(&self) -> u32 {
+        match (self.min_filter, self.mipmap_mode) {
+            (FilterMode::Nearest, MipmapMode::None) => gl::NEAREST,
+            (FilterMode::Linear, MipmapMode::None) => gl::LINEAR,
+            (FilterMode::Nearest, MipmapMode::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+            (FilterMode::Linear, MipmapMode::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+            (FilterMode::Nearest, MipmapMode::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+            (FilterMode::Linear, MipmapMode::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    /// Get the GL magnification filter enum.
+    const fn mag_filter_gl_enum(&self) -> u32 {
+        match self.mag_filter {
+            FilterMode::Nearest => gl::NEAREST,
+            FilterMode::Linear => gl::LINEAR,
+        }
+    }
+
+    /// Get the float fields as bits, for comparing and hashing.
+    fn float_bits(&self) -> [u32; 6] {
+        [
+            self.max_anisotropy.to_bits(),
+            self.border_color.x().to_bits(),
+            self.border_color.y().to_bits(),
+            self.border_color.z().to_bits(),
+            self.border_color.w().to_bits(),
+            self.lod_bias.to_bits(),
+        ]
+    }
+
+    /// Apply these settings to a texture object.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __apply_to_texture(&self, texture_handle: u32) {
+        unsafe {
+            self.__apply(
+                |parameter, value| gl::TextureParameteri(texture_handle, parameter, value),
+                |parameter, value| gl::TextureParameterf(texture_handle, parameter, value),
+                |parameter, value| gl::TextureParameterfv(texture_handle, parameter, value),
+            );
+        }
+    }
+
+    /// Get a GL sampler object with these settings, creating it if it does not exist yet.
+    /// Sampler objects are shared between all views with the same settings.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __sampler_object(&self) -> u32 {
+        SAMPLER_OBJECTS.with(|samplers| {
+            *samplers
+                .borrow_mut()
+                .entry(*self)
+                .or_insert_with(|| unsafe {
+                    let mut handle = 0;
+                    gl::CreateSamplers(1, &mut handle);
+                    self.__apply(
+                        |parameter, value| gl::SamplerParameteri(handle, parameter, value),
+                        |parameter, value| gl::SamplerParameterf(handle, parameter, value),
+                        |parameter, value| gl::SamplerParameterfv(handle, parameter, value),
+                    );
+                    handle
+                })
+        })
+    }
+
+    /// Set each GL parameter with the given functions.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    unsafe fn __apply(
+        &self,
+        set_int: impl Fn(u32, i32),
+        set_float: impl Fn(u32, f32),
+        set_floats: impl Fn(u32, *const f32),
+    ) {
+        // Filtering.
+        set_int(gl::TEXTURE_MIN_FILTER, self.min_filter_gl_enum() as i32);
+        set_int(gl::TEXTURE_MAG_FILTER, self.mag_filter_gl_enum() as i32);
+
+        // Wrapping.
+        set_int(gl::TEXTURE_WRAP_S, self.wrap_u.to_gl_enum() as i32);
+        set_int(gl::TEXTURE_WRAP_T, self.wrap_v.to_gl_enum() as i32);
+        set_int(gl::TEXTURE_WRAP_R, self.wrap_w.to_gl_enum() as i32);
+
+        // Anisotropy, clamped to the hardware limit.
+        let mut max_supported = 1.0;
+        unsafe {
+            gl::GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_supported);
+        }
+        set_float(
+            gl::TEXTURE_MAX_ANISOTROPY,
+            self.max_anisotropy.clamp(1.0, max_supported),
+        );
+
+        // Border color and LOD bias.
+        let border_color = [
+            self.border_color.x(),
+            self.border_color.y(),
+            self.border_color.z(),
+            self.border_color.w(),
+        ];
+        set_floats(gl::TEXTURE_BORDER_COLOR, border_color.as_ptr());
+        set_float(gl::TEXTURE_LOD_BIAS, self.lod_bias);
+    }
+}
+
+impl PartialEq for SamplerSettings {
+    fn eq(&self, other: &Self) -> bool {
+        self.mag_filter == other.mag_filter
+            && self.min_filter == other.min_filter
+            && self.mipmap_mode == other.mipmap_mode
+            && self.wrap_u == other.wrap_u
+            && self.wrap_v == other.wrap_v
+            && self.wrap_w == other.wrap_w
+            && self.float_bits() == other.float_bits()
+    }
+}
+
+impl Eq for SamplerSettings {}
+
+impl Hash for SamplerSettings {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.mag_filter.hash(state);
+        self.min_filter.hash(state);
+        self.mipmap_mode.hash(state);
+        self.wrap_u.hash(state);
+        self.wrap_v.hash(state);
+        self.wrap_w.hash(state);
+        self.float_bits().hash(state);
+    }
+}
+
+/// Represents how the LODs of a texture are generated when it is loaded from an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mipmaps {
+    /// Only the provided LODs are used.
+    None,
+    /// The full mip chain is generated on the GPU with `glGenerateTextureMipmap`.
+    Gpu,
+    /// The full mip chain is generated on the CPU by downsampling with the given filter.
+    Cpu(image::imageops::FilterType),
+}
+
+impl Mipmaps {
+    /// Get the number of LODs in a full mip chain for the given size.
+    pub fn full_chain_length(size: Vector2<u32>) -> usize {
+        let largest = size.x().max(size.y()).max(1);
+        (u32::BITS - largest.leading_zeros()) as usize
+    }
+
+    /// Downsample an image into a full mip chain on the CPU, starting with the image itself.
+    pub fn generate_cpu(
+        image: image::DynamicImage,
+        filter: image::imageops::FilterType,
+    ) -> Vec<image::DynamicImage> {
+        let size = vector!(image.width(), image.height());
+        let lod_count = Self::full_chain_length(size);
+        let mut lods = Vec::with_capacity(lod_count);
+        lods.push(image);
+
+        for _ in 1..lod_count {
+            let previous = lods.last().unwrap();
+            let width = (previous.width() / 2).max(1);
+            let height = (previous.height() / 2).max(1);
+            let lod = previous.resize_exact(width, height, filter);
+            lods.push(lod);
+        }
+
+        lods
+    }
+}
+
+/// Options used when creating a texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureOptions {
+    /// The format the texels are stored in.
+    pub format: TextureFormat,
+    /// How the LODs are generated.
+    pub mipmaps: Mipmaps,
+    /// How the texture is sampled when a view does not override it.
+    pub sampler: SamplerSettings,
+}
+
+impl Default for TextureOptions {
+    /// RGBA8 texels, GPU-generated mipmaps and the default sampler settings.
+    fn default() -> Self {
+        Self {
+            format: TextureFormat::Rgba8,
+            mipmaps: Mipmaps::Gpu,
+            sampler: SamplerSettings::default(),
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Create the default texture options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the format the texels are stored in.
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set how the LODs are generated.
+    pub fn with_mipmaps(mut self, mipmaps: Mipmaps) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Set how the texture is sampled.
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = sampler;
+        self
+    }
+}
@@ -1,5 +1,6 @@
 pub mod prelude;
 pub mod shader_expression;
+pub mod shader_functions;
 pub mod shader_inputs;
 pub mod shader_outputs;
 pub mod shader_parameters;
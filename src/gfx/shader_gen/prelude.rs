@@ -2,3 +2,4 @@ pub use super::shader_expression::*;
 pub use super::shader_inputs::ShaderInputs;
 pub use super::shader_outputs::ShaderOutputs;
 pub use super::shader_parameters::ShaderParameters;
+pub use crate::noise::NoiseType;
@@ -3,8 +3,12 @@ use std::{cell::RefCell, fmt::Display};
 use anyhow::Result;
 use ggmath::prelude::*;
 
+use crate::noise::NoiseType;
+
 use super::{
-    shader_inputs::SHADER_INPUT_PREFIX, shader_parameters::SHADER_UNIFORM_PREFIX,
+    shader_functions::{ShaderFunction, ShaderFunctions},
+    shader_inputs::SHADER_INPUT_PREFIX,
+    shader_parameters::SHADER_UNIFORM_PREFIX,
     shader_type::ShaderType,
 };
 
@@ -15,6 +19,8 @@ pub enum ShaderOperation {
     Uniform(String, ShaderType),
     I32(i32),
     F32(f32),
+    DrawModelMatrix,
+    DrawParameters,
     Vec2(ShaderExpression, ShaderExpression),
     Vec3(ShaderExpression, ShaderExpression, ShaderExpression),
     Vec4(
@@ -36,6 +42,7 @@ pub enum ShaderOperation {
     Floor(ShaderExpression),
     Ceil(ShaderExpression),
     Round(ShaderExpression),
+    ToFloat(ShaderExpression),
     Min(ShaderExpression, ShaderExpression),
     Max(ShaderExpression, ShaderExpression),
     Clamp(ShaderExpression, ShaderExpression, ShaderExpression),
@@ -45,6 +52,81 @@ pub enum ShaderOperation {
     Length(ShaderExpression),
     Normalized(ShaderExpression),
     Sample(ShaderExpression, ShaderExpression, ShaderExpression),
+    SampleAutoLod(ShaderExpression, ShaderExpression),
+    SampleCube(ShaderExpression, ShaderExpression),
+    SampleLayer(ShaderExpression, ShaderExpression, ShaderExpression),
+    Sample3D(ShaderExpression, ShaderExpression),
+    ViewLayer(ShaderExpression),
+    Inverse(ShaderExpression),
+    Transpose(ShaderExpression),
+    Noise(NoiseType, ShaderExpression),
+    Fbm(
+        NoiseType,
+        ShaderExpression,
+        ShaderExpression,
+        ShaderExpression,
+        ShaderExpression,
+    ),
+}
+
+impl ShaderOperation {
+    /// Calls `f` for each expression this operation directly depends on.
+    pub fn for_each_child(&self, mut f: impl FnMut(&ShaderExpression)) {
+        match self {
+            ShaderOperation::Input(_, _)
+            | ShaderOperation::Uniform(_, _)
+            | ShaderOperation::I32(_)
+            | ShaderOperation::F32(_)
+            | ShaderOperation::DrawModelMatrix
+            | ShaderOperation::DrawParameters => {}
+            ShaderOperation::Neg(a)
+            | ShaderOperation::Abs(a)
+            | ShaderOperation::Sign(a)
+            | ShaderOperation::Floor(a)
+            | ShaderOperation::Ceil(a)
+            | ShaderOperation::Round(a)
+            | ShaderOperation::ToFloat(a)
+            | ShaderOperation::Length(a)
+            | ShaderOperation::Normalized(a)
+            | ShaderOperation::Inverse(a)
+            | ShaderOperation::Transpose(a)
+            | ShaderOperation::ViewLayer(a)
+            | ShaderOperation::Noise(_, a) => f(a),
+            ShaderOperation::Vec2(a, b)
+            | ShaderOperation::Append(a, b)
+            | ShaderOperation::Add(a, b)
+            | ShaderOperation::Sub(a, b)
+            | ShaderOperation::Mul(a, b)
+            | ShaderOperation::Div(a, b)
+            | ShaderOperation::Pow(a, b)
+            | ShaderOperation::Rem(a, b)
+            | ShaderOperation::Min(a, b)
+            | ShaderOperation::Max(a, b)
+            | ShaderOperation::Dot(a, b)
+            | ShaderOperation::Cross(a, b)
+            | ShaderOperation::SampleAutoLod(a, b)
+            | ShaderOperation::SampleCube(a, b)
+            | ShaderOperation::Sample3D(a, b) => {
+                f(a);
+                f(b);
+            }
+            ShaderOperation::Vec3(a, b, c)
+            | ShaderOperation::Clamp(a, b, c)
+            | ShaderOperation::Mix(a, b, c)
+            | ShaderOperation::Sample(a, b, c)
+            | ShaderOperation::SampleLayer(a, b, c) => {
+                f(a);
+                f(b);
+                f(c);
+            }
+            ShaderOperation::Vec4(a, b, c, d) | ShaderOperation::Fbm(_, a, b, c, d) => {
+                f(a);
+                f(b);
+                f(c);
+                f(d);
+            }
+        }
+    }
 }
 
 /// Represents a shader expression.
@@ -68,6 +150,8 @@ impl ShaderExpression {
             ShaderOperation::Uniform(_, value_type) => *value_type,
             ShaderOperation::I32(_) => ShaderType::I32,
             ShaderOperation::F32(_) => ShaderType::F32,
+            ShaderOperation::DrawModelMatrix => ShaderType::Mat4,
+            ShaderOperation::DrawParameters => ShaderType::Vec4,
             ShaderOperation::Vec2(_, _) => ShaderType::Vec2,
             ShaderOperation::Vec3(_, _, _) => ShaderType::Vec3,
             ShaderOperation::Vec4(_, _, _, _) => ShaderType::Vec4,
@@ -112,6 +196,16 @@ impl ShaderExpression {
             ShaderOperation::Add(left, _) => left.shader_type()?,
             ShaderOperation::Sub(left, _) => left.shader_type()?,
             ShaderOperation::Mul(left, right) => match left.shader_type()? {
+                ShaderType::Mat3 => match right.shader_type()? {
+                    ShaderType::Vec3 => ShaderType::Vec3,
+                    ShaderType::Mat3 => ShaderType::Mat3,
+                    right => {
+                        return Err(anyhow::anyhow!(
+                            "Right side of mul operation has wrong type: {:?}",
+                            right
+                        ))
+                    }
+                },
                 ShaderType::Mat4 => match right.shader_type()? {
                     ShaderType::Vec4 => ShaderType::Vec4,
                     ShaderType::Mat4 => ShaderType::Mat4,
@@ -133,6 +227,18 @@ impl ShaderExpression {
             ShaderOperation::Floor(expr) => expr.shader_type()?,
             ShaderOperation::Ceil(expr) => expr.shader_type()?,
             ShaderOperation::Round(expr) => expr.shader_type()?,
+            ShaderOperation::ToFloat(expr) => match expr.shader_type()? {
+                ShaderType::I32 | ShaderType::U32 | ShaderType::F32 => ShaderType::F32,
+                ShaderType::IVec2 | ShaderType::UVec2 | ShaderType::Vec2 => ShaderType::Vec2,
+                ShaderType::IVec3 | ShaderType::UVec3 | ShaderType::Vec3 => ShaderType::Vec3,
+                ShaderType::IVec4 | ShaderType::UVec4 | ShaderType::Vec4 => ShaderType::Vec4,
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "Operand of float conversion has wrong type: {:?}",
+                        other
+                    ))
+                }
+            },
             ShaderOperation::Min(left, _) => left.shader_type()?,
             ShaderOperation::Max(left, _) => left.shader_type()?,
             ShaderOperation::Clamp(left, _, _) => left.shader_type()?,
@@ -142,8 +248,47 @@ impl ShaderExpression {
             ShaderOperation::Length(_) => ShaderType::F32,
             ShaderOperation::Normalized(expr) => expr.shader_type()?,
             ShaderOperation::Sample(_, _, _) => ShaderType::Vec4,
+            ShaderOperation::SampleAutoLod(_, _) => ShaderType::Vec4,
+            ShaderOperation::SampleCube(_, _) => ShaderType::Vec4,
+            ShaderOperation::SampleLayer(_, _, _) => ShaderType::Vec4,
+            ShaderOperation::Sample3D(_, _) => ShaderType::Vec4,
+            ShaderOperation::ViewLayer(_) => ShaderType::F32,
+            ShaderOperation::Inverse(expr) => expr.shader_type()?,
+            ShaderOperation::Transpose(expr) => expr.shader_type()?,
+            ShaderOperation::Noise(_, _) => ShaderType::F32,
+            ShaderOperation::Fbm(_, _, _, _, _) => ShaderType::F32,
         })
     }
+
+    /// Adds the helper functions required by this expression (and its children) to `functions`.
+    pub(crate) fn collect_functions(&self, functions: &mut ShaderFunctions) {
+        let operation = self.operation.borrow();
+
+        // Require the helper functions for this operation.
+        match &*operation {
+            ShaderOperation::Noise(noise_type, point) => {
+                functions.require(ShaderFunction::Noise(*noise_type, point.dimensions()))
+            }
+            ShaderOperation::Fbm(noise_type, point, _, _, _) => {
+                functions.require(ShaderFunction::Fbm(*noise_type, point.dimensions()))
+            }
+            ShaderOperation::DrawModelMatrix | ShaderOperation::DrawParameters => {
+                functions.require(ShaderFunction::DrawData)
+            }
+            _ => {}
+        }
+
+        // Require the helper functions for the children.
+        operation.for_each_child(|child| child.collect_functions(functions));
+    }
+
+    /// Returns the component count of this expression's type, or 1 if it has none.
+    fn dimensions(&self) -> usize {
+        self.shader_type()
+            .ok()
+            .and_then(ShaderType::component_count)
+            .unwrap_or(1)
+    }
 }
 
 impl From<i32> for ShaderExpression {
@@ -275,11 +420,12 @@ pub trait ShaderMath: Into<ShaderExpression> + Sized {
         let a: ShaderExpression = self.into();
         let b: ShaderExpression = other.into();
 
-        // Exit early if the left side is a 4x4 matrix and the right side is a 4 component vector.
-        if let ShaderType::Mat4 = a.shader_type().unwrap() {
-            if let ShaderType::Vec4 = b.shader_type().unwrap() {
+        // Exit early if the left side is a matrix and the right side is a vector of its size.
+        match (a.shader_type().unwrap(), b.shader_type().unwrap()) {
+            (ShaderType::Mat3, ShaderType::Vec3) | (ShaderType::Mat4, ShaderType::Vec4) => {
                 return ShaderExpression::new(ShaderOperation::Mul(a, b));
             }
+            _ => {}
         }
         let a_type = a.shader_type().unwrap();
         let b_type = b.shader_type().unwrap();
@@ -414,6 +560,19 @@ pub trait ShaderMath: Into<ShaderExpression> + Sized {
         ShaderExpression::new(ShaderOperation::Round(a))
     }
 
+    /// Converts an integer value to a float value, such as an integer vertex input.
+    fn to_float(self) -> ShaderExpression {
+        let a: ShaderExpression = self.into();
+
+        // Ensure the type is valid for float conversion.
+        let a_type = a.shader_type().unwrap();
+        a_type
+            .ensure_vector_or_scalar("operand of 'to_float'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::ToFloat(a))
+    }
+
     /// Returns the minimum of the two values.
     fn min(self, other: impl Into<ShaderExpression>) -> ShaderExpression {
         let a: ShaderExpression = self.into();
@@ -728,10 +887,232 @@ pub trait ShaderTexture: Into<ShaderExpression> + Sized {
 
         ShaderExpression::new(ShaderOperation::Sample(a, b, c))
     }
+
+    /// Samples the texture at the given texture coordinates, letting the GPU select the LOD.
+    /// This uses the sampler's mipmap and anisotropic filtering, but ignores the view's LOD range.
+    fn sample_auto_lod(self, tex_coord: impl Into<ShaderExpression>) -> ShaderExpression {
+        let a = self.into();
+        let b = tex_coord.into();
+
+        // Ensure the types are valid for sampling.
+        let a_type = a.shader_type().unwrap();
+        let b_type = b.shader_type().unwrap();
+        a_type
+            .ensure_type(
+                ShaderType::Sampler2D,
+                "argument 'self' of 'sample_auto_lod'",
+            )
+            .unwrap();
+        b_type
+            .ensure_type(
+                ShaderType::Vec2,
+                "argument 'tex_coord' of 'sample_auto_lod'",
+            )
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::SampleAutoLod(a, b))
+    }
+
+    /// Samples the cube map in the given direction, which does not need to be normalized.
+    fn sample_cube(self, direction: impl Into<ShaderExpression>) -> ShaderExpression {
+        let a = self.into();
+        let b = direction.into();
+
+        // Ensure the types are valid for sampling.
+        let a_type = a.shader_type().unwrap();
+        let b_type = b.shader_type().unwrap();
+        a_type
+            .ensure_type(ShaderType::SamplerCube, "argument 'self' of 'sample_cube'")
+            .unwrap();
+        b_type
+            .ensure_type(ShaderType::Vec3, "argument 'direction' of 'sample_cube'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::SampleCube(a, b))
+    }
+
+    /// Samples the given layer of the texture array at the given texture coordinates.
+    /// The layer index is rounded to the nearest layer.
+    fn sample_layer(
+        self,
+        tex_coord: impl Into<ShaderExpression>,
+        layer: impl Into<ShaderExpression>,
+    ) -> ShaderExpression {
+        let a = self.into();
+        let b = tex_coord.into();
+        let c = layer.into();
+
+        // Ensure the types are valid for sampling.
+        let a_type = a.shader_type().unwrap();
+        let b_type = b.shader_type().unwrap();
+        let c_type = c.shader_type().unwrap();
+        a_type
+            .ensure_type(
+                ShaderType::Sampler2DArray,
+                "argument 'self' of 'sample_layer'",
+            )
+            .unwrap();
+        b_type
+            .ensure_type(ShaderType::Vec2, "argument 'tex_coord' of 'sample_layer'")
+            .unwrap();
+        c_type
+            .ensure_type(ShaderType::F32, "argument 'layer' of 'sample_layer'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::SampleLayer(a, b, c))
+    }
+
+    /// Returns the layer identified by the texture array view, as a float.
+    /// This can be passed to `sample_layer`, optionally with an offset.
+    fn view_layer(self) -> ShaderExpression {
+        let a = self.into();
+
+        // Ensure the type is a texture array.
+        let a_type = a.shader_type().unwrap();
+        a_type
+            .ensure_type(
+                ShaderType::Sampler2DArray,
+                "argument 'self' of 'view_layer'",
+            )
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::ViewLayer(a))
+    }
+
+    /// Samples the 3D texture at the given texture coordinates.
+    fn sample_3d(self, tex_coord: impl Into<ShaderExpression>) -> ShaderExpression {
+        let a = self.into();
+        let b = tex_coord.into();
+
+        // Ensure the types are valid for sampling.
+        let a_type = a.shader_type().unwrap();
+        let b_type = b.shader_type().unwrap();
+        a_type
+            .ensure_type(ShaderType::Sampler3D, "argument 'self' of 'sample_3d'")
+            .unwrap();
+        b_type
+            .ensure_type(ShaderType::Vec3, "argument 'tex_coord' of 'sample_3d'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::Sample3D(a, b))
+    }
 }
 
 impl ShaderTexture for ShaderExpression {}
 
+pub trait ShaderMatrix: Into<ShaderExpression> + Sized {
+    /// Returns the inverse of the matrix.
+    fn inverse(self) -> ShaderExpression {
+        let a = self.into();
+
+        // Ensure the type is valid for inversion.
+        let a_type = a.shader_type().unwrap();
+        a_type
+            .ensure_type(ShaderType::Mat4, "argument 'self' of 'inverse'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::Inverse(a))
+    }
+
+    /// Returns the transpose of the matrix.
+    fn transposed(self) -> ShaderExpression {
+        let a = self.into();
+
+        // Ensure the type is valid for transposition.
+        let a_type = a.shader_type().unwrap();
+        a_type
+            .ensure_type(ShaderType::Mat4, "argument 'self' of 'transposed'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::Transpose(a))
+    }
+}
+
+impl ShaderMatrix for ShaderExpression {}
+
+pub trait ShaderNoise: Into<ShaderExpression> + Sized {
+    /// Samples the given type of noise at this point.
+    /// The point must be a Vector2, Vector3 or Vector4.
+    fn noise(self, noise_type: NoiseType) -> ShaderExpression {
+        let a = self.into();
+
+        // Ensure the type is valid for noise.
+        let a_type = a.shader_type().unwrap();
+        a_type
+            .ensure_in_list(
+                &[ShaderType::Vec2, ShaderType::Vec3, ShaderType::Vec4],
+                "argument 'self' of 'noise'",
+            )
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::Noise(noise_type, a))
+    }
+
+    /// Samples value noise at this point (0.0 to 1.0).
+    fn value_noise(self) -> ShaderExpression {
+        self.noise(NoiseType::Value)
+    }
+
+    /// Samples Perlin noise at this point (roughly -1.0 to 1.0).
+    fn perlin_noise(self) -> ShaderExpression {
+        self.noise(NoiseType::Perlin)
+    }
+
+    /// Samples simplex noise at this point (roughly -1.0 to 1.0).
+    fn simplex_noise(self) -> ShaderExpression {
+        self.noise(NoiseType::Simplex)
+    }
+
+    /// Samples Worley (cellular) noise at this point.
+    /// This is the distance to the nearest feature point.
+    fn worley_noise(self) -> ShaderExpression {
+        self.noise(NoiseType::Worley)
+    }
+
+    /// Samples fractal Brownian motion built from the given type of noise at this point.
+    /// Each octave multiplies the frequency by `lacunarity` and the amplitude by `gain`.
+    fn fbm(
+        self,
+        noise_type: NoiseType,
+        octaves: impl Into<ShaderExpression>,
+        lacunarity: impl Into<ShaderExpression>,
+        gain: impl Into<ShaderExpression>,
+    ) -> ShaderExpression {
+        let a = self.into();
+        let b = octaves.into();
+        let c = lacunarity.into();
+        let d = gain.into();
+
+        // Ensure the types are valid for fbm.
+        let a_type = a.shader_type().unwrap();
+        let b_type = b.shader_type().unwrap();
+        let c_type = c.shader_type().unwrap();
+        let d_type = d.shader_type().unwrap();
+        a_type
+            .ensure_in_list(
+                &[ShaderType::Vec2, ShaderType::Vec3, ShaderType::Vec4],
+                "argument 'self' of 'fbm'",
+            )
+            .unwrap();
+        b_type
+            .ensure_type(ShaderType::I32, "argument 'octaves' of 'fbm'")
+            .unwrap();
+        c_type
+            .ensure_type(ShaderType::F32, "argument 'lacunarity' of 'fbm'")
+            .unwrap();
+        d_type
+            .ensure_type(ShaderType::F32, "argument 'gain' of 'fbm'")
+            .unwrap();
+
+        ShaderExpression::new(ShaderOperation::Fbm(noise_type, a, b, c, d))
+    }
+}
+
+impl ShaderNoise for ShaderExpression {}
+impl ShaderNoise for Vector2<f32> {}
+impl ShaderNoise for Vector3<f32> {}
+impl ShaderNoise for Vector4<f32> {}
+
 impl Display for ShaderExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &*self.operation.borrow() {
@@ -739,6 +1120,8 @@ impl Display for ShaderExpression {
             ShaderOperation::Uniform(name, _) => write!(f, "{}{}", SHADER_UNIFORM_PREFIX, name),
             ShaderOperation::I32(value) => write!(f, "{}", value),
             ShaderOperation::F32(value) => write!(f, "{}", value),
+            ShaderOperation::DrawModelMatrix => write!(f, "_draw_data[gl_DrawIDARB].model_matrix"),
+            ShaderOperation::DrawParameters => write!(f, "_draw_data[gl_DrawIDARB].parameters"),
             ShaderOperation::Vec2(x, y) => write!(f, "vec2({}, {})", x, y),
             ShaderOperation::Vec3(x, y, z) => write!(f, "vec3({}, {}, {})", x, y, z),
             ShaderOperation::Vec4(x, y, z, w) => write!(f, "vec4({}, {}, {}, {})", x, y, z, w),
@@ -760,6 +1143,9 @@ impl Display for ShaderExpression {
             ShaderOperation::Floor(expr) => write!(f, "floor({})", expr),
             ShaderOperation::Ceil(expr) => write!(f, "ceil({})", expr),
             ShaderOperation::Round(expr) => write!(f, "round({})", expr),
+            ShaderOperation::ToFloat(expr) => {
+                write!(f, "{}({})", self.shader_type().unwrap().glsl_name(), expr)
+            }
             ShaderOperation::Min(left, right) => write!(f, "min({}, {})", left, right),
             ShaderOperation::Max(left, right) => write!(f, "max({}, {})", left, right),
             ShaderOperation::Clamp(left, min, max) => {
@@ -785,6 +1171,45 @@ impl Display for ShaderExpression {
                     _ => unimplemented!(),
                 }
             }
+            ShaderOperation::SampleAutoLod(texture, tex_coord) => {
+                match &*texture.operation.borrow() {
+                    ShaderOperation::Uniform(name, _) => write!(
+                        f,
+                        "texture({0}{1}, {0}{1}_min.xy + ({0}{1}_max.xy - {0}{1}_min.xy) * {2})",
+                        SHADER_UNIFORM_PREFIX, name, tex_coord
+                    ),
+                    _ => unimplemented!(),
+                }
+            }
+            ShaderOperation::SampleCube(cubemap, direction) => {
+                write!(f, "texture({}, {})", cubemap, direction)
+            }
+            ShaderOperation::SampleLayer(texture, tex_coord, layer) => {
+                write!(f, "texture({}, vec3({}, {}))", texture, tex_coord, layer)
+            }
+            ShaderOperation::Sample3D(texture, tex_coord) => {
+                write!(f, "texture({}, {})", texture, tex_coord)
+            }
+            ShaderOperation::ViewLayer(texture) => match &*texture.operation.borrow() {
+                ShaderOperation::Uniform(name, _) => {
+                    write!(f, "{}{}_layer", SHADER_UNIFORM_PREFIX, name)
+                }
+                _ => unimplemented!(),
+            },
+            ShaderOperation::Inverse(expr) => write!(f, "inverse({})", expr),
+            ShaderOperation::Transpose(expr) => write!(f, "transpose({})", expr),
+            ShaderOperation::Noise(noise_type, point) => {
+                write!(f, "_noise_{}({})", noise_type.glsl_name(), point)
+            }
+            ShaderOperation::Fbm(noise_type, point, octaves, lacunarity, gain) => write!(
+                f,
+                "_noise_fbm_{}({}, {}, float({}), float({}))",
+                noise_type.glsl_name(),
+                point,
+                octaves,
+                lacunarity,
+                gain
+            ),
         }
     }
 }
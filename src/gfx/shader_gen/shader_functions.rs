@@ -0,0 +1,303 @@
+use crate::{
+    gfx::indirect_batch::_DRAW_DATA_BUFFER_BINDING,
+    noise::{self, NoiseType},
+};
+
+/// Represents a helper function that generated shader code can depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderFunction {
+    /// The integer hash shared by all noise functions.
+    NoiseHash,
+    /// Random value, gradient and fade helpers for noise with the given number of dimensions.
+    NoiseHelpers(usize),
+    /// A noise function with the given number of dimensions.
+    Noise(NoiseType, usize),
+    /// A fractal Brownian motion function with the given number of dimensions.
+    Fbm(NoiseType, usize),
+    /// The storage buffer holding the data of each draw of an indirect batch.
+    DrawData,
+}
+
+impl ShaderFunction {
+    /// Get the functions that must be declared before this function.
+    pub fn dependencies(self) -> Vec<ShaderFunction> {
+        match self {
+            ShaderFunction::NoiseHash => Vec::new(),
+            ShaderFunction::NoiseHelpers(_) => vec![ShaderFunction::NoiseHash],
+            ShaderFunction::Noise(_, dimensions) => vec![ShaderFunction::NoiseHelpers(dimensions)],
+            ShaderFunction::Fbm(noise_type, dimensions) => {
+                vec![ShaderFunction::Noise(noise_type, dimensions)]
+            }
+            ShaderFunction::DrawData => Vec::new(),
+        }
+    }
+
+    /// Get the GLSL extension this function requires, if any.
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            ShaderFunction::DrawData => Some("GL_ARB_shader_draw_parameters"),
+            _ => None,
+        }
+    }
+
+    /// Get the GLSL source code declaring this function.
+    pub fn glsl_source(self) -> String {
+        match self {
+            ShaderFunction::NoiseHash => NOISE_HASH_SOURCE.to_string(),
+            ShaderFunction::NoiseHelpers(dimensions) => noise_helpers_source(dimensions),
+            ShaderFunction::Noise(noise_type, dimensions) => match noise_type {
+                NoiseType::Value => value_noise_source(dimensions),
+                NoiseType::Perlin => perlin_noise_source(dimensions),
+                NoiseType::Simplex => simplex_noise_source(dimensions),
+                NoiseType::Worley => worley_noise_source(dimensions),
+            },
+            ShaderFunction::Fbm(noise_type, dimensions) => fbm_source(noise_type, dimensions),
+            ShaderFunction::DrawData => draw_data_source(),
+        }
+    }
+}
+
+/// An ordered set of helper functions required by a shader stage.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderFunctions {
+    functions: Vec<ShaderFunction>,
+}
+
+impl ShaderFunctions {
+    /// Create a new empty set of shader functions.
+    pub(crate) fn new() -> Self {
+        Self {
+            functions: Vec::new(),
+        }
+    }
+
+    /// Require the given function and its dependencies.
+    /// Dependencies are always ordered before the functions that use them.
+    pub(crate) fn require(&mut self, function: ShaderFunction) {
+        if self.functions.contains(&function) {
+            return;
+        }
+
+        for dependency in function.dependencies() {
+            self.require(dependency);
+        }
+
+        self.functions.push(function);
+    }
+
+    /// Get an iterator over the required functions.
+    pub fn iter(&self) -> impl Iterator<Item = &ShaderFunction> {
+        self.functions.iter()
+    }
+
+    /// Get the GLSL source code declaring all required functions.
+    pub fn glsl_source(&self) -> String {
+        self.functions
+            .iter()
+            .map(|function| function.glsl_source())
+            .collect()
+    }
+
+    /// Get the `#extension` directives required by the functions.
+    /// These must come directly after the `#version` directive.
+    pub fn glsl_extensions(&self) -> String {
+        let mut extensions = Vec::new();
+        for extension in self
+            .functions
+            .iter()
+            .filter_map(|function| function.extension())
+        {
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+
+        extensions
+            .into_iter()
+            .map(|extension| format!("#extension {} : require\n", extension))
+            .collect()
+    }
+}
+
+/// The per-draw data of an indirect batch, matching `DrawData`.
+fn draw_data_source() -> String {
+    format!(
+        "struct _DrawData {{
+mat4 model_matrix;
+vec4 parameters;
+}};
+layout(std430, binding = {_DRAW_DATA_BUFFER_BINDING}) readonly buffer _DrawDataBuffer {{
+_DrawData _draw_data[];
+}};
+"
+    )
+}
+
+/// PCG integer hash, matching `noise::hash`.
+const NOISE_HASH_SOURCE: &str = "uint _noise_hash(uint x) {
+uint state = x * 747796405u + 2891336453u;
+uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+return (word >> 22u) ^ word;
+}
+";
+
+/// Random value, gradient and fade helpers with the given number of dimensions.
+fn noise_helpers_source(n: usize) -> String {
+    format!(
+        "float _noise_random(ivec{n} cell, uint salt) {{
+uint h = _noise_hash(salt);
+for (int k = 0; k < {n}; k++) {{
+h = _noise_hash(h ^ uint(cell[k]));
+}}
+return float(h) / 4294967295.0;
+}}
+vec{n} _noise_random_vector(ivec{n} cell) {{
+vec{n} v;
+for (int k = 0; k < {n}; k++) {{
+v[k] = _noise_random(cell, uint(k));
+}}
+return v;
+}}
+vec{n} _noise_gradient(ivec{n} cell) {{
+vec{n} g = _noise_random_vector(cell) * 2.0 - 1.0;
+return g / max(length(g), 1e-6);
+}}
+vec{n} _noise_fade(vec{n} t) {{
+return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}}
+"
+    )
+}
+
+/// Value noise with the given number of dimensions, matching `noise::value`.
+fn value_noise_source(n: usize) -> String {
+    format!(
+        "float _noise_value(vec{n} p) {{
+vec{n} i = floor(p);
+vec{n} u = _noise_fade(p - i);
+float result = 0.0;
+for (int c = 0; c < {corners}; c++) {{
+ivec{n} corner = ivec{n}(i);
+float weight = 1.0;
+for (int k = 0; k < {n}; k++) {{
+int o = (c >> k) & 1;
+corner[k] += o;
+weight *= o == 1 ? u[k] : 1.0 - u[k];
+}}
+result += weight * _noise_random(corner, 0u);
+}}
+return result;
+}}
+",
+        corners = 1 << n
+    )
+}
+
+/// Perlin noise with the given number of dimensions, matching `noise::perlin`.
+fn perlin_noise_source(n: usize) -> String {
+    format!(
+        "float _noise_perlin(vec{n} p) {{
+vec{n} i = floor(p);
+vec{n} f = p - i;
+vec{n} u = _noise_fade(f);
+float result = 0.0;
+for (int c = 0; c < {corners}; c++) {{
+ivec{n} corner = ivec{n}(i);
+vec{n} offset = vec{n}(0.0);
+float weight = 1.0;
+for (int k = 0; k < {n}; k++) {{
+int o = (c >> k) & 1;
+corner[k] += o;
+offset[k] = float(o);
+weight *= o == 1 ? u[k] : 1.0 - u[k];
+}}
+result += weight * dot(_noise_gradient(corner), f - offset);
+}}
+return result * {scale:?};
+}}
+",
+        corners = 1 << n,
+        scale = 2.0 / (n as f32).sqrt()
+    )
+}
+
+/// Simplex noise with the given number of dimensions, matching `noise::simplex`.
+fn simplex_noise_source(n: usize) -> String {
+    let (skew, unskew, radius_squared, scale) = noise::simplex_constants(n);
+    format!(
+        "float _noise_simplex(vec{n} p) {{
+vec{n} i = floor(p + dot(p, vec{n}(1.0)) * {skew:?});
+vec{n} x0 = p - (i - dot(i, vec{n}(1.0)) * {unskew:?});
+ivec{n} rank = ivec{n}(0);
+for (int a = 0; a < {n}; a++) {{
+for (int b = a + 1; b < {n}; b++) {{
+if (x0[a] >= x0[b]) {{
+rank[b]++;
+}} else {{
+rank[a]++;
+}}
+}}
+}}
+float result = 0.0;
+for (int v = 0; v <= {n}; v++) {{
+vec{n} offset = vec{n}(0.0);
+for (int k = 0; k < {n}; k++) {{
+offset[k] = rank[k] < v ? 1.0 : 0.0;
+}}
+vec{n} x = x0 - offset + float(v) * {unskew:?};
+float t = {radius_squared:?} - dot(x, x);
+if (t > 0.0) {{
+t *= t;
+result += t * t * dot(_noise_gradient(ivec{n}(i + offset)), x);
+}}
+}}
+return result * {scale:?};
+}}
+"
+    )
+}
+
+/// Worley (cellular) noise with the given number of dimensions, matching `noise::worley`.
+fn worley_noise_source(n: usize) -> String {
+    format!(
+        "float _noise_worley(vec{n} p) {{
+vec{n} i = floor(p);
+vec{n} f = p - i;
+float result = 1e9;
+for (int c = 0; c < {neighbors}; c++) {{
+vec{n} offset;
+int rest = c;
+for (int k = 0; k < {n}; k++) {{
+offset[k] = float(rest % 3 - 1);
+rest /= 3;
+}}
+vec{n} feature = offset + _noise_random_vector(ivec{n}(i + offset));
+result = min(result, length(feature - f));
+}}
+return result;
+}}
+",
+        neighbors = 3usize.pow(n as u32)
+    )
+}
+
+/// Fractal Brownian motion with the given noise type and number of dimensions,
+/// matching `noise::fbm`.
+fn fbm_source(noise_type: NoiseType, n: usize) -> String {
+    format!(
+        "float _noise_fbm_{name}(vec{n} p, int octaves, float lacunarity, float gain) {{
+float result = 0.0;
+float amplitude = 1.0;
+float total = 0.0;
+for (int o = 0; o < octaves; o++) {{
+result += amplitude * _noise_{name}(p);
+total += amplitude;
+p *= lacunarity;
+amplitude *= gain;
+}}
+return result / max(total, 1e-6);
+}}
+",
+        name = noise_type.glsl_name()
+    )
+}
@@ -1,7 +1,10 @@
 use anyhow::Result;
 
+use crate::gfx::vertex_layout::VertexInput;
+
 use super::{
     shader_expression::{ShaderExpression, ShaderOperation},
+    shader_parameters::ShaderParameters,
     shader_type::ShaderType,
 };
 
@@ -80,6 +83,29 @@ impl ShaderInputs {
             .ok_or_else(|| anyhow::anyhow!("Input not found: {}", name))
     }
 
+    /// Get the model matrix for the vertex being shaded.
+    /// For instanced input layouts this is the renderer's model matrix multiplied by the
+    /// instance's `ModelMatrix` input; otherwise it is the renderer's model matrix.
+    pub fn model_matrix(&self, parameters: &mut ShaderParameters) -> ShaderExpression {
+        let model_matrix = parameters.get_model_matrix();
+        match self.get(VertexInput::ModelMatrix) {
+            Ok(instance_matrix) => model_matrix * instance_matrix,
+            Err(_) => model_matrix,
+        }
+    }
+
+    /// Get the model matrix of the current draw of an indirect batch.
+    /// Only available in vertex shaders rendered with `TargetBuffer::render_indirect`.
+    pub fn draw_model_matrix(&self) -> ShaderExpression {
+        ShaderExpression::new(ShaderOperation::DrawModelMatrix)
+    }
+
+    /// Get the parameters of the current draw of an indirect batch.
+    /// Only available in vertex shaders rendered with `TargetBuffer::render_indirect`.
+    pub fn draw_parameters(&self) -> ShaderExpression {
+        ShaderExpression::new(ShaderOperation::DrawParameters)
+    }
+
     /// Get an iterator over the inputs.
     pub fn iter(&self) -> impl Iterator<Item = &ShaderInput> {
         self.inputs.iter()
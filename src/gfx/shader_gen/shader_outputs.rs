@@ -2,7 +2,9 @@ use anyhow::Result;
 
 use crate::gfx::shader::ShaderStage;
 
-use super::{shader_expression::ShaderExpression, shader_type::ShaderType};
+use super::{
+    shader_expression::ShaderExpression, shader_functions::ShaderFunctions, shader_type::ShaderType,
+};
 
 /// The prefix for shader output variables.
 pub(crate) const SHADER_OUTPUT_PREFIX: &str = "_output_";
@@ -154,4 +156,17 @@ impl ShaderOutputs {
     pub fn iter(&self) -> impl Iterator<Item = &ShaderOutput> {
         self.outputs.iter()
     }
+
+    /// Adds the helper functions required by all output expressions to `functions`.
+    pub(crate) fn collect_functions(&self, functions: &mut ShaderFunctions) {
+        let expressions = self
+            .vertex_position
+            .iter()
+            .chain(self.fragment_color.iter())
+            .chain(self.outputs.iter().filter_map(|output| output.expression()));
+
+        for expression in expressions {
+            expression.collect_functions(functions);
+        }
+    }
 }
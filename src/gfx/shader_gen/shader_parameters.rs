@@ -4,7 +4,7 @@ use ggmath::prelude::*;
 use crate::gfx::program::{UniformDefault, UniformValue};
 
 use super::{
-    prelude::{ShaderExpression, ShaderOperation},
+    prelude::{ShaderExpression, ShaderMatrix, ShaderOperation},
     shader_type::ShaderType,
 };
 
@@ -20,6 +20,34 @@ pub(crate) const PARAMETER_PROJECTION_MATRIX: &str = "builtin_projection_matrix"
 /// The built-in model matrix parameter name in generated shaders.
 pub(crate) const PARAMETER_MODEL_MATRIX: &str = "builtin_model_matrix";
 
+/// The built-in inverse view matrix parameter name in generated shaders.
+pub(crate) const PARAMETER_INVERSE_VIEW_MATRIX: &str = "builtin_inverse_view_matrix";
+
+/// The built-in inverse projection matrix parameter name in generated shaders.
+pub(crate) const PARAMETER_INVERSE_PROJECTION_MATRIX: &str = "builtin_inverse_projection_matrix";
+
+/// The built-in model-view-projection matrix parameter name in generated shaders.
+pub(crate) const PARAMETER_MODEL_VIEW_PROJECTION_MATRIX: &str =
+    "builtin_model_view_projection_matrix";
+
+/// The built-in normal matrix parameter name in generated shaders.
+pub(crate) const PARAMETER_NORMAL_MATRIX: &str = "builtin_normal_matrix";
+
+/// The built-in elapsed time parameter name in generated shaders.
+pub(crate) const PARAMETER_TIME: &str = "builtin_time";
+
+/// The built-in delta time parameter name in generated shaders.
+pub(crate) const PARAMETER_DELTA_TIME: &str = "builtin_delta_time";
+
+/// The built-in frame index parameter name in generated shaders.
+pub(crate) const PARAMETER_FRAME_INDEX: &str = "builtin_frame_index";
+
+/// The built-in viewport resolution parameter name in generated shaders.
+pub(crate) const PARAMETER_RESOLUTION: &str = "builtin_resolution";
+
+/// The built-in camera position parameter name in generated shaders.
+pub(crate) const PARAMETER_CAMERA_POSITION: &str = "builtin_camera_position";
+
 /// Represents a single parameter for a shader.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ShaderParameter {
@@ -149,6 +177,56 @@ impl ShaderParameters {
         self.get::<Matrix4x4<f32>>(PARAMETER_MODEL_MATRIX)
     }
 
+    /// Get the inverse of the view matrix.
+    /// This is computed on the CPU when the view matrix is set.
+    pub fn get_inverse_view_matrix(&mut self) -> ShaderExpression {
+        self.get::<Matrix4x4<f32>>(PARAMETER_INVERSE_VIEW_MATRIX)
+    }
+
+    /// Get the inverse of the projection matrix.
+    /// This is computed on the CPU when the projection matrix is set.
+    pub fn get_inverse_projection_matrix(&mut self) -> ShaderExpression {
+        self.get::<Matrix4x4<f32>>(PARAMETER_INVERSE_PROJECTION_MATRIX)
+    }
+
+    /// Get the combined model-view-projection matrix.
+    /// This is computed on the CPU once the model, view, and projection matrices are set.
+    pub fn get_model_view_projection_matrix(&mut self) -> ShaderExpression {
+        self.get::<Matrix4x4<f32>>(PARAMETER_MODEL_VIEW_PROJECTION_MATRIX)
+    }
+
+    /// Get the `mat3` normal matrix (the inverse transpose of the model matrix's 3x3 part).
+    /// This is computed on the CPU when the model matrix is set.
+    /// Multiply it with a 3 component normal.
+    pub fn get_normal_matrix(&mut self) -> ShaderExpression {
+        self.get::<[[f32; 3]; 3]>(PARAMETER_NORMAL_MATRIX)
+    }
+
+    /// Get the time since the engine started, in seconds.
+    pub fn get_time(&mut self) -> ShaderExpression {
+        self.get::<f32>(PARAMETER_TIME)
+    }
+
+    /// Get the time between the previous frame and the current frame, in seconds.
+    pub fn get_delta_time(&mut self) -> ShaderExpression {
+        self.get::<f32>(PARAMETER_DELTA_TIME)
+    }
+
+    /// Get the index of the current frame.
+    pub fn get_frame_index(&mut self) -> ShaderExpression {
+        self.get::<i32>(PARAMETER_FRAME_INDEX)
+    }
+
+    /// Get the size of the viewport in pixels.
+    pub fn get_resolution(&mut self) -> ShaderExpression {
+        self.get::<Vector2<f32>>(PARAMETER_RESOLUTION)
+    }
+
+    /// Get the position of the camera in world space.
+    pub fn get_camera_position(&mut self) -> ShaderExpression {
+        self.get::<Vector3<f32>>(PARAMETER_CAMERA_POSITION)
+    }
+
     /// Get an iterator over the parameters.
     pub fn iter(&self) -> impl Iterator<Item = &ShaderParameter> {
         self.parameters.iter()
@@ -8,8 +8,19 @@ pub enum ShaderType {
     Vec2,
     Vec3,
     Vec4,
+    IVec2,
+    IVec3,
+    IVec4,
+    U32,
+    UVec2,
+    UVec3,
+    UVec4,
+    Mat3,
     Mat4,
     Sampler2D,
+    SamplerCube,
+    Sampler2DArray,
+    Sampler3D,
 }
 
 impl ShaderType {
@@ -22,7 +33,18 @@ impl ShaderType {
             | ShaderType::Vec2
             | ShaderType::Vec3
             | ShaderType::Vec4
-            | ShaderType::Sampler2D => 1,
+            | ShaderType::IVec2
+            | ShaderType::IVec3
+            | ShaderType::IVec4
+            | ShaderType::U32
+            | ShaderType::UVec2
+            | ShaderType::UVec3
+            | ShaderType::UVec4
+            | ShaderType::Sampler2D
+            | ShaderType::SamplerCube
+            | ShaderType::Sampler2DArray
+            | ShaderType::Sampler3D => 1,
+            ShaderType::Mat3 => 3,
             ShaderType::Mat4 => 4,
         }
     }
@@ -36,8 +58,19 @@ impl ShaderType {
             ShaderType::Vec2 => "vec2",
             ShaderType::Vec3 => "vec3",
             ShaderType::Vec4 => "vec4",
+            ShaderType::IVec2 => "ivec2",
+            ShaderType::IVec3 => "ivec3",
+            ShaderType::IVec4 => "ivec4",
+            ShaderType::U32 => "uint",
+            ShaderType::UVec2 => "uvec2",
+            ShaderType::UVec3 => "uvec3",
+            ShaderType::UVec4 => "uvec4",
+            ShaderType::Mat3 => "mat3",
             ShaderType::Mat4 => "mat4",
             ShaderType::Sampler2D => "sampler2D",
+            ShaderType::SamplerCube => "samplerCube",
+            ShaderType::Sampler2DArray => "sampler2DArray",
+            ShaderType::Sampler3D => "sampler3D",
         }
     }
 
@@ -50,31 +83,53 @@ impl ShaderType {
             ShaderType::Vec2 => "Vector2<f32>",
             ShaderType::Vec3 => "Vector3<f32>",
             ShaderType::Vec4 => "Vector4<f32>",
+            ShaderType::IVec2 => "Vector2<i32>",
+            ShaderType::IVec3 => "Vector3<i32>",
+            ShaderType::IVec4 => "Vector4<i32>",
+            ShaderType::U32 => "u32",
+            ShaderType::UVec2 => "Vector2<u32>",
+            ShaderType::UVec3 => "Vector3<u32>",
+            ShaderType::UVec4 => "Vector4<u32>",
+            ShaderType::Mat3 => "[[f32; 3]; 3]",
             ShaderType::Mat4 => "Matrix4x4<f32>",
             ShaderType::Sampler2D => "TextureView",
+            ShaderType::SamplerCube => "CubemapView",
+            ShaderType::Sampler2DArray => "TextureArrayView",
+            ShaderType::Sampler3D => "Texture3DView",
         }
     }
 
     /// Get the component count for this type.
     pub fn component_count(self) -> Option<usize> {
         match self {
-            ShaderType::I32 | ShaderType::F32 => Some(1),
-            ShaderType::Vec2 => Some(2),
-            ShaderType::Vec3 => Some(3),
-            ShaderType::Vec4 => Some(4),
+            ShaderType::I32 | ShaderType::U32 | ShaderType::F32 => Some(1),
+            ShaderType::Vec2 | ShaderType::IVec2 | ShaderType::UVec2 => Some(2),
+            ShaderType::Vec3 | ShaderType::IVec3 | ShaderType::UVec3 => Some(3),
+            ShaderType::Vec4 | ShaderType::IVec4 | ShaderType::UVec4 => Some(4),
+            ShaderType::Mat3 => Some(9),
             ShaderType::Mat4 => Some(16),
-            ShaderType::Sampler2D => None,
+            ShaderType::Sampler2D
+            | ShaderType::SamplerCube
+            | ShaderType::Sampler2DArray
+            | ShaderType::Sampler3D => None,
         }
     }
 
     /// Get the component type for this type (or the type itself if it is a scalar).
     pub fn component_type(self) -> Option<ShaderType> {
         match self {
-            ShaderType::I32 | ShaderType::F32 => Some(self),
-            ShaderType::Vec2 | ShaderType::Vec3 | ShaderType::Vec4 | ShaderType::Mat4 => {
-                Some(ShaderType::F32)
-            }
-            ShaderType::Sampler2D => None,
+            ShaderType::I32 | ShaderType::U32 | ShaderType::F32 => Some(self),
+            ShaderType::Vec2
+            | ShaderType::Vec3
+            | ShaderType::Vec4
+            | ShaderType::Mat3
+            | ShaderType::Mat4 => Some(ShaderType::F32),
+            ShaderType::IVec2 | ShaderType::IVec3 | ShaderType::IVec4 => Some(ShaderType::I32),
+            ShaderType::UVec2 | ShaderType::UVec3 | ShaderType::UVec4 => Some(ShaderType::U32),
+            ShaderType::Sampler2D
+            | ShaderType::SamplerCube
+            | ShaderType::Sampler2DArray
+            | ShaderType::Sampler3D => None,
         }
     }
 
@@ -1,15 +1,25 @@
+use std::{os::raw::c_void, path::Path};
+
 use anyhow::Result;
 use ggmath::prelude::*;
 
 use super::{
-    input_layout::{InputLayout, _VERTEX_BUFFER_LOCATION},
-    mesh::Mesh,
+    buffer::VertexBuffer,
+    gl_state::GlState,
+    indirect_batch::{IndirectBatch, _DRAW_DATA_BUFFER_BINDING},
+    input_layout::{InputLayout, _INSTANCE_BUFFER_LOCATION, _VERTEX_BUFFER_LOCATION},
+    material::ResolvedMaterial,
+    mesh::{Mesh, MeshRange, PrimitiveTopology},
+    mesh_pool::MeshPool,
+    pipeline_state::PipelineState,
     program::Program,
+    readback::{self, PixelReadback},
     render_parameters::RenderParameters,
+    ring_buffer::RingBuffer,
 };
 
 /// Represents a GL buffer for rendering to.
-// TODO: Make it support other types of buffers besides framebuffers.
+/// This is either the default framebuffer or a `Framebuffer` from the `GfxCache`.
 pub struct TargetBuffer {
     handle: u32,
 }
@@ -21,6 +31,11 @@ impl TargetBuffer {
     /// The default framebuffer.
     pub const DEFAULT: TargetBuffer = TargetBuffer { handle: 0 };
 
+    /// Create a target buffer pointing to the framebuffer with the given GL handle.
+    pub(crate) const fn __from_handle(handle: u32) -> Self {
+        Self { handle }
+    }
+
     /// Get the GL handle.
     /// Returns 0 if this is the default framebuffer.
     pub const fn handle(&self) -> u32 {
@@ -31,14 +46,15 @@ impl TargetBuffer {
     pub fn clear_with_color(&self, color: Vector4<f32>) {
         unsafe {
             // Bind the buffer.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle);
+            GlState::__verify();
+            GlState::__bind_draw_framebuffer(self.handle);
+
+            // Make sure the whole buffer can be cleared.
+            PipelineState::__apply_for_clear();
 
             // Clear the buffer.
             gl::ClearColor(color.x(), color.y(), color.z(), color.w());
             gl::Clear(gl::COLOR_BUFFER_BIT);
-
-            // Unbind the buffer.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
         }
     }
 
@@ -46,18 +62,35 @@ impl TargetBuffer {
     pub fn clear_depth(&self) {
         unsafe {
             // Bind the buffer.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle);
+            GlState::__verify();
+            GlState::__bind_draw_framebuffer(self.handle);
+
+            // Make sure the whole buffer can be cleared.
+            PipelineState::__apply_for_clear();
 
             // Clear the buffer.
             gl::ClearDepth(1.0);
             gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
 
-            // Unbind the buffer.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    /// Clear the buffer stencil with a value.
+    pub fn clear_stencil(&self, value: i32) {
+        unsafe {
+            // Bind the buffer.
+            GlState::__verify();
+            GlState::__bind_draw_framebuffer(self.handle);
+
+            // Make sure the whole buffer can be cleared.
+            PipelineState::__apply_for_clear();
+
+            // Clear the buffer.
+            gl::ClearStencil(value);
+            gl::Clear(gl::STENCIL_BUFFER_BIT);
         }
     }
 
-    /// Render a mesh to this buffer.
+    /// Render a mesh to this buffer with the default pipeline state.
     pub fn render_mesh(
         &self,
         program: &Program,
@@ -65,63 +98,471 @@ impl TargetBuffer {
         parameters: &RenderParameters,
         mesh: &Mesh,
     ) -> Result<()> {
-        let vertex_buffer = mesh.vertex_buffer();
+        self.render_mesh_with_state(
+            program,
+            input_layout,
+            parameters,
+            mesh,
+            &PipelineState::default(),
+        )
+    }
+
+    /// Render a mesh to this buffer with the given pipeline state.
+    pub fn render_mesh_with_state(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        mesh: &Mesh,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.render_mesh_range(
+            program,
+            input_layout,
+            parameters,
+            mesh,
+            mesh.full_range(),
+            state,
+        )
+    }
+
+    /// Render a range of a mesh to this buffer with the given pipeline state.
+    /// This allows one mesh to hold many sub-meshes.
+    pub fn render_mesh_range(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        mesh: &Mesh,
+        range: MeshRange,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.draw_mesh(
+            program,
+            input_layout,
+            &[parameters],
+            mesh,
+            MeshDraw::Range(range, None),
+            state,
+        )
+    }
+
+    /// Render a mesh once for each instance in the instance buffer, in a single draw call.
+    /// The instance buffer must be created with the input layout's instance layout.
+    pub fn render_mesh_instanced(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        mesh: &Mesh,
+        instances: &VertexBuffer,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.render_mesh_range_instanced(
+            program,
+            input_layout,
+            parameters,
+            mesh,
+            mesh.full_range(),
+            instances,
+            state,
+        )
+    }
+
+    /// Render a range of a mesh once for each instance in the instance buffer,
+    /// in a single draw call.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_mesh_range_instanced(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        mesh: &Mesh,
+        range: MeshRange,
+        instances: &VertexBuffer,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.draw_mesh(
+            program,
+            input_layout,
+            &[parameters],
+            mesh,
+            MeshDraw::Range(range, Some(Instances::Buffer(instances))),
+            state,
+        )
+    }
+
+    /// Render a mesh once for each of `instance_count` instances written to a ring buffer,
+    /// in a single draw call.
+    /// `offset` is the byte offset of the first instance, as returned by `RingBuffer::write`.
+    /// The instances must be written with the input layout's instance layout.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_mesh_instanced_from_ring(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        mesh: &Mesh,
+        instances: &RingBuffer<u8>,
+        offset: usize,
+        instance_count: usize,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.draw_mesh(
+            program,
+            input_layout,
+            &[parameters],
+            mesh,
+            MeshDraw::Range(
+                mesh.full_range(),
+                Some(Instances::Ring(instances, offset, instance_count)),
+            ),
+            state,
+        )
+    }
+
+    /// Render every draw of an indirect batch from a mesh pool with a single
+    /// `glMultiDrawElementsIndirect` call.
+    /// Shaders read the model matrix and parameters of each draw with
+    /// `ShaderInputs::draw_model_matrix` and `ShaderInputs::draw_parameters`.
+    /// Returns an error if the batch has not been uploaded since it was last changed.
+    pub fn render_indirect(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &RenderParameters,
+        pool: &MeshPool,
+        batch: &IndirectBatch,
+        state: &PipelineState,
+    ) -> Result<()> {
+        self.draw_mesh(
+            program,
+            input_layout,
+            &[parameters],
+            pool.mesh(),
+            MeshDraw::Indirect(batch),
+            state,
+        )
+    }
+
+    /// Render a mesh with a material, using the material's program and pipeline state.
+    /// `parameters` are layers that override the material's default parameters, such as the
+    /// built-in parameters and a renderer's parameters. Earlier layers override later ones.
+    pub fn render_mesh_with_material(
+        &self,
+        material: &ResolvedMaterial,
+        input_layout: &InputLayout,
+        parameters: &[&RenderParameters],
+        mesh: &Mesh,
+    ) -> Result<()> {
+        self.draw_mesh(
+            material.program(),
+            input_layout,
+            &material_layers(material, parameters),
+            mesh,
+            MeshDraw::Range(mesh.full_range(), None),
+            material.pipeline_state(),
+        )
+    }
+
+    /// Render a mesh with a material once for each instance in the instance buffer,
+    /// in a single draw call.
+    /// `parameters` are layers that override the material's default parameters.
+    pub fn render_mesh_instanced_with_material(
+        &self,
+        material: &ResolvedMaterial,
+        input_layout: &InputLayout,
+        parameters: &[&RenderParameters],
+        mesh: &Mesh,
+        instances: &VertexBuffer,
+    ) -> Result<()> {
+        self.draw_mesh(
+            material.program(),
+            input_layout,
+            &material_layers(material, parameters),
+            mesh,
+            MeshDraw::Range(mesh.full_range(), Some(Instances::Buffer(instances))),
+            material.pipeline_state(),
+        )
+    }
+
+    /// Render a mesh with a direct or indirect draw call.
+    /// Each parameter is taken from the first layer of `parameters` that sets it.
+    fn draw_mesh(
+        &self,
+        program: &Program,
+        input_layout: &InputLayout,
+        parameters: &[&RenderParameters],
+        mesh: &Mesh,
+        draw: MeshDraw,
+        state: &PipelineState,
+    ) -> Result<()> {
         let index_buffer = mesh.index_buffer();
-        let index_count = mesh.index_count();
 
-        // Return early if index_count == 0.
-        if index_count == 0 {
-            return Ok(());
-        }
+        // Validate the draw and return early if there is nothing to draw.
+        let (instances, instance_count) = match draw {
+            MeshDraw::Range(range, instances) => {
+                // Validate the instance buffer and find the instances it holds.
+                let (instances, instance_count) = match instances {
+                    Some(Instances::Buffer(instances)) => {
+                        input_layout.validate_instance_buffer(instances)?;
+                        let count = instances.len() / input_layout.instance_byte_stride();
+                        (Some((instances.handle(), 0)), count)
+                    }
+                    Some(Instances::Ring(instances, offset, count)) => {
+                        if input_layout.instance_layout().is_none() {
+                            anyhow::bail!("Input layout has no instance layout.");
+                        }
+                        if offset + count * input_layout.instance_byte_stride()
+                            > instances.capacity()
+                        {
+                            anyhow::bail!("Instance range is greater than the ring buffer length.");
+                        }
+                        (Some((instances.handle(), offset)), count)
+                    }
+                    None => (None, 1),
+                };
+                if range.count == 0 || instance_count == 0 {
+                    return Ok(());
+                }
 
-        // Validate the index count.
-        if index_count > index_buffer.len() {
-            anyhow::bail!("Index count is greater than the buffer length.");
-        }
-        if index_count % 3 != 0 {
-            anyhow::bail!("Index count is not a multiple of 3.");
-        }
+                // Validate the range.
+                if range.first + range.count > mesh.draw_count() {
+                    anyhow::bail!("Draw range is greater than the buffer length.");
+                }
+                mesh.topology()
+                    .validate_count(range.count, mesh.primitive_restart())?;
+                (instances, instance_count)
+            }
+            MeshDraw::Indirect(batch) => {
+                if !batch.is_uploaded() {
+                    anyhow::bail!("Indirect batch must be uploaded before rendering.");
+                }
+                if input_layout.instance_layout().is_some() {
+                    anyhow::bail!("Indirect draws do not support instance layouts.");
+                }
+                if batch.is_empty() {
+                    return Ok(());
+                }
 
-        // Validate the vertex buffer.
-        input_layout.validate_buffer(vertex_buffer)?;
+                // Validate the range of each draw.
+                for command in batch.commands() {
+                    if (command.first_index + command.count) as usize > mesh.draw_count() {
+                        anyhow::bail!("Draw range is greater than the buffer length.");
+                    }
+                    mesh.topology()
+                        .validate_count(command.count as usize, mesh.primitive_restart())?;
+                }
+                (None, 1)
+            }
+        };
+
+        // Find the vertex buffer for each stream of the input layout.
+        let vertex_buffers = input_layout.match_mesh_streams(mesh)?;
 
         unsafe {
+            // Check that the cached bindings match the GL state in debug builds.
+            GlState::__verify();
+
             // Enable the attributes in the input layout.
             input_layout.__enable_attributes();
 
-            // Bind this target buffer.
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.handle);
-            gl::BindVertexArray(input_layout.vertex_array_handle());
-            gl::BindVertexBuffer(
-                _VERTEX_BUFFER_LOCATION,
-                vertex_buffer.handle(),
-                0,
-                input_layout.byte_stride() as i32,
-            );
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer.handle());
+            // Bind this target buffer and the mesh's buffers, if they are not already bound.
+            let vertex_array = input_layout.vertex_array_handle();
+            GlState::__bind_draw_framebuffer(self.handle);
+            GlState::__bind_vertex_array(vertex_array);
+            for (stream, vertex_buffer) in vertex_buffers.iter().enumerate() {
+                GlState::__vertex_array_vertex_buffer(
+                    vertex_array,
+                    _VERTEX_BUFFER_LOCATION + stream as u32,
+                    vertex_buffer.handle(),
+                    0,
+                    input_layout.stream_byte_stride(stream) as i32,
+                );
+            }
+            if let Some((instances, offset)) = instances {
+                GlState::__vertex_array_vertex_buffer(
+                    vertex_array,
+                    _INSTANCE_BUFFER_LOCATION,
+                    instances,
+                    offset as isize,
+                    input_layout.instance_byte_stride() as i32,
+                );
+            }
+            if let Some(index_buffer) = index_buffer {
+                GlState::__vertex_array_element_buffer(vertex_array, index_buffer.handle());
+            }
+
+            // Apply the pipeline state.
+            state.__apply();
+
+            // Set the primitive assembly state of the mesh.
+            GlState::__set_primitive_restart(mesh.primitive_restart());
+            if let PrimitiveTopology::Patches(vertices) = mesh.topology() {
+                gl::PatchParameteri(gl::PATCH_VERTICES, vertices as i32);
+            }
 
             // Use the program.
-            gl::UseProgram(program.handle());
+            GlState::__use_program(program.handle());
 
             // Use the parameters.
             program.use_parameters(parameters)?;
 
             // Draw call.
-            gl::DrawElements(
-                gl::TRIANGLES,
-                index_count as i32,
-                gl::UNSIGNED_INT,
+            let mode = mesh.topology().gl_mode();
+            match (draw, index_buffer) {
+                (MeshDraw::Range(range, _), Some(index_buffer)) => {
+                    let index_type = index_buffer.index_type();
+                    gl::DrawElementsInstancedBaseVertex(
+                        mode,
+                        range.count as i32,
+                        index_type.gl_type(),
+                        (range.first * index_type.byte_size()) as *const _,
+                        instance_count as i32,
+                        range.base_vertex,
+                    );
+                }
+                (MeshDraw::Range(range, _), None) => gl::DrawArraysInstanced(
+                    mode,
+                    range.first as i32 + range.base_vertex,
+                    range.count as i32,
+                    instance_count as i32,
+                ),
+                (MeshDraw::Indirect(batch), Some(index_buffer)) => {
+                    // Bind the draw commands and the per-draw data.
+                    GlState::__bind_draw_indirect_buffer(batch.command_buffer().handle());
+                    GlState::__bind_shader_storage_buffer(
+                        _DRAW_DATA_BUFFER_BINDING,
+                        batch.draw_data_buffer().handle(),
+                    );
+
+                    gl::MultiDrawElementsIndirect(
+                        mode,
+                        index_buffer.index_type().gl_type(),
+                        std::ptr::null(),
+                        batch.len() as i32,
+                        0,
+                    );
+                }
+                (MeshDraw::Indirect(_), None) => unreachable!("Mesh pools are always indexed"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the pixels in a region of the given color attachment into an image.
+    /// `min` is the bottom-left corner of the region in pixels.
+    /// The default framebuffer only has color attachment 0 (the back buffer).
+    /// The returned image has the top row first.
+    pub fn read_pixels(
+        &self,
+        attachment: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+    ) -> Result<image::RgbaImage> {
+        let mut pixels = vec![0u8; readback::byte_count(size)];
+
+        unsafe {
+            // Read into client memory rather than a pixel pack buffer.
+            GlState::__bind_pixel_pack_buffer(0);
+            self.__read_pixels(attachment, min, size, pixels.as_mut_ptr() as *mut _)?;
+        }
+
+        readback::pixels_to_image(size, pixels)
+    }
+
+    /// Start reading the pixels in a region of the given color attachment without stalling.
+    /// The pixels are copied into a pixel buffer object; use `PixelReadback::is_ready` and
+    /// `PixelReadback::take` to retrieve the image on a later frame.
+    pub fn read_pixels_async(
+        &self,
+        attachment: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+    ) -> Result<PixelReadback> {
+        unsafe {
+            // Create a buffer to copy the pixels into.
+            let mut buffer_handle = 0;
+            gl::CreateBuffers(1, &mut buffer_handle);
+            gl::NamedBufferData(
+                buffer_handle,
+                readback::byte_count(size) as isize,
                 std::ptr::null(),
+                gl::STREAM_READ,
             );
 
-            // Stop using the program.
-            gl::UseProgram(0);
+            // Copy the pixels into the buffer.
+            GlState::__bind_pixel_pack_buffer(buffer_handle);
+            let result = self.__read_pixels(attachment, min, size, std::ptr::null_mut());
+            GlState::__bind_pixel_pack_buffer(0);
+
+            // Delete the buffer if the copy failed.
+            if let Err(e) = result {
+                gl::DeleteBuffers(1, &buffer_handle);
+                GlState::__forget_buffer(buffer_handle);
+                return Err(e);
+            }
 
-            // Unbind everything.
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            gl::BindVertexBuffer(_VERTEX_BUFFER_LOCATION, 0, 0, 0);
-            gl::BindVertexArray(0);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            Ok(PixelReadback::__new(buffer_handle, size))
+        }
+    }
+
+    /// Save the pixels of color attachment 0 to a PNG file.
+    /// `size` should be the size of the buffer in pixels.
+    pub fn save_png(&self, path: impl AsRef<Path>, size: Vector2<u32>) -> Result<()> {
+        let path = path.as_ref();
+        let image = self.read_pixels(0, Vector::zero(), size)?;
+
+        image
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| anyhow::anyhow!("Failed to save image file {:?}: {:?}", path, e))
+    }
+
+    /// Read the pixels in a region of the given color attachment as tightly packed RGBA8,
+    /// with the bottom row first.
+    /// If a pixel pack buffer is bound, `pixels` is an offset into that buffer.
+    /// # Safety
+    /// This function is unsafe because `pixels` must point to enough memory for the region.
+    unsafe fn __read_pixels(
+        &self,
+        attachment: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+        pixels: *mut c_void,
+    ) -> Result<()> {
+        // Validate the region and attachment.
+        if size.x() == 0 || size.y() == 0 {
+            anyhow::bail!("Cannot read pixels from an empty region.");
+        }
+        if self.handle == 0 && attachment != 0 {
+            anyhow::bail!("The default framebuffer only has color attachment 0.");
+        }
+
+        // Get the buffer to read from.
+        let read_buffer = if self.handle == 0 {
+            gl::BACK
+        } else {
+            gl::COLOR_ATTACHMENT0 + attachment as u32
+        };
+
+        unsafe {
+            // Bind this target buffer for reading.
+            GlState::__verify();
+            gl::NamedFramebufferReadBuffer(self.handle, read_buffer);
+            GlState::__bind_read_framebuffer(self.handle);
+
+            // Read the pixels without row padding.
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                min.x() as i32,
+                min.y() as i32,
+                size.x() as i32,
+                size.y() as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels,
+            );
         }
 
         Ok(())
@@ -152,6 +593,36 @@ impl TargetBuffer {
     }
 }
 
+/// Get the parameter layers followed by the default parameters of the material.
+fn material_layers<'a>(
+    material: &ResolvedMaterial<'a>,
+    parameters: &[&'a RenderParameters],
+) -> Vec<&'a RenderParameters> {
+    parameters
+        .iter()
+        .chain(material.parameters())
+        .copied()
+        .collect()
+}
+
+/// What a draw call of `TargetBuffer::draw_mesh` draws.
+#[derive(Clone, Copy)]
+enum MeshDraw<'a> {
+    /// A range of the mesh, once for each instance if there are instances.
+    Range(MeshRange, Option<Instances<'a>>),
+    /// Every draw of an indirect batch.
+    Indirect(&'a IndirectBatch),
+}
+
+/// Where the instances of a `MeshDraw::Range` are read from.
+#[derive(Clone, Copy)]
+enum Instances<'a> {
+    /// Every instance in an instance buffer.
+    Buffer(&'a VertexBuffer),
+    /// A number of instances starting at a byte offset of a ring buffer.
+    Ring(&'a RingBuffer<u8>, usize, usize),
+}
+
 impl Default for TargetBuffer {
     fn default() -> Self {
         Self::DEFAULT
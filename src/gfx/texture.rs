@@ -1,16 +1,32 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 use anyhow::Result;
 use ggmath::prelude::*;
 use image::GenericImageView;
 
+use super::{
+    readback,
+    sampler::{MipmapMode, Mipmaps, SamplerSettings, TextureOptions, WrapMode},
+    texture_format::TextureFormat,
+    texture_metadata::TextureMetadata,
+};
+
 /// A GL texture.
 pub struct Texture {
     handle: u32,
     texture_type: TextureType,
+    format: TextureFormat,
     dimensions: Vec<Vector2<u32>>,
+    sampler: SamplerSettings,
     regions: Option<HashMap<String, TextureRegion>>,
     glyphs: Option<HashMap<char, TextureGlyph>>,
+    shadow: Option<TextureShadow>,
+}
+
+/// A CPU copy of LOD 0 of a texture, with the region that has not been uploaded yet.
+struct TextureShadow {
+    image: image::RgbaImage,
+    dirty: Option<(Vector2<u32>, Vector2<u32>)>,
 }
 
 impl !Send for Texture {}
@@ -18,6 +34,8 @@ impl !Sync for Texture {}
 
 impl Texture {
     /// Create a new texture from an image.
+    /// If a single LOD is provided, the rest of the mip chain is generated as described by
+    /// `options.mipmaps`.
     /// # Safety
     /// This function is unsafe because it should only be used on the main thread.
     pub(crate) unsafe fn __from_image(
@@ -26,6 +44,7 @@ impl Texture {
         lods: &[image::DynamicImage],
         regions: Option<HashMap<String, TextureRegion>>,
         glyphs: Option<HashMap<char, TextureGlyph>>,
+        options: TextureOptions,
     ) -> Result<Self> {
         let name = name.as_ref();
 
@@ -34,6 +53,14 @@ impl Texture {
             anyhow::bail!("No LODs provided for texture {}", name);
         }
 
+        // Generate the mip chain on the CPU if requested.
+        let lods = match options.mipmaps {
+            Mipmaps::Cpu(filter) if lods.len() == 1 => {
+                Cow::Owned(Mipmaps::generate_cpu(lods[0].clone(), filter))
+            }
+            _ => Cow::Borrowed(lods),
+        };
+
         // Ensure that each LOD has the same format as the first LOD.
         for (i, lod) in lods.iter().enumerate() {
             if lod.color() != lods[0].color() {
@@ -65,11 +92,32 @@ impl Texture {
             }
         }
 
-        // Get the dimensions of each LOD.
-        let dimensions = lods
+        // Get the dimensions of each LOD, including the LODs generated on the GPU.
+        let generate_on_gpu = options.mipmaps == Mipmaps::Gpu && lods.len() == 1;
+        let dimensions: Vec<Vector2<u32>> = if generate_on_gpu {
+            let size = vector!(lods[0].width(), lods[0].height());
+            (0..Mipmaps::full_chain_length(size))
+                .map(|i| vector!((size.x() >> i).max(1), (size.y() >> i).max(1)))
+                .collect()
+        } else {
+            lods.iter()
+                .map(|lod| vector!(lod.width() as u32, lod.height() as u32))
+                .collect()
+        };
+
+        // Convert each LOD to the texture format.
+        let format = options.format;
+        let (pixel_format, pixel_type) = format.upload_format().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Failed to load {}: cannot load an image into format {:?}",
+                name,
+                format
+            )
+        })?;
+        let lod_bytes = lods
             .iter()
-            .map(|lod| vector!(lod.width() as u32, lod.height() as u32))
-            .collect();
+            .map(|lod| format.image_to_bytes(lod))
+            .collect::<Result<Vec<_>>>()?;
 
         // Create the texture.
         unsafe {
@@ -77,14 +125,15 @@ impl Texture {
             gl::CreateTextures(gl::TEXTURE_2D, 1, &mut handle);
             gl::TextureStorage2D(
                 handle,
-                lods.len() as i32,
-                gl::RGBA8,
+                dimensions.len() as i32,
+                format.internal_format(),
                 lods[0].width() as i32,
                 lods[0].height() as i32,
             );
 
-            for (i, lod) in lods.iter().enumerate() {
-                let lod = lod.to_rgba8();
+            // Upload without row padding, as single channel rows may not be 4-byte aligned.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (i, (lod, bytes)) in lods.iter().zip(&lod_bytes).enumerate() {
                 gl::TextureSubImage2D(
                     handle,
                     i as i32,
@@ -92,19 +141,411 @@ impl Texture {
                     0,
                     lod.width() as i32,
                     lod.height() as i32,
-                    gl::RGBA,
-                    gl::UNSIGNED_BYTE,
-                    lod.as_ptr() as *const _,
+                    pixel_format,
+                    pixel_type,
+                    bytes.as_ptr() as *const _,
                 );
             }
 
-            Ok(Self {
+            // Generate the rest of the mip chain on the GPU if requested.
+            if generate_on_gpu {
+                gl::GenerateTextureMipmap(handle);
+            }
+
+            let mut texture = Self {
                 handle,
                 texture_type,
+                format,
                 dimensions,
+                sampler: options.sampler,
                 regions,
                 glyphs,
-            })
+                shadow: None,
+            };
+            texture.set_sampler(options.sampler);
+
+            Ok(texture)
+        }
+    }
+
+    /// Create a new empty texture with the given format, size and a single LOD.
+    /// The contents of the texture are undefined until it is rendered to.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __new_empty(
+        name: impl AsRef<str>,
+        texture_type: TextureType,
+        format: TextureFormat,
+        size: Vector2<u32>,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+
+        // Ensure that the size is valid.
+        if size.x() == 0 || size.y() == 0 {
+            anyhow::bail!(
+                "Failed to create {}: size ({}, {}) must not be zero",
+                name,
+                size.x(),
+                size.y()
+            );
+        }
+
+        // Create the texture.
+        unsafe {
+            let mut handle = 0;
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut handle);
+            gl::TextureStorage2D(
+                handle,
+                1,
+                format.internal_format(),
+                size.x() as i32,
+                size.y() as i32,
+            );
+
+            // Render targets are usually sampled 1:1, so clamp to the edges.
+            let sampler = SamplerSettings::default().with_wrap(WrapMode::ClampToEdge);
+            let mut texture = Self {
+                handle,
+                texture_type,
+                format,
+                dimensions: vec![size],
+                sampler,
+                regions: None,
+                glyphs: None,
+                shadow: None,
+            };
+            texture.set_sampler(sampler);
+
+            Ok(texture)
+        }
+    }
+
+    /// Get the format the texels are stored in.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Get the sampler settings used by views that do not override them.
+    pub fn sampler(&self) -> &SamplerSettings {
+        &self.sampler
+    }
+
+    /// Set the sampler settings used by views that do not override them.
+    /// If the texture has a single LOD, the mipmap mode is ignored so the texture stays complete.
+    pub fn set_sampler(&mut self, sampler: SamplerSettings) {
+        self.sampler = sampler;
+
+        let mut effective = sampler;
+        if self.lod_count() == 1 {
+            effective.mipmap_mode = MipmapMode::None;
+        }
+        unsafe {
+            effective.__apply_to_texture(self.handle);
+        }
+    }
+
+    /// Regenerate LODs 1 and up from LOD 0 on the GPU.
+    /// This is useful after rendering to or updating LOD 0.
+    pub fn generate_mipmaps(&self) {
+        if self.lod_count() > 1 {
+            unsafe {
+                gl::GenerateTextureMipmap(self.handle);
+            }
+        }
+    }
+
+    /// Overwrite a region of the given LOD with an image, with `min` as the top-left pixel.
+    /// The image is converted to the texture's format by GL.
+    /// Call `generate_mipmaps` afterwards to update the other LODs.
+    pub fn update_region(
+        &mut self,
+        lod: usize,
+        min: Vector2<u32>,
+        image: &image::RgbaImage,
+    ) -> Result<()> {
+        let size = vector!(image.width(), image.height());
+        self.validate_region(lod, min, size)?;
+
+        unsafe {
+            self.__upload(lod, min, size, gl::RGBA, gl::UNSIGNED_BYTE, image.as_ptr());
+        }
+
+        // Keep the shadow copy in sync.
+        if lod == 0 {
+            if let Some(shadow) = &mut self.shadow {
+                image::imageops::replace(&mut shadow.image, image, min.x() as i64, min.y() as i64);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Overwrite a region of the given LOD with raw texel bytes, with `min` as the top-left pixel.
+    /// The bytes must be tightly packed rows in the texture's format, with 32-bit float channels
+    /// for float formats. This avoids converting through an image, such as for video frames.
+    pub fn update_region_bytes(
+        &mut self,
+        lod: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+        bytes: &[u8],
+    ) -> Result<()> {
+        self.validate_region(lod, min, size)?;
+
+        // Ensure that the bytes match the region size.
+        let (pixel_format, pixel_type) = self.format.upload_format().ok_or_else(|| {
+            anyhow::anyhow!("Cannot upload bytes to depth format {:?}", self.format)
+        })?;
+        let expected = (size.x() * size.y()) as usize * self.format.upload_texel_size().unwrap();
+        if bytes.len() != expected {
+            anyhow::bail!(
+                "Expected {} bytes for a {}x{} region in format {:?}, got {}",
+                expected,
+                size.x(),
+                size.y(),
+                self.format,
+                bytes.len()
+            );
+        }
+
+        unsafe {
+            self.__upload(lod, min, size, pixel_format, pixel_type, bytes.as_ptr());
+        }
+
+        // The bytes are not RGBA, so refresh the shadow copy from the GPU.
+        if lod == 0 && self.shadow.is_some() {
+            self.refresh_shadow()?;
+        }
+
+        Ok(())
+    }
+
+    /// Clear every LOD of this texture to the given color.
+    pub fn clear(&mut self, color: Vector4<f32>) -> Result<()> {
+        if self.format.is_depth() {
+            anyhow::bail!("Cannot clear depth texture to a color");
+        }
+
+        unsafe {
+            for lod in 0..self.lod_count() {
+                gl::ClearTexImage(
+                    self.handle,
+                    lod as i32,
+                    gl::RGBA,
+                    gl::FLOAT,
+                    color.as_ptr() as *const _,
+                );
+            }
+        }
+
+        // Keep the shadow copy in sync.
+        if let Some(shadow) = &mut self.shadow {
+            let pixel = color_to_rgba8(color);
+            shadow.image.pixels_mut().for_each(|p| *p = pixel);
+            shadow.dirty = None;
+        }
+
+        Ok(())
+    }
+
+    /// Copy a region from a LOD of another texture into a LOD of this texture on the GPU.
+    /// `source_min` and `min` are the top-left pixels of the regions.
+    /// Both textures must have the same format.
+    pub fn copy_from(
+        &mut self,
+        source: &Texture,
+        source_lod: usize,
+        source_min: Vector2<u32>,
+        lod: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+    ) -> Result<()> {
+        if source.format != self.format {
+            anyhow::bail!(
+                "Cannot copy from format {:?} to format {:?}",
+                source.format,
+                self.format
+            );
+        }
+        source.validate_region(source_lod, source_min, size)?;
+        self.validate_region(lod, min, size)?;
+
+        unsafe {
+            gl::CopyImageSubData(
+                source.handle,
+                gl::TEXTURE_2D,
+                source_lod as i32,
+                source_min.x() as i32,
+                source_min.y() as i32,
+                0,
+                self.handle,
+                gl::TEXTURE_2D,
+                lod as i32,
+                min.x() as i32,
+                min.y() as i32,
+                0,
+                size.x() as i32,
+                size.y() as i32,
+                1,
+            );
+        }
+
+        // The copy happened on the GPU, so refresh the shadow copy from it.
+        if lod == 0 && self.shadow.is_some() {
+            self.refresh_shadow()?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep a CPU copy of LOD 0 for `get_pixel` and `set_pixel`, downloading the current contents.
+    /// Only 8-bit color formats can be shadowed, since the copy is stored as RGBA8.
+    pub fn enable_shadow(&mut self) -> Result<()> {
+        if self.format.is_depth() || self.format.is_float() {
+            anyhow::bail!("Cannot keep a shadow copy of format {:?}", self.format);
+        }
+
+        if self.shadow.is_none() {
+            self.shadow = Some(TextureShadow {
+                image: self.download(0)?,
+                dirty: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Stop keeping a CPU copy of LOD 0, discarding any changes that were not flushed.
+    pub fn disable_shadow(&mut self) {
+        self.shadow = None;
+    }
+
+    /// Check if this texture keeps a CPU copy of LOD 0.
+    pub fn has_shadow(&self) -> bool {
+        self.shadow.is_some()
+    }
+
+    /// Get the CPU copy of LOD 0, if enabled.
+    /// This includes changes from `set_pixel` that have not been flushed yet.
+    pub fn shadow(&self) -> Option<&image::RgbaImage> {
+        self.shadow.as_ref().map(|shadow| &shadow.image)
+    }
+
+    /// Get a pixel of LOD 0 from the CPU copy, with (0, 0) as the top-left pixel.
+    /// Returns `None` if the shadow copy is not enabled or the pixel is out of bounds.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<image::Rgba<u8>> {
+        let shadow = self.shadow.as_ref()?;
+        shadow.image.get_pixel_checked(x, y).copied()
+    }
+
+    /// Set a pixel of LOD 0 in the CPU copy, with (0, 0) as the top-left pixel.
+    /// Changes are uploaded by `flush_shadow`.
+    /// Returns an error if the shadow copy is not enabled or the pixel is out of bounds.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: image::Rgba<u8>) -> Result<()> {
+        let shadow = self
+            .shadow
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Texture does not have a shadow copy"))?;
+        let pixel = shadow
+            .image
+            .get_pixel_mut_checked(x, y)
+            .ok_or_else(|| anyhow::anyhow!("Pixel ({}, {}) is out of bounds", x, y))?;
+        *pixel = color;
+
+        // Grow the dirty region to include the pixel.
+        let (min, max) = shadow
+            .dirty
+            .unwrap_or((vector!(x, y), vector!(x + 1, y + 1)));
+        shadow.dirty = Some((
+            vector!(min.x().min(x), min.y().min(y)),
+            vector!(max.x().max(x + 1), max.y().max(y + 1)),
+        ));
+
+        Ok(())
+    }
+
+    /// Upload the region of the CPU copy changed by `set_pixel` to LOD 0.
+    /// Call `generate_mipmaps` afterwards to update the other LODs.
+    pub fn flush_shadow(&mut self) {
+        let Some((min, max)) = self.shadow.as_mut().and_then(|shadow| shadow.dirty.take()) else {
+            return;
+        };
+        let shadow = self.shadow.as_ref().unwrap();
+
+        unsafe {
+            // Upload the dirty rows of the shadow, skipping the pixels outside the region.
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, shadow.image.width() as i32);
+            let offset = ((min.y() * shadow.image.width() + min.x()) * 4) as usize;
+            self.__upload(
+                0,
+                min,
+                max - min,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                shadow.image.as_ptr().add(offset),
+            );
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+        }
+    }
+
+    /// Download LOD 0 into the shadow copy, discarding any changes that were not flushed.
+    fn refresh_shadow(&mut self) -> Result<()> {
+        let image = self.download(0)?;
+        if let Some(shadow) = &mut self.shadow {
+            shadow.image = image;
+            shadow.dirty = None;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the region is outside of the given LOD.
+    fn validate_region(&self, lod: usize, min: Vector2<u32>, size: Vector2<u32>) -> Result<()> {
+        let dimensions = self
+            .dimensions(lod)
+            .ok_or_else(|| anyhow::anyhow!("Texture does not have LOD {}", lod))?;
+        if min.x() + size.x() > dimensions.x() || min.y() + size.y() > dimensions.y() {
+            anyhow::bail!(
+                "Region at ({}, {}) with size ({}, {}) is outside of LOD {} ({}, {})",
+                min.x(),
+                min.y(),
+                size.x(),
+                size.y(),
+                lod,
+                dimensions.x(),
+                dimensions.y()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Upload texels into a region of the given LOD.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread,
+    /// and `pixels` must point to enough texels for the region.
+    unsafe fn __upload(
+        &self,
+        lod: usize,
+        min: Vector2<u32>,
+        size: Vector2<u32>,
+        pixel_format: u32,
+        pixel_type: u32,
+        pixels: *const u8,
+    ) {
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TextureSubImage2D(
+                self.handle,
+                lod as i32,
+                min.x() as i32,
+                min.y() as i32,
+                size.x() as i32,
+                size.y() as i32,
+                pixel_format,
+                pixel_type,
+                pixels as *const _,
+            );
         }
     }
 
@@ -119,6 +560,21 @@ impl Texture {
         self.dimensions.len()
     }
 
+    /// Get all texture regions, if this texture has any.
+    pub fn regions(&self) -> Option<&HashMap<String, TextureRegion>> {
+        self.regions.as_ref()
+    }
+
+    /// Get all character glyphs, if this texture has any.
+    pub fn glyphs(&self) -> Option<&HashMap<char, TextureGlyph>> {
+        self.glyphs.as_ref()
+    }
+
+    /// Get the regions and glyphs of this texture, for saving to a sidecar file.
+    pub fn metadata(&self) -> TextureMetadata {
+        TextureMetadata::new(self.regions.clone(), self.glyphs.clone())
+    }
+
     /// Get the given texture region.
     /// Returns `None` if the region does not exist.
     pub fn region(&self, name: impl AsRef<str>) -> Option<&TextureRegion> {
@@ -171,6 +627,36 @@ impl Texture {
         Some((glyph.region().min_lod(), glyph.region().max_lod()))
     }
 
+    /// Download the given LOD of this texture into an RGBA8 image.
+    /// Other color formats are converted by GL; float values are clamped to 0.0 to 1.0.
+    /// Rows are returned in the order they were uploaded, so images loaded from files come back
+    /// top row first, while textures rendered to by a framebuffer come back bottom row first.
+    pub fn download(&self, lod: usize) -> Result<image::RgbaImage> {
+        if self.format.is_depth() {
+            anyhow::bail!("Cannot download depth texture as an image");
+        }
+        let size = self
+            .dimensions(lod)
+            .ok_or_else(|| anyhow::anyhow!("Texture does not have LOD {}", lod))?;
+        let mut pixels = vec![0u8; readback::byte_count(size)];
+
+        unsafe {
+            // Read the pixels without row padding.
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTextureImage(
+                self.handle,
+                lod as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.len() as i32,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+
+        image::RgbaImage::from_raw(size.x(), size.y(), pixels)
+            .ok_or_else(|| anyhow::anyhow!("Pixel data does not match the texture size"))
+    }
+
     /// Get the GL handle.
     pub fn handle(&self) -> u32 {
         self.handle
@@ -186,6 +672,7 @@ impl Texture {
         TextureView {
             texture_handle: self.handle,
             texture_type: self.texture_type,
+            sampler: None,
             min: Vector::zero(),
             max: Vector::one(),
         }
@@ -199,6 +686,7 @@ impl Texture {
         Some(TextureView {
             texture_handle: self.handle,
             texture_type: self.texture_type,
+            sampler: None,
             min: vector!(0.0, 0.0, lod as f32) / to_tex_coord_and_levels,
             max: vector!(1.0, 1.0, lod as f32) / to_tex_coord_and_levels,
         })
@@ -214,6 +702,7 @@ impl Texture {
         Some(TextureView {
             texture_handle: self.handle,
             texture_type: self.texture_type,
+            sampler: None,
             min: min.append(min_lod as f32),
             max: max.append(max_lod as f32),
         })
@@ -228,6 +717,7 @@ impl Texture {
         Some(TextureView {
             texture_handle: self.handle,
             texture_type: self.texture_type,
+            sampler: None,
             min: min.append(min_lod as f32),
             max: max.append(max_lod as f32),
         })
@@ -245,7 +735,8 @@ impl Drop for Texture {
     }
 }
 
-/// Represents a type of texture.
+/// Represents the semantic type of a texture, such as color or normal data.
+/// This is metadata only; texture units are allocated per draw.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureType {
     Invalid,
@@ -256,20 +747,6 @@ pub enum TextureType {
     Emissive,
 }
 
-impl TextureType {
-    /// Convert to a texture unit index.
-    pub(crate) fn texture_unit_index(&self) -> u32 {
-        match self {
-            TextureType::Invalid => panic!("Invalid texture type"),
-            TextureType::Color => 0,
-            TextureType::Normal => 1,
-            TextureType::Metallic => 2,
-            TextureType::Roughness => 3,
-            TextureType::Emissive => 4,
-        }
-    }
-}
-
 /// Represents a region within a texture.
 /// The X and Y axes correspond to the image's pixels.
 /// The Z axis corresponds to the LOD level.
@@ -313,6 +790,24 @@ impl TextureRegion {
         Self { min, max }
     }
 
+    /// Create the regions of a regular grid of equally sized cells, such as a sprite sheet.
+    /// The regions are ordered left to right, then top to bottom, and use LOD 0 only.
+    pub fn grid(
+        top_left: Vector2<i32>,
+        cell_size: Vector2<i32>,
+        columns: u32,
+        rows: u32,
+    ) -> Vec<Self> {
+        (0..rows as i32)
+            .flat_map(|row| {
+                (0..columns as i32).map(move |column| {
+                    let cell = vector!(column * cell_size.x(), row * cell_size.y());
+                    Self::new(top_left + cell, cell_size, 0, 1)
+                })
+            })
+            .collect()
+    }
+
     /// Get the minimum coordinates.
     pub const fn min(&self) -> Vector3<i32> {
         self.min
@@ -403,6 +898,7 @@ impl AsRef<TextureRegion> for TextureGlyph {
 pub struct TextureView {
     texture_handle: u32,
     texture_type: TextureType,
+    sampler: Option<SamplerSettings>,
     min: Vector3<f32>,
     max: Vector3<f32>,
 }
@@ -421,6 +917,17 @@ impl TextureView {
         self.texture_type
     }
 
+    /// Sample this view with the given sampler settings instead of the texture's.
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Get the sampler settings that override the texture's, if any.
+    pub fn sampler(&self) -> Option<&SamplerSettings> {
+        self.sampler.as_ref()
+    }
+
     /// Get the minimum coordinates.
     pub fn min(&self) -> Vector3<f32> {
         self.min
@@ -457,8 +964,20 @@ impl Default for TextureView {
         Self {
             texture_handle: 0,
             texture_type: TextureType::Invalid,
+            sampler: None,
             min: Vector::zero(),
             max: Vector::one(),
         }
     }
 }
+
+/// Convert a color with components from 0.0 to 1.0 into an RGBA8 pixel.
+fn color_to_rgba8(color: Vector4<f32>) -> image::Rgba<u8> {
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    image::Rgba([
+        to_u8(color.x()),
+        to_u8(color.y()),
+        to_u8(color.z()),
+        to_u8(color.w()),
+    ])
+}
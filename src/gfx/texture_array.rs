@@ -0,0 +1,495 @@
+use anyhow::Result;
+use ggmath::prelude::*;
+use image::GenericImageView;
+
+use super::{
+    sampler::{MipmapMode, Mipmaps, SamplerSettings, TextureOptions},
+    texture::TextureRegion,
+    texture_format::TextureFormat,
+};
+
+/// A GL 2D array texture, where each layer is a separate image of the same size.
+/// Layers are sampled by index, so neighbouring layers never bleed into each other.
+pub struct TextureArray {
+    handle: u32,
+    format: TextureFormat,
+    size: Vector2<u32>,
+    layer_count: u32,
+    lod_count: usize,
+    sampler: SamplerSettings,
+}
+
+impl !Send for TextureArray {}
+impl !Sync for TextureArray {}
+
+impl TextureArray {
+    /// Create a new texture array from a list of equally sized images, one per layer.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_images(
+        name: impl AsRef<str>,
+        layers: &[image::DynamicImage],
+        options: TextureOptions,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+
+        // Ensure that there is at least one layer and that all layers are equally sized.
+        if layers.is_empty() {
+            anyhow::bail!("No layers provided for texture array {}", name);
+        }
+        let size = vector!(layers[0].width(), layers[0].height());
+        for (i, layer) in layers.iter().enumerate() {
+            if layer.dimensions() != (size.x(), size.y()) {
+                anyhow::bail!(
+                    "Failed to load {}: layer {} is {}x{}, expected {}x{}",
+                    name,
+                    i,
+                    layer.width(),
+                    layer.height(),
+                    size.x(),
+                    size.y()
+                );
+            }
+        }
+
+        // Get the number of LODs.
+        let lod_count = match options.mipmaps {
+            Mipmaps::None => 1,
+            Mipmaps::Gpu | Mipmaps::Cpu(_) => Mipmaps::full_chain_length(size),
+        };
+
+        // Generate the mip chain of each layer on the CPU if requested.
+        let layer_lods = layers
+            .iter()
+            .map(|layer| match options.mipmaps {
+                Mipmaps::Cpu(filter) => Mipmaps::generate_cpu(layer.clone(), filter),
+                Mipmaps::None | Mipmaps::Gpu => vec![layer.clone()],
+            })
+            .collect::<Vec<_>>();
+
+        // Create the texture array.
+        let handle = unsafe {
+            __create_layered(
+                name,
+                gl::TEXTURE_2D_ARRAY,
+                size.append(layers.len() as u32),
+                lod_count,
+                &layer_lods,
+                options,
+            )?
+        };
+
+        let mut texture_array = Self {
+            handle,
+            format: options.format,
+            size,
+            layer_count: layers.len() as u32,
+            lod_count,
+            sampler: options.sampler,
+        };
+        texture_array.set_sampler(options.sampler);
+
+        Ok(texture_array)
+    }
+
+    /// Create a new texture array from regions of a single image, one layer per region.
+    /// The regions must be equally sized, such as those returned by `TextureRegion::grid`.
+    /// The LOD levels of the regions are ignored.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_regions(
+        name: impl AsRef<str>,
+        image: &image::DynamicImage,
+        regions: &[TextureRegion],
+        options: TextureOptions,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+        let layers = regions
+            .iter()
+            .map(|region| crop_region(name, image, region))
+            .collect::<Result<Vec<_>>>()?;
+
+        unsafe { Self::__from_images(name, &layers, options) }
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Get the format the texels are stored in.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Get the width and height of each layer at LOD 0, in pixels.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Get the number of layers in this texture array.
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    /// Get the number of LODs in this texture array.
+    pub fn lod_count(&self) -> usize {
+        self.lod_count
+    }
+
+    /// Get the sampler settings used by views that do not override them.
+    pub fn sampler(&self) -> &SamplerSettings {
+        &self.sampler
+    }
+
+    /// Set the sampler settings used by views that do not override them.
+    /// If the texture array has a single LOD, the mipmap mode is ignored so it stays complete.
+    pub fn set_sampler(&mut self, sampler: SamplerSettings) {
+        self.sampler = sampler;
+
+        let mut effective = sampler;
+        if self.lod_count == 1 {
+            effective.mipmap_mode = MipmapMode::None;
+        }
+        unsafe {
+            effective.__apply_to_texture(self.handle);
+        }
+    }
+
+    /// Get a `TextureArrayView` to the given layer.
+    /// Returns `None` if the layer does not exist.
+    pub fn layer_view(&self, layer: u32) -> Option<TextureArrayView> {
+        if layer >= self.layer_count {
+            return None;
+        }
+
+        Some(TextureArrayView {
+            texture_handle: self.handle,
+            sampler: None,
+            layer,
+        })
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.handle);
+        }
+    }
+}
+
+/// A GL 3D texture, built from a stack of equally sized slices.
+pub struct Texture3D {
+    handle: u32,
+    format: TextureFormat,
+    size: Vector3<u32>,
+    lod_count: usize,
+    sampler: SamplerSettings,
+}
+
+impl !Send for Texture3D {}
+impl !Sync for Texture3D {}
+
+impl Texture3D {
+    /// Create a new 3D texture from a list of equally sized slices, ordered by depth.
+    /// Slices are filtered together when sampled, so the mip chain always shrinks all three axes.
+    /// `Mipmaps::Cpu` cannot filter across slices, so it is generated on the GPU instead.
+    /// # Safety
+    /// This function is unsafe because it should only be used on the main thread.
+    pub(crate) unsafe fn __from_slices(
+        name: impl AsRef<str>,
+        slices: &[image::DynamicImage],
+        mut options: TextureOptions,
+    ) -> Result<Self> {
+        let name = name.as_ref();
+
+        // Ensure that there is at least one slice and that all slices are equally sized.
+        if slices.is_empty() {
+            anyhow::bail!("No slices provided for 3D texture {}", name);
+        }
+        let size = vector!(slices[0].width(), slices[0].height(), slices.len() as u32);
+        for (i, slice) in slices.iter().enumerate() {
+            if slice.dimensions() != (size.x(), size.y()) {
+                anyhow::bail!(
+                    "Failed to load {}: slice {} is {}x{}, expected {}x{}",
+                    name,
+                    i,
+                    slice.width(),
+                    slice.height(),
+                    size.x(),
+                    size.y()
+                );
+            }
+        }
+
+        // Get the number of LODs, which depends on the largest axis.
+        if let Mipmaps::Cpu(_) = options.mipmaps {
+            options.mipmaps = Mipmaps::Gpu;
+        }
+        let lod_count = match options.mipmaps {
+            Mipmaps::None => 1,
+            Mipmaps::Gpu | Mipmaps::Cpu(_) => {
+                Mipmaps::full_chain_length(vector!(size.x().max(size.z()), size.y()))
+            }
+        };
+
+        // Create the 3D texture.
+        let slice_lods = slices
+            .iter()
+            .map(|slice| vec![slice.clone()])
+            .collect::<Vec<_>>();
+        let handle = unsafe {
+            __create_layered(name, gl::TEXTURE_3D, size, lod_count, &slice_lods, options)?
+        };
+
+        let mut texture = Self {
+            handle,
+            format: options.format,
+            size,
+            lod_count,
+            sampler: options.sampler,
+        };
+        texture.set_sampler(options.sampler);
+
+        Ok(texture)
+    }
+
+    /// Get the GL handle.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Get the format the texels are stored in.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Get the width, height and depth of this texture at LOD 0, in pixels.
+    pub fn size(&self) -> Vector3<u32> {
+        self.size
+    }
+
+    /// Get the number of LODs in this texture.
+    pub fn lod_count(&self) -> usize {
+        self.lod_count
+    }
+
+    /// Get the sampler settings used by views that do not override them.
+    pub fn sampler(&self) -> &SamplerSettings {
+        &self.sampler
+    }
+
+    /// Set the sampler settings used by views that do not override them.
+    /// If the texture has a single LOD, the mipmap mode is ignored so it stays complete.
+    pub fn set_sampler(&mut self, sampler: SamplerSettings) {
+        self.sampler = sampler;
+
+        let mut effective = sampler;
+        if self.lod_count == 1 {
+            effective.mipmap_mode = MipmapMode::None;
+        }
+        unsafe {
+            effective.__apply_to_texture(self.handle);
+        }
+    }
+
+    /// Get a `Texture3DView` for sampling this texture.
+    pub fn view(&self) -> Texture3DView {
+        Texture3DView {
+            texture_handle: self.handle,
+            sampler: None,
+        }
+    }
+}
+
+impl Drop for Texture3D {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.handle);
+        }
+    }
+}
+
+/// Represents a view of a single layer of a texture array, for sampling.
+/// Shaders can sample the view's layer or any other layer by index.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextureArrayView {
+    texture_handle: u32,
+    sampler: Option<SamplerSettings>,
+    layer: u32,
+}
+
+impl !Send for TextureArrayView {}
+impl !Sync for TextureArrayView {}
+
+impl TextureArrayView {
+    /// Get the texture array handle.
+    pub fn handle(&self) -> u32 {
+        self.texture_handle
+    }
+
+    /// Get the layer this view identifies.
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
+
+    /// Identify a different layer of the same texture array, such as the next animation frame.
+    /// The layer is not checked against the texture array's layer count.
+    pub fn with_layer(mut self, layer: u32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Sample this view with the given sampler settings instead of the texture array's.
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Get the sampler settings that override the texture array's, if any.
+    pub fn sampler(&self) -> Option<&SamplerSettings> {
+        self.sampler.as_ref()
+    }
+}
+
+/// Represents a view of a 3D texture, for sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Texture3DView {
+    texture_handle: u32,
+    sampler: Option<SamplerSettings>,
+}
+
+impl !Send for Texture3DView {}
+impl !Sync for Texture3DView {}
+
+impl Texture3DView {
+    /// Get the texture handle.
+    pub fn handle(&self) -> u32 {
+        self.texture_handle
+    }
+
+    /// Sample this view with the given sampler settings instead of the texture's.
+    pub fn with_sampler(mut self, sampler: SamplerSettings) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Get the sampler settings that override the texture's, if any.
+    pub fn sampler(&self) -> Option<&SamplerSettings> {
+        self.sampler.as_ref()
+    }
+}
+
+/// Crop a region out of an image, ensuring that it lies within the image.
+fn crop_region(
+    name: &str,
+    image: &image::DynamicImage,
+    region: &TextureRegion,
+) -> Result<image::DynamicImage> {
+    let min = region.min_pixel();
+    let size = region.pixel_size();
+    if min.x() < 0
+        || min.y() < 0
+        || size.x() <= 0
+        || size.y() <= 0
+        || (min.x() + size.x()) as u32 > image.width()
+        || (min.y() + size.y()) as u32 > image.height()
+    {
+        anyhow::bail!(
+            "Failed to load {}: region {:?} is outside of the {}x{} image",
+            name,
+            region,
+            image.width(),
+            image.height()
+        );
+    }
+
+    Ok(image.crop_imm(
+        min.x() as u32,
+        min.y() as u32,
+        size.x() as u32,
+        size.y() as u32,
+    ))
+}
+
+/// Create a layered GL texture and upload each layer's LODs into it.
+/// `layer_lods` holds the LODs of each layer, starting at LOD 0.
+/// The rest of the mip chain is generated on the GPU if requested.
+/// # Safety
+/// This function is unsafe because it should only be used on the main thread.
+unsafe fn __create_layered(
+    name: &str,
+    target: u32,
+    size: Vector3<u32>,
+    lod_count: usize,
+    layer_lods: &[Vec<image::DynamicImage>],
+    options: TextureOptions,
+) -> Result<u32> {
+    // Convert each layer and LOD to the texture format.
+    let format = options.format;
+    let (pixel_format, pixel_type) = format.upload_format().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to load {}: cannot load an image into format {:?}",
+            name,
+            format
+        )
+    })?;
+    let layer_bytes = layer_lods
+        .iter()
+        .map(|lods| {
+            lods.iter()
+                .map(|lod| Ok((lod.dimensions(), format.image_to_bytes(lod)?)))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    unsafe {
+        let mut handle = 0;
+        gl::CreateTextures(target, 1, &mut handle);
+
+        // Set the texture's label
+        gl::ObjectLabel(
+            gl::TEXTURE,
+            handle,
+            name.len() as i32,
+            name.as_ptr() as *const _,
+        );
+
+        gl::TextureStorage3D(
+            handle,
+            lod_count as i32,
+            format.internal_format(),
+            size.x() as i32,
+            size.y() as i32,
+            size.z() as i32,
+        );
+
+        // Upload each layer into its depth index.
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        for (layer, lods) in layer_bytes.iter().enumerate() {
+            for (level, ((width, height), bytes)) in lods.iter().enumerate() {
+                gl::TextureSubImage3D(
+                    handle,
+                    level as i32,
+                    0,
+                    0,
+                    layer as i32,
+                    *width as i32,
+                    *height as i32,
+                    1,
+                    pixel_format,
+                    pixel_type,
+                    bytes.as_ptr() as *const _,
+                );
+            }
+        }
+
+        // Generate the rest of the mip chain on the GPU if requested.
+        if options.mipmaps == Mipmaps::Gpu {
+            gl::GenerateTextureMipmap(handle);
+        }
+
+        Ok(handle)
+    }
+}
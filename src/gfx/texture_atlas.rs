@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use ggmath::prelude::*;
+
+use super::texture::TextureRegion;
+
+/// Packs many images into one or more atlas pages at runtime.
+/// Each image becomes a named `TextureRegion` that can be addressed with `Texture::region_view`.
+#[derive(Debug, Clone)]
+pub struct AtlasBuilder {
+    page_size: Vector2<u32>,
+    padding: u32,
+    extrusion: u32,
+    sources: Vec<(String, AtlasSource)>,
+}
+
+/// Represents where the pixels of an atlas entry come from.
+#[derive(Debug, Clone)]
+enum AtlasSource {
+    Image(image::DynamicImage),
+    File(PathBuf),
+}
+
+/// Represents a packed atlas page, before it is uploaded as a texture.
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    /// The packed image.
+    pub image: image::RgbaImage,
+    /// The regions of each entry in the image.
+    pub regions: HashMap<String, TextureRegion>,
+}
+
+impl AtlasBuilder {
+    /// Create a new atlas builder with the given maximum page size.
+    /// Entries are separated by 1 pixel of padding and are extruded by 1 pixel by default.
+    pub fn new(page_size: Vector2<u32>) -> Self {
+        Self {
+            page_size,
+            padding: 1,
+            extrusion: 1,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Set the number of transparent pixels between entries.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the number of pixels each entry's edges are repeated outward by.
+    /// Extrusion prevents neighbouring texels from bleeding in when sampling with filtering.
+    pub fn with_extrusion(mut self, extrusion: u32) -> Self {
+        self.extrusion = extrusion;
+        self
+    }
+
+    /// Add an image in memory with the given region name.
+    pub fn with_image(
+        mut self,
+        name: impl Into<String>,
+        image: impl Into<image::DynamicImage>,
+    ) -> Self {
+        self.sources
+            .push((name.into(), AtlasSource::Image(image.into())));
+        self
+    }
+
+    /// Add an image file, named after the file name without the extension.
+    /// The file is loaded when the atlas is packed.
+    pub fn with_file(self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        self.with_named_file(name, path)
+    }
+
+    /// Add an image file with the given region name.
+    /// The file is loaded when the atlas is packed.
+    pub fn with_named_file(mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        self.sources
+            .push((name.into(), AtlasSource::File(path.as_ref().to_path_buf())));
+        self
+    }
+
+    /// Get the maximum page size.
+    pub fn page_size(&self) -> Vector2<u32> {
+        self.page_size
+    }
+
+    /// Get the number of transparent pixels between entries.
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    /// Get the number of pixels each entry's edges are repeated outward by.
+    pub fn extrusion(&self) -> u32 {
+        self.extrusion
+    }
+
+    /// Load every entry and pack them into as few pages as possible.
+    /// Returns an error if a file could not be loaded, a name is used twice,
+    /// or an entry does not fit on an empty page.
+    pub fn pack(&self) -> Result<Vec<AtlasPage>> {
+        // Load the images and ensure that the names are unique.
+        let mut names = HashSet::new();
+        let mut entries = Vec::with_capacity(self.sources.len());
+        for (name, source) in &self.sources {
+            if !names.insert(name.as_str()) {
+                anyhow::bail!("Atlas entry {:?} was added more than once", name);
+            }
+
+            let image = match source {
+                AtlasSource::Image(image) => image.to_rgba8(),
+                AtlasSource::File(path) => image::open(path)
+                    .map_err(|e| anyhow::anyhow!("Failed to open image file {:?}: {:?}", path, e))?
+                    .to_rgba8(),
+            };
+            if image.width() == 0 || image.height() == 0 {
+                anyhow::bail!("Atlas entry {:?} is empty", name);
+            }
+            entries.push((name.as_str(), image));
+        }
+
+        // Pack the tallest entries first, which keeps the skyline flat.
+        entries.sort_by(|(a_name, a), (b_name, b)| {
+            b.height()
+                .cmp(&a.height())
+                .then(b.width().cmp(&a.width()))
+                .then(a_name.cmp(b_name))
+        });
+
+        let mut packers: Vec<SkylinePacker> = Vec::new();
+        let mut pages: Vec<AtlasPage> = Vec::new();
+        for (name, image) in entries {
+            // Reserve room for the extruded edges and the padding after them.
+            let border = self.extrusion * 2 + self.padding;
+            let cell = vector!(image.width() + border, image.height() + border);
+            if cell.x() > self.page_size.x() + self.padding
+                || cell.y() > self.page_size.y() + self.padding
+            {
+                anyhow::bail!(
+                    "Atlas entry {:?} is {}x{}, which does not fit in a {}x{} page",
+                    name,
+                    image.width(),
+                    image.height(),
+                    self.page_size.x(),
+                    self.page_size.y()
+                );
+            }
+
+            // Place the entry on the first page it fits on, or start a new page.
+            let placement = packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(i, packer)| Some((i, packer.insert(cell)?)));
+            let (page_index, position) = match placement {
+                Some(placement) => placement,
+                None => {
+                    // The padding after the last row and column may hang off the page.
+                    let mut packer = SkylinePacker::new(vector!(
+                        self.page_size.x() + self.padding,
+                        self.page_size.y() + self.padding
+                    ));
+                    let position = packer.insert(cell).unwrap();
+                    packers.push(packer);
+                    pages.push(AtlasPage {
+                        image: image::RgbaImage::new(self.page_size.x(), self.page_size.y()),
+                        regions: HashMap::new(),
+                    });
+                    (packers.len() - 1, position)
+                }
+            };
+
+            // Copy the entry and its extruded edges into the page.
+            let page = &mut pages[page_index];
+            let top_left = vector!(position.x() + self.extrusion, position.y() + self.extrusion);
+            blit_extruded(&mut page.image, &image, top_left, self.extrusion);
+
+            let region = TextureRegion::new(
+                top_left.convert_to::<i32>().unwrap(),
+                vector!(image.width() as i32, image.height() as i32),
+                0,
+                1,
+            );
+            page.regions.insert(name.to_string(), region);
+        }
+
+        Ok(pages)
+    }
+}
+
+/// Copy `image` into `page` with its top-left pixel at `top_left`,
+/// repeating its edge pixels outward by `extrusion` pixels.
+fn blit_extruded(
+    page: &mut image::RgbaImage,
+    image: &image::RgbaImage,
+    top_left: Vector2<u32>,
+    extrusion: u32,
+) {
+    let (width, height) = image.dimensions();
+    let extrusion = extrusion as i64;
+    for y in -extrusion..height as i64 + extrusion {
+        for x in -extrusion..width as i64 + extrusion {
+            let source_x = x.clamp(0, width as i64 - 1) as u32;
+            let source_y = y.clamp(0, height as i64 - 1) as u32;
+            let page_x = top_left.x() as i64 + x;
+            let page_y = top_left.y() as i64 + y;
+            if page_x < page.width() as i64 && page_y < page.height() as i64 {
+                page.put_pixel(
+                    page_x as u32,
+                    page_y as u32,
+                    *image.get_pixel(source_x, source_y),
+                );
+            }
+        }
+    }
+}
+
+/// A skyline bin packer, which places each rectangle as low as possible, then as far left.
+#[derive(Debug, Clone)]
+struct SkylinePacker {
+    size: Vector2<u32>,
+    /// The skyline segments as (x, y, width), ordered by x and covering the full width.
+    skyline: Vec<(u32, u32, u32)>,
+}
+
+impl SkylinePacker {
+    /// Create a new empty packer with the given size.
+    fn new(size: Vector2<u32>) -> Self {
+        Self {
+            size,
+            skyline: vec![(0, 0, size.x())],
+        }
+    }
+
+    /// Find a position for a rectangle of the given size and reserve it.
+    /// Returns `None` if the rectangle does not fit.
+    fn insert(&mut self, size: Vector2<u32>) -> Option<Vector2<u32>> {
+        // Find the segment where the rectangle rests lowest, breaking ties by the narrowest fit.
+        let mut best: Option<(usize, u32, u32)> = None;
+        for i in 0..self.skyline.len() {
+            let Some(y) = self.fit(i, size) else {
+                continue;
+            };
+            let waste = self.skyline[i].2;
+            if best.is_none_or(|(_, best_y, best_waste)| {
+                y < best_y || (y == best_y && waste < best_waste)
+            }) {
+                best = Some((i, y, waste));
+            }
+        }
+        let (index, y, _) = best?;
+        let x = self.skyline[index].0;
+
+        // Raise the skyline under the rectangle.
+        self.skyline.insert(index, (x, y + size.y(), size.x()));
+        let right = x + size.x();
+        let mut i = index + 1;
+        while i < self.skyline.len() {
+            let (segment_x, segment_y, segment_width) = self.skyline[i];
+            if segment_x >= right {
+                break;
+            }
+            let segment_right = segment_x + segment_width;
+            if segment_right <= right {
+                self.skyline.remove(i);
+            } else {
+                self.skyline[i] = (right, segment_y, segment_right - right);
+                break;
+            }
+        }
+
+        // Merge neighbouring segments at the same height.
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].1 == self.skyline[i + 1].1 {
+                self.skyline[i].2 += self.skyline[i + 1].2;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+
+        Some(vector!(x, y))
+    }
+
+    /// Get the height a rectangle would rest at if placed at the start of the given segment.
+    /// Returns `None` if it would not fit.
+    fn fit(&self, index: usize, size: Vector2<u32>) -> Option<u32> {
+        let x = self.skyline[index].0;
+        if x + size.x() > self.size.x() {
+            return None;
+        }
+
+        // Rest on the highest segment under the rectangle.
+        let mut y = 0;
+        let mut remaining = size.x() as i64;
+        for &(_, segment_y, segment_width) in &self.skyline[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment_y);
+            remaining -= segment_width as i64;
+        }
+
+        if y + size.y() > self.size.y() {
+            return None;
+        }
+
+        Some(y)
+    }
+}
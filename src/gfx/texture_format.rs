@@ -0,0 +1,133 @@
+use anyhow::Result;
+
+/// Represents the format that texels are stored in on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureFormat {
+    /// 8-bit linear RGBA.
+    #[default]
+    Rgba8,
+    /// 8-bit sRGB color with linear alpha. Sampling converts to linear color.
+    Srgb8Alpha8,
+    /// 8-bit single channel, for masks.
+    R8,
+    /// 8-bit two channels.
+    Rg8,
+    /// 16-bit float single channel.
+    R16F,
+    /// 16-bit float RGBA, for HDR colors.
+    Rgba16F,
+    /// 32-bit float RGBA.
+    Rgba32F,
+    /// 24-bit depth with 8-bit stencil.
+    Depth24Stencil8,
+    /// 32-bit float depth.
+    Depth32F,
+}
+
+impl TextureFormat {
+    /// Get the GL internal format.
+    pub(crate) const fn internal_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::Rg8 => gl::RG8,
+            TextureFormat::R16F => gl::R16F,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::Rgba32F => gl::RGBA32F,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+        }
+    }
+
+    /// Get the framebuffer attachment point for a depth format.
+    /// Returns `None` if this is a color format.
+    pub(crate) const fn depth_attachment(self) -> Option<u32> {
+        match self {
+            TextureFormat::Depth24Stencil8 => Some(gl::DEPTH_STENCIL_ATTACHMENT),
+            TextureFormat::Depth32F => Some(gl::DEPTH_ATTACHMENT),
+            _ => None,
+        }
+    }
+
+    /// Check if this is a depth or depth/stencil format.
+    pub const fn is_depth(self) -> bool {
+        self.depth_attachment().is_some()
+    }
+
+    /// Check if this format stores floating point values.
+    pub const fn is_float(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::R16F
+                | TextureFormat::Rgba16F
+                | TextureFormat::Rgba32F
+                | TextureFormat::Depth32F
+        )
+    }
+
+    /// Get the number of channels.
+    pub const fn channel_count(self) -> usize {
+        match self {
+            TextureFormat::R8
+            | TextureFormat::R16F
+            | TextureFormat::Depth24Stencil8
+            | TextureFormat::Depth32F => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgba8
+            | TextureFormat::Srgb8Alpha8
+            | TextureFormat::Rgba16F
+            | TextureFormat::Rgba32F => 4,
+        }
+    }
+
+    /// Get the GL pixel format and type used when uploading texels from CPU memory.
+    /// Float formats are uploaded as 32-bit floats and converted by GL.
+    /// Returns `None` for depth formats, which cannot be uploaded from images.
+    pub(crate) const fn upload_format(self) -> Option<(u32, u32)> {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => {
+                Some((gl::RGBA, gl::UNSIGNED_BYTE))
+            }
+            TextureFormat::R8 => Some((gl::RED, gl::UNSIGNED_BYTE)),
+            TextureFormat::Rg8 => Some((gl::RG, gl::UNSIGNED_BYTE)),
+            TextureFormat::R16F => Some((gl::RED, gl::FLOAT)),
+            TextureFormat::Rgba16F | TextureFormat::Rgba32F => Some((gl::RGBA, gl::FLOAT)),
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F => None,
+        }
+    }
+
+    /// Get the number of bytes per texel when uploading in the format from `upload_format`.
+    /// Returns `None` for depth formats.
+    pub(crate) const fn upload_texel_size(self) -> Option<usize> {
+        match self.upload_format() {
+            Some((_, gl::FLOAT)) => Some(self.channel_count() * 4),
+            Some(_) => Some(self.channel_count()),
+            None => None,
+        }
+    }
+
+    /// Convert an image into tightly packed texel bytes for uploading in this format.
+    /// Single channel formats use the image's luminance and two channel formats use
+    /// luminance and alpha.
+    pub(crate) fn image_to_bytes(self, image: &image::DynamicImage) -> Result<Vec<u8>> {
+        let bytes = match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => image.to_rgba8().into_raw(),
+            TextureFormat::R8 => image.to_luma8().into_raw(),
+            TextureFormat::Rg8 => image.to_luma_alpha8().into_raw(),
+            TextureFormat::R16F => floats_to_bytes(image.to_luma32f().into_raw()),
+            TextureFormat::Rgba16F | TextureFormat::Rgba32F => {
+                floats_to_bytes(image.to_rgba32f().into_raw())
+            }
+            TextureFormat::Depth24Stencil8 | TextureFormat::Depth32F => {
+                anyhow::bail!("Cannot load an image into depth format {:?}", self)
+            }
+        };
+        Ok(bytes)
+    }
+}
+
+/// Convert floats into native-endian bytes.
+fn floats_to_bytes(floats: Vec<f32>) -> Vec<u8> {
+    floats.into_iter().flat_map(f32::to_ne_bytes).collect()
+}
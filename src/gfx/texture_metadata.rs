@@ -0,0 +1,316 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use ggmath::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::texture::{TextureGlyph, TextureRegion};
+
+/// The regions and glyphs of a texture, as stored in a sidecar file next to the image.
+///
+/// Sidecar files are named after the image, such as `ascii.atlas.ron` or `ascii.atlas.json`
+/// for `ascii.png`. TexturePacker JSON files (hash or array) named `ascii.json` are also read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextureMetadata {
+    /// The named regions of the texture.
+    pub regions: HashMap<String, TextureRegion>,
+    /// The character glyphs of the texture.
+    pub glyphs: HashMap<char, TextureGlyph>,
+}
+
+impl TextureMetadata {
+    /// Create new texture metadata from region and glyph maps, such as those built in code.
+    pub fn new(
+        regions: Option<HashMap<String, TextureRegion>>,
+        glyphs: Option<HashMap<char, TextureGlyph>>,
+    ) -> Self {
+        Self {
+            regions: regions.unwrap_or_default(),
+            glyphs: glyphs.unwrap_or_default(),
+        }
+    }
+
+    /// Get the regions, or `None` if there are none.
+    pub fn regions(&self) -> Option<&HashMap<String, TextureRegion>> {
+        (!self.regions.is_empty()).then_some(&self.regions)
+    }
+
+    /// Get the glyphs, or `None` if there are none.
+    pub fn glyphs(&self) -> Option<&HashMap<char, TextureGlyph>> {
+        (!self.glyphs.is_empty()).then_some(&self.glyphs)
+    }
+
+    /// Find the sidecar file for the given image path, if one exists.
+    /// The candidates are checked in the order `.atlas.ron`, `.atlas.json` and `.json`.
+    pub fn find_sidecar(image_path: impl AsRef<Path>) -> Option<PathBuf> {
+        let image_path = image_path.as_ref();
+        let stem = image_path.file_stem()?.to_str()?;
+
+        ["atlas.ron", "atlas.json", "json"]
+            .iter()
+            .map(|extension| image_path.with_file_name(format!("{}.{}", stem, extension)))
+            .find(|path| path.is_file())
+    }
+
+    /// Load texture metadata from a sidecar file.
+    /// `.ron` files use this crate's format. `.json` files may use this crate's format or
+    /// TexturePacker's, which is detected by its `frames` field.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read sidecar file {:?}: {:?}", path, e))?;
+
+        let result = match extension(path).as_deref() {
+            Some("ron") => ron::from_str::<MetadataFile>(&text)
+                .map_err(anyhow::Error::from)
+                .and_then(MetadataFile::into_metadata),
+            Some("json") => {
+                let value = serde_json::from_str::<serde_json::Value>(&text)?;
+                if value.get("frames").is_some() {
+                    serde_json::from_value::<TexturePackerFile>(value)
+                        .map_err(anyhow::Error::from)
+                        .and_then(TexturePackerFile::into_metadata)
+                } else {
+                    serde_json::from_value::<MetadataFile>(value)
+                        .map_err(anyhow::Error::from)
+                        .and_then(MetadataFile::into_metadata)
+                }
+            }
+            _ => Err(anyhow::anyhow!("expected a .ron or .json file")),
+        };
+
+        result.map_err(|e| anyhow::anyhow!("Failed to load sidecar file {:?}: {}", path, e))
+    }
+
+    /// Save this metadata to a sidecar file in this crate's format.
+    /// The format is chosen by the file extension, which must be `.ron` or `.json`.
+    /// Entries are sorted by name so the output is stable.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = MetadataFile::from_metadata(self);
+
+        let text = match extension(path).as_deref() {
+            Some("ron") => ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?,
+            Some("json") => serde_json::to_string_pretty(&file)?,
+            _ => anyhow::bail!(
+                "Failed to save sidecar file {:?}: expected a .ron or .json file",
+                path
+            ),
+        };
+
+        std::fs::write(path, text)
+            .map_err(|e| anyhow::anyhow!("Failed to write sidecar file {:?}: {:?}", path, e))
+    }
+}
+
+/// Get the lowercase extension of a path.
+fn extension(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_str()?.to_lowercase())
+}
+
+/// This crate's sidecar file format.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataFile {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    regions: BTreeMap<String, RegionEntry>,
+    /// Glyphs are keyed by single-character strings, which both RON and JSON support.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    glyphs: BTreeMap<String, GlyphEntry>,
+}
+
+/// A region in the sidecar file format, in pixels from the top-left of the image.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct RegionEntry {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    #[serde(default)]
+    min_lod: u32,
+    #[serde(default = "default_lod_levels")]
+    lod_levels: u32,
+}
+
+/// A glyph in the sidecar file format, with the same fields as a region plus its advance.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct GlyphEntry {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    #[serde(default)]
+    min_lod: u32,
+    #[serde(default = "default_lod_levels")]
+    lod_levels: u32,
+    advance: i32,
+}
+
+/// Regions cover a single LOD unless specified.
+fn default_lod_levels() -> u32 {
+    1
+}
+
+impl RegionEntry {
+    fn from_region(region: &TextureRegion) -> Self {
+        let min = region.min_pixel();
+        let size = region.pixel_size();
+        Self {
+            x: min.x(),
+            y: min.y(),
+            width: size.x(),
+            height: size.y(),
+            min_lod: region.min_lod(),
+            lod_levels: region.max_lod() - region.min_lod() + 1,
+        }
+    }
+
+    fn to_region(self) -> TextureRegion {
+        TextureRegion::new(
+            vector!(self.x, self.y),
+            vector!(self.width, self.height),
+            self.min_lod,
+            self.lod_levels,
+        )
+    }
+}
+
+impl MetadataFile {
+    fn from_metadata(metadata: &TextureMetadata) -> Self {
+        Self {
+            regions: metadata
+                .regions
+                .iter()
+                .map(|(name, region)| (name.clone(), RegionEntry::from_region(region)))
+                .collect(),
+            glyphs: metadata
+                .glyphs
+                .iter()
+                .map(|(character, glyph)| {
+                    let region = RegionEntry::from_region(&glyph.region());
+                    let entry = GlyphEntry {
+                        x: region.x,
+                        y: region.y,
+                        width: region.width,
+                        height: region.height,
+                        min_lod: region.min_lod,
+                        lod_levels: region.lod_levels,
+                        advance: glyph.advance(),
+                    };
+                    (character.to_string(), entry)
+                })
+                .collect(),
+        }
+    }
+
+    fn into_metadata(self) -> Result<TextureMetadata> {
+        let regions = self
+            .regions
+            .into_iter()
+            .map(|(name, entry)| (name, entry.to_region()))
+            .collect();
+
+        let glyphs = self
+            .glyphs
+            .into_iter()
+            .map(|(key, entry)| {
+                // Ensure that each glyph key is a single character.
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(character), None) => {
+                        let region = RegionEntry {
+                            x: entry.x,
+                            y: entry.y,
+                            width: entry.width,
+                            height: entry.height,
+                            min_lod: entry.min_lod,
+                            lod_levels: entry.lod_levels,
+                        };
+                        Ok((
+                            character,
+                            TextureGlyph::new(region.to_region(), entry.advance),
+                        ))
+                    }
+                    _ => Err(anyhow::anyhow!(
+                        "glyph key {:?} is not a single character",
+                        key
+                    )),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(TextureMetadata { regions, glyphs })
+    }
+}
+
+/// A TexturePacker JSON file, exported as either a hash or an array of frames.
+#[derive(Debug, Deserialize)]
+struct TexturePackerFile {
+    frames: TexturePackerFrames,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TexturePackerFrames {
+    Hash(BTreeMap<String, TexturePackerFrame>),
+    Array(Vec<TexturePackerFrame>),
+}
+
+#[derive(Debug, Deserialize)]
+struct TexturePackerFrame {
+    /// Only present in the array format; the hash format uses the key instead.
+    #[serde(default)]
+    filename: Option<String>,
+    frame: TexturePackerRect,
+    #[serde(default)]
+    rotated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TexturePackerRect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+impl TexturePackerFile {
+    /// Convert the frames to regions, keyed by their names in the file.
+    fn into_metadata(self) -> Result<TextureMetadata> {
+        let frames: Vec<(String, TexturePackerFrame)> = match self.frames {
+            TexturePackerFrames::Hash(frames) => frames.into_iter().collect(),
+            TexturePackerFrames::Array(frames) => frames
+                .into_iter()
+                .map(|frame| {
+                    let name = frame
+                        .filename
+                        .clone()
+                        .ok_or_else(|| anyhow::anyhow!("frame is missing a filename"))?;
+                    Ok((name, frame))
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let regions = frames
+            .into_iter()
+            .map(|(name, frame)| {
+                // Rotated frames would need their texture coordinates rotated too.
+                if frame.rotated {
+                    anyhow::bail!("frame {:?} is rotated, which is not supported", name);
+                }
+
+                let rect = frame.frame;
+                let region =
+                    TextureRegion::new(vector!(rect.x, rect.y), vector!(rect.w, rect.h), 0, 1);
+                Ok((name, region))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(TextureMetadata {
+            regions,
+            glyphs: HashMap::new(),
+        })
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 
 use super::shader_gen::{shader_inputs::ShaderInput, shader_type::ShaderType};
 
-// Allowed type for vertex data.
+// Type of vertex data before it is converted to each attribute's format.
 pub type VertexComponent = f32;
 
 /// Represents a single vertex input.
@@ -12,44 +12,110 @@ pub enum VertexInput {
     Normal,
     Color,
     TexCoord,
+    /// The tangent for normal mapping, pointing along increasing U.
+    Tangent,
+    /// The bitangent for normal mapping, pointing along increasing V.
+    Bitangent,
+    /// A second set of texture coordinates, such as lightmap UVs.
+    TexCoord1,
+    /// A third set of texture coordinates.
+    TexCoord2,
+    /// A fourth set of texture coordinates.
+    TexCoord3,
+    /// The indices of up to 4 joints that influence the vertex, for skinning.
+    /// Read as a `uvec4` in shaders when stored in the default `VertexFormat::U8`.
+    JointIndices,
+    /// The weights of the joints in `JointIndices`.
+    JointWeights,
+    /// The model matrix of an instance, relative to the renderer, for instanced rendering.
+    ModelMatrix,
+    /// A user-defined input with a name and a scalar or vector type.
+    Custom(String, ShaderType),
 }
 
 impl VertexInput {
+    /// Create a user-defined input with the given name and scalar or vector type.
+    pub fn custom(name: impl Into<String>, shader_type: ShaderType) -> Self {
+        VertexInput::Custom(name.into(), shader_type)
+    }
+
     /// Get the # of components for this input.
-    pub const fn component_count(&self) -> usize {
+    pub fn component_count(&self) -> usize {
         match self {
             VertexInput::Position => 3,
             VertexInput::Normal => 3,
             VertexInput::Color => 4,
             VertexInput::TexCoord => 2,
+            VertexInput::Tangent | VertexInput::Bitangent => 3,
+            VertexInput::TexCoord1 | VertexInput::TexCoord2 | VertexInput::TexCoord3 => 2,
+            VertexInput::JointIndices | VertexInput::JointWeights => 4,
+            VertexInput::ModelMatrix => 16,
+            VertexInput::Custom(_, shader_type) => shader_type.component_count().unwrap_or(0),
         }
     }
 
-    /// Get the byte size of this input.
-    pub const fn byte_size(&self) -> usize {
-        self.component_count() * std::mem::size_of::<VertexComponent>()
-    }
-
     /// Get the name of this input.
-    pub const fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         match self {
             VertexInput::Position => "Position",
             VertexInput::Normal => "Normal",
             VertexInput::Color => "Color",
             VertexInput::TexCoord => "TexCoord",
+            VertexInput::Tangent => "Tangent",
+            VertexInput::Bitangent => "Bitangent",
+            VertexInput::TexCoord1 => "TexCoord1",
+            VertexInput::TexCoord2 => "TexCoord2",
+            VertexInput::TexCoord3 => "TexCoord3",
+            VertexInput::JointIndices => "JointIndices",
+            VertexInput::JointWeights => "JointWeights",
+            VertexInput::ModelMatrix => "ModelMatrix",
+            VertexInput::Custom(name, _) => name,
         }
     }
 
-    /// Get the corresponding shader type of this input.
-    pub const fn shader_type(&self) -> ShaderType {
+    /// Get the corresponding shader type of this input when stored as floats.
+    /// Custom inputs use their own type.
+    pub fn shader_type(&self) -> ShaderType {
         match self {
             VertexInput::Position => ShaderType::Vec3,
             VertexInput::Normal => ShaderType::Vec3,
             VertexInput::Color => ShaderType::Vec4,
             VertexInput::TexCoord => ShaderType::Vec2,
+            VertexInput::Tangent | VertexInput::Bitangent => ShaderType::Vec3,
+            VertexInput::TexCoord1 | VertexInput::TexCoord2 | VertexInput::TexCoord3 => {
+                ShaderType::Vec2
+            }
+            VertexInput::JointIndices | VertexInput::JointWeights => ShaderType::Vec4,
+            VertexInput::ModelMatrix => ShaderType::Mat4,
+            VertexInput::Custom(_, shader_type) => *shader_type,
         }
     }
 
+    /// Validate the input for correctness.
+    /// Custom inputs must have a valid GLSL name and a scalar, vector or matrix type.
+    pub fn validate(&self) -> Result<()> {
+        if let VertexInput::Custom(name, shader_type) = self {
+            let mut chars = name.chars();
+            let valid_start = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+            if !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                anyhow::bail!(
+                    "Custom vertex input name {:?} is not a valid identifier.",
+                    name
+                );
+            }
+            if !matches!(shader_type.component_count(), Some(1..=4 | 16)) {
+                anyhow::bail!(
+                    "Custom vertex input {} has type {}, but must be a scalar, vector or matrix.",
+                    name,
+                    shader_type.rust_name()
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Create a shader input from this vertex input.
     pub fn to_shader_input(
         &self,
@@ -59,6 +125,305 @@ impl VertexInput {
     }
 }
 
+/// Represents how the components of a vertex input are stored in a vertex buffer.
+/// `VertexList` converts its `f32` data into the format of each attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VertexFormat {
+    /// 32-bit floats.
+    #[default]
+    F32,
+    /// 16-bit half floats.
+    F16,
+    /// 8-bit unsigned integers mapped to `0.0..=1.0`, such as colors.
+    UNorm8,
+    /// 8-bit signed integers mapped to `-1.0..=1.0`.
+    SNorm8,
+    /// 16-bit unsigned integers mapped to `0.0..=1.0`, such as texture coordinates.
+    UNorm16,
+    /// 16-bit signed integers mapped to `-1.0..=1.0`, such as positions within a unit box.
+    SNorm16,
+    /// Three 10-bit and one 2-bit unsigned integers packed into 32 bits, mapped to `0.0..=1.0`.
+    UNorm10_10_10_2,
+    /// Three 10-bit and one 2-bit signed integers packed into 32 bits, mapped to `-1.0..=1.0`,
+    /// such as normals.
+    SNorm10_10_10_2,
+    /// 8-bit unsigned integers, read as unsigned integers in shaders.
+    U8,
+    /// 8-bit signed integers, read as integers in shaders.
+    I8,
+    /// 16-bit unsigned integers, read as unsigned integers in shaders.
+    U16,
+    /// 16-bit signed integers, read as integers in shaders.
+    I16,
+    /// 32-bit signed integers, read as integers in shaders.
+    I32,
+}
+
+impl VertexFormat {
+    /// Get the GL component type.
+    pub const fn gl_type(self) -> u32 {
+        match self {
+            VertexFormat::F32 => gl::FLOAT,
+            VertexFormat::F16 => gl::HALF_FLOAT,
+            VertexFormat::UNorm8 | VertexFormat::U8 => gl::UNSIGNED_BYTE,
+            VertexFormat::SNorm8 | VertexFormat::I8 => gl::BYTE,
+            VertexFormat::UNorm16 | VertexFormat::U16 => gl::UNSIGNED_SHORT,
+            VertexFormat::SNorm16 | VertexFormat::I16 => gl::SHORT,
+            VertexFormat::UNorm10_10_10_2 => gl::UNSIGNED_INT_2_10_10_10_REV,
+            VertexFormat::SNorm10_10_10_2 => gl::INT_2_10_10_10_REV,
+            VertexFormat::I32 => gl::INT,
+        }
+    }
+
+    /// Returns true if integer data is normalized to floats when read by shaders.
+    pub const fn is_normalized(self) -> bool {
+        matches!(
+            self,
+            VertexFormat::UNorm8
+                | VertexFormat::SNorm8
+                | VertexFormat::UNorm16
+                | VertexFormat::SNorm16
+                | VertexFormat::UNorm10_10_10_2
+                | VertexFormat::SNorm10_10_10_2
+        )
+    }
+
+    /// Returns true if the data is read as integers by shaders.
+    pub const fn is_integer(self) -> bool {
+        matches!(
+            self,
+            VertexFormat::U8
+                | VertexFormat::I8
+                | VertexFormat::U16
+                | VertexFormat::I16
+                | VertexFormat::I32
+        )
+    }
+
+    /// Returns true if the data is read as unsigned integers by shaders.
+    pub const fn is_unsigned_integer(self) -> bool {
+        matches!(self, VertexFormat::U8 | VertexFormat::U16)
+    }
+
+    /// Returns true if all components are packed into a single 32-bit value.
+    pub const fn is_packed(self) -> bool {
+        matches!(
+            self,
+            VertexFormat::UNorm10_10_10_2 | VertexFormat::SNorm10_10_10_2
+        )
+    }
+
+    /// Get the byte size of a single component.
+    /// Packed formats return the size of the whole packed value.
+    pub const fn component_size(self) -> usize {
+        match self {
+            VertexFormat::UNorm8 | VertexFormat::SNorm8 | VertexFormat::U8 | VertexFormat::I8 => 1,
+            VertexFormat::F16
+            | VertexFormat::UNorm16
+            | VertexFormat::SNorm16
+            | VertexFormat::U16
+            | VertexFormat::I16 => 2,
+            VertexFormat::F32
+            | VertexFormat::I32
+            | VertexFormat::UNorm10_10_10_2
+            | VertexFormat::SNorm10_10_10_2 => 4,
+        }
+    }
+
+    /// Get the byte size of an attribute with `component_count` components in this format.
+    /// Attributes are padded to 4 bytes, as GL expects aligned attribute offsets.
+    pub const fn byte_size(self, component_count: usize) -> usize {
+        let size = if self.is_packed() {
+            self.component_size()
+        } else {
+            self.component_size() * component_count
+        };
+        size.next_multiple_of(4)
+    }
+
+    /// Get the component count passed to GL for an attribute with `component_count` components.
+    /// Packed formats always have 4 components; shaders ignore the ones they do not read.
+    pub const fn gl_component_count(self, component_count: usize) -> usize {
+        if self.is_packed() {
+            4
+        } else {
+            component_count
+        }
+    }
+
+    /// Get the shader type that an attribute in this format is read as,
+    /// given the shader type of its input when stored as floats.
+    /// Unsigned integer formats are read as `uint` vectors, and signed ones as `int` vectors.
+    pub const fn shader_type(self, float_type: ShaderType) -> ShaderType {
+        if !self.is_integer() {
+            return float_type;
+        }
+
+        let unsigned = self.is_unsigned_integer();
+        match float_type {
+            ShaderType::Vec2 | ShaderType::IVec2 | ShaderType::UVec2 if unsigned => {
+                ShaderType::UVec2
+            }
+            ShaderType::Vec3 | ShaderType::IVec3 | ShaderType::UVec3 if unsigned => {
+                ShaderType::UVec3
+            }
+            ShaderType::Vec4 | ShaderType::IVec4 | ShaderType::UVec4 if unsigned => {
+                ShaderType::UVec4
+            }
+            ShaderType::Vec2 | ShaderType::IVec2 | ShaderType::UVec2 => ShaderType::IVec2,
+            ShaderType::Vec3 | ShaderType::IVec3 | ShaderType::UVec3 => ShaderType::IVec3,
+            ShaderType::Vec4 | ShaderType::IVec4 | ShaderType::UVec4 => ShaderType::IVec4,
+            _ if unsigned => ShaderType::U32,
+            _ => ShaderType::I32,
+        }
+    }
+
+    /// Convert `components` into this format and write them to the start of `target`.
+    /// Normalized values are clamped to their range and integer values are rounded.
+    /// Packed formats fill a missing fourth component with 1.
+    pub fn write(self, components: &[VertexComponent], target: &mut [u8]) {
+        match self {
+            VertexFormat::F32 => write_each(components, target, |v| v.to_ne_bytes()),
+            VertexFormat::F16 => write_each(components, target, |v| f32_to_f16(v).to_ne_bytes()),
+            VertexFormat::UNorm8 => write_each(components, target, |v| {
+                [(v.clamp(0.0, 1.0) * 255.0).round() as u8]
+            }),
+            VertexFormat::SNorm8 => write_each(components, target, |v| {
+                ((v.clamp(-1.0, 1.0) * 127.0).round() as i8).to_ne_bytes()
+            }),
+            VertexFormat::UNorm16 => write_each(components, target, |v| {
+                ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes()
+            }),
+            VertexFormat::SNorm16 => write_each(components, target, |v| {
+                ((v.clamp(-1.0, 1.0) * 32767.0).round() as i16).to_ne_bytes()
+            }),
+            VertexFormat::UNorm10_10_10_2 | VertexFormat::SNorm10_10_10_2 => {
+                let signed = self == VertexFormat::SNorm10_10_10_2;
+                let packed = (0..4).fold(0u32, |packed, i| {
+                    let value = components.get(i).copied().unwrap_or(1.0);
+                    let bits = if i < 3 { 10 } else { 2 };
+                    packed | (pack_normalized(value, bits, signed) << (i * 10))
+                });
+                target[..4].copy_from_slice(&packed.to_ne_bytes());
+            }
+            VertexFormat::U8 => write_each(components, target, |v| [v.round() as u8]),
+            VertexFormat::I8 => write_each(components, target, |v| (v.round() as i8).to_ne_bytes()),
+            VertexFormat::U16 => {
+                write_each(components, target, |v| (v.round() as u16).to_ne_bytes())
+            }
+            VertexFormat::I16 => {
+                write_each(components, target, |v| (v.round() as i16).to_ne_bytes())
+            }
+            VertexFormat::I32 => {
+                write_each(components, target, |v| (v.round() as i32).to_ne_bytes())
+            }
+        }
+    }
+}
+
+/// Convert each component with `f` and write the bytes one after another.
+fn write_each<const N: usize>(
+    components: &[VertexComponent],
+    target: &mut [u8],
+    f: impl Fn(VertexComponent) -> [u8; N],
+) {
+    for (i, component) in components.iter().enumerate() {
+        target[i * N..(i + 1) * N].copy_from_slice(&f(*component));
+    }
+}
+
+/// Convert a normalized value to an integer with `bits` bits, in two's complement if `signed`.
+fn pack_normalized(value: f32, bits: u32, signed: bool) -> u32 {
+    let mask = (1u32 << bits) - 1;
+    if signed {
+        let max = ((1u32 << (bits - 1)) - 1) as f32;
+        ((value.clamp(-1.0, 1.0) * max).round() as i32 as u32) & mask
+    } else {
+        (value.clamp(0.0, 1.0) * mask as f32).round() as u32
+    }
+}
+
+/// Convert a float to the bits of a half float, rounding to the nearest value.
+/// Values too large for a half float become infinity.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    // Infinity and NaN.
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        // Too large, so round to infinity.
+        sign | 0x7c00
+    } else if half_exponent <= 0 {
+        // Subnormal or too small, so shift the mantissa (with its implicit bit) into place.
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let rounded = (mantissa + (1 << (shift - 1))) >> shift;
+        sign | rounded as u16
+    } else {
+        // Normal, so round the mantissa, which may carry into the exponent.
+        let rounded = ((half_exponent as u32) << 10) + ((mantissa + 0x1000) >> 13);
+        sign | rounded.min(0x7c00) as u16
+    }
+}
+
+/// Represents a single vertex input together with how it is stored in a vertex buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    input: VertexInput,
+    format: VertexFormat,
+    stream: usize,
+    byte_offset: usize,
+}
+
+impl VertexAttribute {
+    /// Get the input of this attribute.
+    pub fn input(&self) -> &VertexInput {
+        &self.input
+    }
+
+    /// Get the format of this attribute.
+    pub fn format(&self) -> VertexFormat {
+        self.format
+    }
+
+    /// Get the vertex stream (and vertex buffer) this attribute is stored in.
+    pub fn stream(&self) -> usize {
+        self.stream
+    }
+
+    /// Get the byte offset of this attribute within a vertex of its stream.
+    pub fn byte_offset(&self) -> usize {
+        self.byte_offset
+    }
+
+    /// Get the byte size of this attribute, including padding.
+    pub fn byte_size(&self) -> usize {
+        self.format.byte_size(self.input.component_count())
+    }
+
+    /// Get the shader type this attribute is read as.
+    pub fn shader_type(&self) -> ShaderType {
+        self.format.shader_type(self.input.shader_type())
+    }
+
+    /// Get the number of attribute locations this attribute occupies.
+    /// Matrices occupy one location per column.
+    pub fn location_count(&self) -> usize {
+        self.shader_type().location_count()
+    }
+}
+
 impl AsRef<str> for VertexInput {
     fn as_ref(&self) -> &str {
         self.name()
@@ -66,10 +431,13 @@ impl AsRef<str> for VertexInput {
 }
 
 /// Represents the layout of a tightly-packed vertex in memory.
+/// The attributes may be spread over several streams, each stored interleaved in its own
+/// vertex buffer, so that streams can be read or updated separately.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VertexLayout {
-    inputs: Vec<VertexInput>,
-    component_stride: usize,
+    attributes: Vec<VertexAttribute>,
+    stream_strides: Vec<usize>,
+    stream: usize,
 }
 
 impl VertexLayout {
@@ -78,24 +446,49 @@ impl VertexLayout {
     /// This function is unsafe because it creates a new vertex layout without validating it.
     pub(crate) unsafe fn __new() -> Self {
         Self {
-            inputs: Vec::new(),
-            component_stride: 0,
+            attributes: Vec::new(),
+            stream_strides: vec![0],
+            stream: 0,
         }
     }
 
-    /// Push a new input to the layout.
+    /// Push a new input to the layout, stored as floats.
     pub fn push(&mut self, input: VertexInput) {
-        self.component_stride += input.component_count();
-        self.inputs.push(input);
+        self.push_with_format(input, VertexFormat::F32);
     }
 
-    /// Push multiple inputs to the layout.
+    /// Push a new input to the layout, stored in the given format.
+    pub fn push_with_format(&mut self, input: VertexInput, format: VertexFormat) {
+        let attribute = VertexAttribute {
+            input,
+            format,
+            stream: self.stream,
+            byte_offset: self.stream_strides[self.stream],
+        };
+        self.stream_strides[self.stream] += attribute.byte_size();
+        self.attributes.push(attribute);
+    }
+
+    /// Set the stream that the following inputs are pushed to.
+    /// Streams start at 0 and every stream up to the last must have at least one input.
+    pub fn set_stream(&mut self, stream: usize) {
+        if stream >= self.stream_strides.len() {
+            self.stream_strides.resize(stream + 1, 0);
+        }
+        self.stream = stream;
+    }
+
+    /// Push the following inputs to the given stream.
+    pub fn with_stream(mut self, stream: usize) -> Self {
+        self.set_stream(stream);
+        self
+    }
+
+    /// Push multiple inputs to the layout, stored as floats.
     pub fn push_many(&mut self, inputs: Vec<VertexInput>) {
-        self.component_stride += inputs
-            .iter()
-            .map(|input| input.component_count())
-            .sum::<usize>();
-        self.inputs.extend(inputs);
+        for input in inputs {
+            self.push(input);
+        }
     }
 
     /// Push a new position input to the layout.
@@ -122,40 +515,297 @@ impl VertexLayout {
         self
     }
 
+    /// Push a new tangent input to the layout.
+    pub fn with_tangent(mut self) -> Self {
+        self.push(VertexInput::Tangent);
+        self
+    }
+
+    /// Push a new bitangent input to the layout.
+    pub fn with_bitangent(mut self) -> Self {
+        self.push(VertexInput::Bitangent);
+        self
+    }
+
+    /// Push a new joint indices input to the layout, stored as 8-bit unsigned integers and
+    /// read as a `uvec4` in shaders.
+    pub fn with_joint_indices(mut self) -> Self {
+        self.push_with_format(VertexInput::JointIndices, VertexFormat::U8);
+        self
+    }
+
+    /// Push a new joint weights input to the layout.
+    pub fn with_joint_weights(mut self) -> Self {
+        self.push(VertexInput::JointWeights);
+        self
+    }
+
+    /// Push a new model matrix input to the layout, for per-instance transforms.
+    pub fn with_model_matrix(mut self) -> Self {
+        self.push(VertexInput::ModelMatrix);
+        self
+    }
+
+    /// Push a new user-defined input to the layout, stored as floats.
+    /// Integer types must be pushed with an integer format using `with_input` instead.
+    pub fn with_custom(mut self, name: impl Into<String>, shader_type: ShaderType) -> Self {
+        self.push(VertexInput::custom(name, shader_type));
+        self
+    }
+
+    /// Push a new input to the layout, stored in the given format.
+    pub fn with_input(mut self, input: VertexInput, format: VertexFormat) -> Self {
+        self.push_with_format(input, format);
+        self
+    }
+
     /// Get the inputs in the layout.
-    pub fn inputs(&self) -> &[VertexInput] {
-        &self.inputs
+    pub fn inputs(&self) -> impl Iterator<Item = &VertexInput> {
+        self.attributes.iter().map(VertexAttribute::input)
+    }
+
+    /// Get the attributes in the layout.
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
     }
 
     /// Validate the layout for correctness.
     pub fn validate(&self) -> Result<()> {
-        // Check for duplicate inputs.
+        // Check for duplicate input names, which includes custom inputs named after built-ins.
         let mut seen = Vec::new();
-        for input in &self.inputs {
-            if seen.contains(&input) {
+        for input in self.inputs() {
+            if seen.contains(&input.name()) {
                 anyhow::bail!("Duplicate input found in vertex layout.");
             }
-            seen.push(input);
+            seen.push(input.name());
+        }
+
+        // Check that each input is valid.
+        for input in self.inputs() {
+            input.validate()?;
+        }
+
+        // Check that integer shader types are only stored in integer formats.
+        for attribute in &self.attributes {
+            let integer_type = matches!(
+                attribute.input.shader_type().component_type(),
+                Some(ShaderType::I32 | ShaderType::U32)
+            );
+            if integer_type && !attribute.format.is_integer() {
+                anyhow::bail!(
+                    "Vertex input {} has integer type {}, but format {:?} is not an integer format.",
+                    attribute.input.name(),
+                    attribute.input.shader_type().rust_name(),
+                    attribute.format
+                );
+            }
+        }
+
+        // Check that matrices are stored as floats.
+        for attribute in &self.attributes {
+            if attribute.input.shader_type() == ShaderType::Mat4
+                && attribute.format != VertexFormat::F32
+            {
+                anyhow::bail!(
+                    "Vertex input {} is a matrix, so it must use format {:?}.",
+                    attribute.input.name(),
+                    VertexFormat::F32
+                );
+            }
+        }
+
+        // Check that no stream is empty.
+        if let Some(stream) = self.stream_strides.iter().position(|stride| *stride == 0) {
+            anyhow::bail!("Vertex stream {} has no inputs.", stream);
+        }
+
+        // Check that packed formats are only used by inputs with 3 or 4 components.
+        for attribute in &self.attributes {
+            if attribute.format.is_packed() && attribute.input.component_count() < 3 {
+                anyhow::bail!(
+                    "Vertex input {} has {} components, but format {:?} needs 3 or 4.",
+                    attribute.input.name(),
+                    attribute.input.component_count(),
+                    attribute.format
+                );
+            }
         }
         Ok(())
     }
 
-    /// Validate the given vertex data for this layout.
-    pub fn validate_data(&self, data: &[VertexComponent]) -> Result<()> {
+    /// Validate the given vertex data for a stream of this layout.
+    pub fn validate_data(&self, stream: usize, data: &[u8]) -> Result<()> {
         // Check for correct data size.
-        if data.len() % self.component_stride != 0 {
+        let stride = self.stream_byte_stride(stream);
+        if stride == 0 || data.len() % stride != 0 {
             anyhow::bail!("Vertex data was invalid for layout: wrong size.");
         }
         Ok(())
     }
 
-    /// Get the component stride of this layout (the size of one vertex in components).
-    pub fn component_stride(&self) -> usize {
-        self.component_stride
+    /// Get the number of streams in this layout.
+    pub fn stream_count(&self) -> usize {
+        self.stream_strides.len()
+    }
+
+    /// Get the byte stride of a stream (the size of one vertex in that stream in bytes).
+    /// Returns 0 if the stream does not exist.
+    pub fn stream_byte_stride(&self, stream: usize) -> usize {
+        self.stream_strides.get(stream).copied().unwrap_or(0)
     }
 
-    /// Get the byte stride of this layout (the size of one vertex in bytes).
-    pub fn byte_stride(&self) -> usize {
-        self.component_stride * std::mem::size_of::<VertexComponent>()
+    /// Get the attributes stored in the given stream.
+    pub fn stream_attributes(&self, stream: usize) -> impl Iterator<Item = &VertexAttribute> {
+        self.attributes
+            .iter()
+            .filter(move |attribute| attribute.stream == stream)
+    }
+
+    /// Find the stream of `other` that each stream of this layout reads from.
+    /// A stream matches if `other` stores the same inputs in the same formats at the same
+    /// offsets with the same stride, so a layout with only positions can draw a mesh whose
+    /// positions are in their own stream.
+    /// Returns an error if a stream has no match.
+    pub fn match_streams(&self, other: &VertexLayout) -> Result<Vec<usize>> {
+        if self == other {
+            return Ok((0..self.stream_count()).collect());
+        }
+
+        (0..self.stream_count())
+            .map(|stream| {
+                let first = self.stream_attributes(stream).next();
+                let other_stream = first
+                    .and_then(|first| {
+                        other
+                            .attributes
+                            .iter()
+                            .find(|attribute| attribute.input.name() == first.input.name())
+                    })
+                    .map(|attribute| attribute.stream)
+                    .filter(|other_stream| {
+                        other.stream_byte_stride(*other_stream) == self.stream_byte_stride(stream)
+                            && self.stream_attributes(stream).all(|attribute| {
+                                other
+                                    .stream_attributes(*other_stream)
+                                    .any(|other_attribute| {
+                                        other_attribute.input.name() == attribute.input.name()
+                                            && other_attribute.format == attribute.format
+                                            && other_attribute.byte_offset == attribute.byte_offset
+                                    })
+                            })
+                    });
+
+                other_stream.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Vertex stream {} has no matching stream in the buffers.",
+                        stream
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32_to_f16_keeps_the_sign_of_zero() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+    }
+
+    #[test]
+    fn f32_to_f16_converts_normal_values() {
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(2.0f32.powi(-14)), 0x0400);
+    }
+
+    #[test]
+    fn f32_to_f16_converts_subnormal_values() {
+        assert_eq!(f32_to_f16(2.0f32.powi(-15)), 0x0200);
+        assert_eq!(f32_to_f16(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(-(2.0f32.powi(-24))), 0x8001);
+
+        // Too small for a subnormal, so it flushes to a signed zero.
+        assert_eq!(f32_to_f16(2.0f32.powi(-26)), 0x0000);
+        assert_eq!(f32_to_f16(-(2.0f32.powi(-26))), 0x8000);
+    }
+
+    #[test]
+    fn f32_to_f16_overflows_to_infinity() {
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert_eq!(f32_to_f16(-1.0e6), 0xfc00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+    }
+
+    #[test]
+    fn f32_to_f16_keeps_nan() {
+        let half = f32_to_f16(f32::NAN);
+        assert_eq!(half & 0x7c00, 0x7c00);
+        assert_ne!(half & 0x03ff, 0);
+    }
+
+    #[test]
+    fn pack_normalized_clamps_unsigned_values() {
+        assert_eq!(pack_normalized(0.0, 10, false), 0);
+        assert_eq!(pack_normalized(1.0, 10, false), 1023);
+        assert_eq!(pack_normalized(2.0, 10, false), 1023);
+        assert_eq!(pack_normalized(-1.0, 10, false), 0);
+    }
+
+    #[test]
+    fn pack_normalized_uses_twos_complement_for_signed_values() {
+        assert_eq!(pack_normalized(0.0, 10, true), 0);
+        assert_eq!(pack_normalized(1.0, 10, true), 511);
+        assert_eq!(pack_normalized(-1.0, 10, true), 513);
+        assert_eq!(pack_normalized(-2.0, 10, true), 513);
+    }
+
+    #[test]
+    fn pack_normalized_handles_two_bit_alpha() {
+        assert_eq!(pack_normalized(0.0, 2, false), 0);
+        assert_eq!(pack_normalized(0.5, 2, false), 2);
+        assert_eq!(pack_normalized(1.0, 2, false), 3);
+
+        assert_eq!(pack_normalized(1.0, 2, true), 1);
+        assert_eq!(pack_normalized(0.0, 2, true), 0);
+        assert_eq!(pack_normalized(-1.0, 2, true), 3);
+    }
+
+    #[test]
+    fn write_clamps_normalized_bytes() {
+        let mut target = [0u8; 4];
+        VertexFormat::UNorm8.write(&[2.0, -1.0, 0.5], &mut target);
+        assert_eq!(target[..3], [255, 0, 128]);
+
+        VertexFormat::SNorm8.write(&[1.0, -1.0, -2.0], &mut target);
+        assert_eq!(target[..3], [127, (-127i8) as u8, (-127i8) as u8]);
+    }
+
+    #[test]
+    fn write_converts_half_floats() {
+        let mut target = [0u8; 4];
+        VertexFormat::F16.write(&[1.0, -0.0], &mut target);
+        assert_eq!(target[..2], 0x3c00u16.to_ne_bytes());
+        assert_eq!(target[2..], 0x8000u16.to_ne_bytes());
+    }
+
+    #[test]
+    fn write_packs_with_a_default_alpha_of_one() {
+        let mut target = [0u8; 4];
+        VertexFormat::UNorm10_10_10_2.write(&[1.0, 0.0, 0.5], &mut target);
+        assert_eq!(u32::from_ne_bytes(target), 1023 | (512 << 20) | (3 << 30));
+
+        VertexFormat::SNorm10_10_10_2.write(&[1.0, -1.0, 0.0], &mut target);
+        assert_eq!(u32::from_ne_bytes(target), 511 | (513 << 10) | (1 << 30));
+
+        VertexFormat::SNorm10_10_10_2.write(&[0.0, 0.0, 0.0, -1.0], &mut target);
+        assert_eq!(u32::from_ne_bytes(target), 3 << 30);
     }
 }
@@ -8,7 +8,7 @@ use crate::geometry::shape::ShapeToTriangles;
 
 use super::{
     gfx_cache::GfxCache,
-    vertex_layout::{VertexComponent, VertexInput, VertexLayout},
+    vertex_layout::{VertexAttribute, VertexComponent, VertexInput, VertexLayout},
 };
 
 /// Represents an input for vertices going into a VertexList.
@@ -18,63 +18,93 @@ pub enum VertexListInput<'a> {
     Normal(&'a [Vector3<VertexComponent>]),
     Color(&'a [Vector4<VertexComponent>]),
     TexCoord(&'a [Vector2<VertexComponent>]),
+    Tangent(&'a [Vector3<VertexComponent>]),
+    Bitangent(&'a [Vector3<VertexComponent>]),
+    TexCoord1(&'a [Vector2<VertexComponent>]),
+    TexCoord2(&'a [Vector2<VertexComponent>]),
+    TexCoord3(&'a [Vector2<VertexComponent>]),
+    /// Joint indices as floats, converted to the attribute's integer format when written.
+    JointIndices(&'a [Vector4<VertexComponent>]),
+    JointWeights(&'a [Vector4<VertexComponent>]),
+    /// Any input, such as a custom input, given as `component_count` components per vertex.
+    Components(VertexInput, &'a [VertexComponent]),
 }
 
 impl<'a> VertexListInput<'a> {
     /// Get the input type.
-    pub const fn input_type(&self) -> VertexInput {
+    pub fn input_type(&self) -> VertexInput {
         match self {
             VertexListInput::Position(_) => VertexInput::Position,
             VertexListInput::Normal(_) => VertexInput::Normal,
             VertexListInput::Color(_) => VertexInput::Color,
             VertexListInput::TexCoord(_) => VertexInput::TexCoord,
+            VertexListInput::Tangent(_) => VertexInput::Tangent,
+            VertexListInput::Bitangent(_) => VertexInput::Bitangent,
+            VertexListInput::TexCoord1(_) => VertexInput::TexCoord1,
+            VertexListInput::TexCoord2(_) => VertexInput::TexCoord2,
+            VertexListInput::TexCoord3(_) => VertexInput::TexCoord3,
+            VertexListInput::JointIndices(_) => VertexInput::JointIndices,
+            VertexListInput::JointWeights(_) => VertexInput::JointWeights,
+            VertexListInput::Components(input, _) => input.clone(),
         }
     }
 
     /// Get the number of inputs.
-    pub const fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         match self {
-            VertexListInput::Position(data) => data.len(),
-            VertexListInput::Normal(data) => data.len(),
-            VertexListInput::Color(data) => data.len(),
-            VertexListInput::TexCoord(data) => data.len(),
+            VertexListInput::Position(data)
+            | VertexListInput::Normal(data)
+            | VertexListInput::Tangent(data)
+            | VertexListInput::Bitangent(data) => data.len(),
+            VertexListInput::Color(data)
+            | VertexListInput::JointIndices(data)
+            | VertexListInput::JointWeights(data) => data.len(),
+            VertexListInput::TexCoord(data)
+            | VertexListInput::TexCoord1(data)
+            | VertexListInput::TexCoord2(data)
+            | VertexListInput::TexCoord3(data) => data.len(),
+            VertexListInput::Components(input, data) => data.len() / input.component_count().max(1),
         }
     }
 
-    /// Copy the input data into the given buffer using the given stride.
-    pub fn copy_to(&self, target: &mut [VertexComponent], component_stride: usize) {
+    /// Convert the input data into the attribute's format and copy it into the given buffer,
+    /// using the given stride in bytes.
+    pub fn copy_to(&self, target: &mut [u8], byte_stride: usize, attribute: &VertexAttribute) {
+        let format = attribute.format();
+        let mut write = |i: usize, components: &[VertexComponent]| {
+            format.write(
+                components,
+                &mut target[i * byte_stride + attribute.byte_offset()..],
+            );
+        };
+
         match self {
-            VertexListInput::Position(data) => {
+            VertexListInput::Position(data)
+            | VertexListInput::Normal(data)
+            | VertexListInput::Tangent(data)
+            | VertexListInput::Bitangent(data) => {
                 for (i, v) in data.iter().enumerate() {
-                    let offset = i * component_stride;
-
-                    target[offset] = v.x();
-                    target[offset + 1] = v.y();
-                    target[offset + 2] = v.z();
+                    write(i, &[v.x(), v.y(), v.z()]);
                 }
             }
-            VertexListInput::Normal(data) => {
+            VertexListInput::Color(data)
+            | VertexListInput::JointIndices(data)
+            | VertexListInput::JointWeights(data) => {
                 for (i, v) in data.iter().enumerate() {
-                    let offset = i * component_stride;
-                    target[offset] = v.x();
-                    target[offset + 1] = v.y();
-                    target[offset + 2] = v.z();
+                    write(i, &[v.x(), v.y(), v.z(), v.w()]);
                 }
             }
-            VertexListInput::Color(data) => {
+            VertexListInput::TexCoord(data)
+            | VertexListInput::TexCoord1(data)
+            | VertexListInput::TexCoord2(data)
+            | VertexListInput::TexCoord3(data) => {
                 for (i, v) in data.iter().enumerate() {
-                    let offset = i * component_stride;
-                    target[offset] = v.x();
-                    target[offset + 1] = v.y();
-                    target[offset + 2] = v.z();
-                    target[offset + 3] = v.w();
+                    write(i, &[v.x(), v.y()]);
                 }
             }
-            VertexListInput::TexCoord(data) => {
-                for (i, v) in data.iter().enumerate() {
-                    let offset = i * component_stride;
-                    target[offset] = v.x();
-                    target[offset + 1] = v.y();
+            VertexListInput::Components(input, data) => {
+                for (i, v) in data.chunks_exact(input.component_count()).enumerate() {
+                    write(i, v);
                 }
             }
         }
@@ -84,47 +114,68 @@ impl<'a> VertexListInput<'a> {
 /// Represents a list of vertices.
 pub struct VertexList {
     layout: Rc<VertexLayout>,
-    data: Vec<VertexComponent>,
+    data: Vec<Vec<u8>>,
     indices: Vec<u32>,
 }
 
 impl VertexList {
     /// Create a new vertex list.
+    /// Returns an error if the inputs are empty or have different lengths.
     pub fn new(
         layout: Rc<VertexLayout>,
         inputs: &[VertexListInput],
         indices: Vec<u32>,
     ) -> Result<Self> {
         // Ensure all inputs have the same length.
-        let len = inputs[0].len();
+        let len = inputs.first().map_or(0, VertexListInput::len);
         for input in inputs.iter().skip(1) {
             if input.len() != len {
                 anyhow::bail!("All inputs must have the same length.");
             }
         }
 
-        // Ensure that the inputs and indices are not empty.
-        if len == 0 || indices.is_empty() {
-            anyhow::bail!("Inputs and indices must not be empty.");
+        // Ensure that the inputs are not empty.
+        // The indices may be empty for non-indexed meshes and instance data.
+        if len == 0 {
+            anyhow::bail!("Inputs must not be empty.");
         }
 
-        // Allocate the data buffer.
-        let mut data = vec![0f32; layout.component_stride() * len];
+        // Allocate a data buffer for each stream.
+        let mut data: Vec<Vec<u8>> = (0..layout.stream_count())
+            .map(|stream| vec![0u8; layout.stream_byte_stride(stream) * len])
+            .collect();
 
-        // Iterate over the layout's expected inputs and copy the data into the buffer.
-        let mut data_offset = 0;
-        for layout_input in layout.inputs() {
+        // Iterate over the layout's expected attributes and convert the data into the buffer.
+        for attribute in layout.attributes() {
             // Find the matching provided input, or error if it wasn't provided.
             let matching_input = inputs
                 .iter()
-                .find(|list_input| list_input.input_type() == *layout_input)
-                .ok_or_else(|| anyhow::anyhow!("Input type {:?} was not provided", layout_input))?;
+                .find(|list_input| list_input.input_type().name() == attribute.input().name())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Input type {:?} was not provided", attribute.input())
+                })?;
 
-            // Copy the input data into the buffer.
-            matching_input.copy_to(&mut data[data_offset..], layout.component_stride());
+            // Ensure that inputs given as components match the layout's component count.
+            if let VertexListInput::Components(input, components) = matching_input {
+                let component_count = attribute.input().component_count();
+                if input.component_count() != component_count
+                    || components.len() != len * component_count
+                {
+                    anyhow::bail!(
+                        "Input {} must have {} components per vertex.",
+                        input.name(),
+                        component_count
+                    );
+                }
+            }
 
-            // Move the data offset.
-            data_offset += layout_input.component_count();
+            // Convert and copy the input data into its stream's buffer.
+            let stream = attribute.stream();
+            matching_input.copy_to(
+                &mut data[stream],
+                layout.stream_byte_stride(stream),
+                attribute,
+            );
         }
 
         Ok(Self {
@@ -143,9 +194,21 @@ impl VertexList {
         shape.to_triangles(cache).into_vertex_list(layout)
     }
 
-    /// Get the vertex data within the vertex list.
-    pub fn vertex_data(&self) -> &[VertexComponent] {
-        &self.data
+    /// Get the vertex data of the first stream, converted to the layout's formats.
+    /// This is all of the vertex data if the layout has a single stream.
+    pub fn vertex_data(&self) -> &[u8] {
+        &self.data[0]
+    }
+
+    /// Get the vertex data of the given stream, converted to the layout's formats.
+    /// Returns `None` if the stream does not exist.
+    pub fn stream_data(&self, stream: usize) -> Option<&[u8]> {
+        self.data.get(stream).map(Vec::as_slice)
+    }
+
+    /// Get the number of vertex streams.
+    pub fn stream_count(&self) -> usize {
+        self.data.len()
     }
 
     /// Get the layout of the vertex list.
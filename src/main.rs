@@ -7,6 +7,7 @@ pub mod geometry;
 pub mod gfx;
 pub mod node_class;
 pub mod node_component;
+pub mod noise;
 pub mod universe_ref;
 pub mod window;
 
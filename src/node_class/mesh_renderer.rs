@@ -1,19 +1,33 @@
+use std::{cell::RefCell, rc::Rc};
+
 use ggmath::prelude::*;
 use multiverse_ecs::prelude::*;
 
 use crate::{
     app::app_prelude::{RenderParameters, TargetBuffer},
-    geometry::orientation::Orientation,
+    engine::FrameInfo,
+    geometry::orientation::{HasOrientation, Orientation},
     gfx::{
+        buffer::{Buffer, BufferUsage, VertexBuffer},
         gfx_cache::{CacheHandle, GfxCache},
+        material::ResolvedMaterial,
+        pipeline_state::PipelineState,
+        program::UniformValue,
         render_camera::RenderCamera,
+        render_queue::SortKey,
+        vertex_layout::{VertexInput, VertexLayout},
+        vertex_list::{VertexList, VertexListInput},
     },
     node_component::render_component::RenderComponent,
 };
 
+use super::RenderInstance;
+
 define_class! {
-    /// Renders a mesh.
+    /// Renders a mesh with a material.
     /// Add nodes with the `RenderInstance` class as children to the renderer.
+    /// If the input layout has an instance layout, the mesh is drawn once per instance in a
+    /// single instanced draw, and nothing is drawn without instances.
     pub class MeshRenderer {
         /// The base orientation.
         orientation: Orientation,
@@ -21,69 +35,302 @@ define_class! {
         mesh: CacheHandle,
         /// The input layout for the mesh.
         input_layout: CacheHandle,
-        /// The program to use for rendering the mesh.
-        program: CacheHandle,
-        /// Parameters passed when rendering the mesh.
+        /// The material to render the mesh with.
+        material: CacheHandle,
+        /// Parameters that override the material's default parameters.
         parameters: RenderParameters,
+        /// The pipeline state that overrides the material's pipeline state.
+        pipeline_state: Option<PipelineState>,
+        /// The buffer the instance inputs are uploaded to every frame.
+        /// Created on the first render with instances, if the input layout has an instance layout.
+        instance_buffer: RefCell<Option<VertexBuffer>>,
         /// The render component that will render the mesh.
         render_component: RenderComponent
     }
 }
 impl MeshRenderer {
     /// Create a new MeshRenderer.
+    /// `parameters` override the material's default parameters for this renderer only.
     pub fn new(
         orientation: Orientation,
         mesh: CacheHandle,
         input_layout: CacheHandle,
-        program: CacheHandle,
+        material: CacheHandle,
         parameters: RenderParameters,
     ) -> Self {
         // Create a render component that will render the mesh.
-        let render_component = RenderComponent::new(Self::__render);
+        let render_component = RenderComponent::new(Self::__render).with_sort_key(Self::__sort_key);
 
         Self {
             orientation,
             mesh,
             input_layout,
-            program,
+            material,
             parameters,
+            pipeline_state: None,
+            instance_buffer: RefCell::new(None),
             render_component,
         }
     }
 
+    /// Override the material's pipeline state for this renderer.
+    pub fn with_pipeline_state(mut self, pipeline_state: PipelineState) -> Self {
+        self.pipeline_state = Some(pipeline_state);
+        self
+    }
+
+    /// Get the pipeline state that overrides the material's pipeline state.
+    /// Returns None if the material's pipeline state is used.
+    pub const fn pipeline_state(&self) -> Option<&PipelineState> {
+        self.pipeline_state.as_ref()
+    }
+
+    /// Set the render layer. Lower layers are drawn first.
+    pub fn with_render_layer(mut self, layer: i32) -> Self {
+        self.render_component.set_layer(layer);
+        self
+    }
+
+    /// Set the sort order within the render layer. Lower orders are drawn first.
+    pub fn with_render_order(mut self, order: i32) -> Self {
+        self.render_component.set_order(order);
+        self
+    }
+
+    /// Get the material the mesh is rendered with.
+    pub const fn material(&self) -> &CacheHandle {
+        &self.material
+    }
+
+    /// Set the material the mesh is rendered with.
+    pub fn set_material(&mut self, material: CacheHandle) {
+        self.material = material;
+    }
+
+    /// Get the parameters that override the material's default parameters.
+    pub const fn parameters(&self) -> &RenderParameters {
+        &self.parameters
+    }
+
+    /// Get the parameters that override the material's default parameters mutably.
+    pub fn parameters_mut(&mut self) -> &mut RenderParameters {
+        &mut self.parameters
+    }
+
+    /// Supplied to the render component to sort the mesh in a render queue.
+    /// The mesh is transparent if its pipeline state blends, and its depth is the depth of
+    /// its origin. The orientation is used as the model matrix without any parent transforms,
+    /// so the depth is only correct for renderers positioned in world space.
+    fn __sort_key(node: &Node, camera: &RenderCamera, cache: &GfxCache) -> SortKey {
+        let mesh_renderer = node
+            .class_as::<MeshRenderer>()
+            .expect("Node is not a MeshRenderer");
+        let material = cache.resolve_material(&mesh_renderer.material).unwrap();
+        let pipeline_state = mesh_renderer
+            .pipeline_state
+            .as_ref()
+            .unwrap_or(material.pipeline_state());
+
+        // Group by the texture the renderer sets, or else the nearest material's texture.
+        let texture = mesh_renderer
+            .parameters
+            .texture_handle()
+            .or_else(|| {
+                material
+                    .parameters()
+                    .iter()
+                    .find_map(|parameters| parameters.texture_handle())
+            })
+            .unwrap_or(0);
+
+        SortKey::new()
+            .with_transparent(pipeline_state.blend.is_some())
+            .with_depth(camera.view_depth(mesh_renderer.orientation.position()))
+            .with_program(material.program().handle())
+            .with_material(&mesh_renderer.material)
+            .with_texture(texture)
+    }
+
     /// Supplied to the render component.
     fn __render(
         node: &Node,
         target_buffer: &TargetBuffer,
         buffer_size: Vector2<u32>,
         camera: &RenderCamera,
+        frame_info: &FrameInfo,
         cache: &mut GfxCache,
+        universe: Option<&Universe>,
     ) {
-        // Render the mesh using the node's orientation and mesh.
-        if let Some(mesh_renderer) = node.class_as::<MeshRenderer>() {
-            // Get mesh, input layout, and program from the cache.
-            let mesh = cache
-                .get_mesh(&mesh_renderer.mesh)
-                .expect("Mesh not found in cache");
-            let input_layout = cache
-                .get_input_layout(&mesh_renderer.input_layout)
-                .expect("Input layout not found in cache");
-            let program = cache
-                .get_program(&mesh_renderer.program)
-                .expect("Program not found in cache");
-
-            // Clone the parameters because we need to modify them.
-            let mut parameters = mesh_renderer.parameters.clone();
-
-            // Set the model matrix and camera matrices in the parameters.
-            parameters.set_model_matrix(mesh_renderer.orientation.get_transform());
-            parameters.set_camera(buffer_size.convert_to().unwrap(), camera);
-
-            target_buffer
-                .render_mesh(program, input_layout, &parameters, mesh)
-                .unwrap();
-        } else {
+        let Some(mesh_renderer) = node.class_as::<MeshRenderer>() else {
             panic!("Node is not a MeshRenderer");
+        };
+
+        // Get mesh, input layout, and material from the cache.
+        let mesh = cache
+            .get_mesh(&mesh_renderer.mesh)
+            .expect("Mesh not found in cache");
+        let input_layout = cache
+            .get_input_layout(&mesh_renderer.input_layout)
+            .expect("Input layout not found in cache");
+        let mut material = cache.resolve_material(&mesh_renderer.material).unwrap();
+        if let Some(pipeline_state) = mesh_renderer.pipeline_state {
+            material = material.with_pipeline_state(pipeline_state);
+        }
+
+        // Set the built-in parameters (model matrix, camera, resolution and timing).
+        let mut builtins = RenderParameters::new();
+        builtins.set_builtins(
+            mesh_renderer.orientation.get_transform(),
+            buffer_size.convert_to().unwrap(),
+            camera,
+            frame_info,
+        );
+
+        // The built-ins and the renderer's parameters override the material's parameters.
+        let parameters = [&builtins, &mesh_renderer.parameters];
+
+        // Draw every instance at once, or the mesh alone without an instance layout.
+        match input_layout.instance_layout() {
+            Some(instance_layout) => {
+                // Upload the instance inputs. Nothing is drawn without instances.
+                let mut instance_buffer = mesh_renderer.instance_buffer.borrow_mut();
+                let Some(instance_buffer) = mesh_renderer.upload_instances(
+                    &mut instance_buffer,
+                    instance_layout,
+                    &material,
+                    node,
+                    universe,
+                ) else {
+                    return;
+                };
+                target_buffer
+                    .render_mesh_instanced_with_material(
+                        &material,
+                        input_layout,
+                        &parameters,
+                        mesh,
+                        instance_buffer,
+                    )
+                    .unwrap();
+            }
+            None => {
+                target_buffer
+                    .render_mesh_with_material(&material, input_layout, &parameters, mesh)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Upload the inputs of the instances among the renderer's children to its instance buffer,
+    /// creating the buffer with the instance layout if it does not exist yet.
+    /// The buffer's storage is orphaned, and only grows when the instances do not fit.
+    /// Returns None if the renderer has no instances.
+    fn upload_instances<'a>(
+        &self,
+        instance_buffer: &'a mut Option<VertexBuffer>,
+        instance_layout: &VertexLayout,
+        material: &ResolvedMaterial,
+        node: &Node,
+        universe: Option<&Universe>,
+    ) -> Option<&'a VertexBuffer> {
+        // Find the instances among the renderer's children.
+        let instances = universe
+            .into_iter()
+            .flat_map(|universe| universe.nodes_with_handles(node.children()).flatten())
+            .filter_map(|child| child.class_as::<RenderInstance>())
+            .collect::<Vec<_>>();
+        if instances.is_empty() {
+            return None;
         }
+
+        // Create an empty instance buffer, which grows to fit the instances when uploading.
+        let instance_buffer = instance_buffer.get_or_insert_with(|| unsafe {
+            Buffer::__from_slice_with_usage(
+                &[],
+                Some(Rc::new(instance_layout.clone())),
+                BufferUsage::Stream,
+            )
+        });
+
+        // Build the instance inputs in the instance buffer's layout and upload them.
+        let instance_list = self.build_instance_list(
+            instance_buffer.vertex_layout().unwrap(),
+            &instances,
+            material,
+        );
+        instance_buffer.set_data(instance_list.vertex_data());
+        Some(&*instance_buffer)
+    }
+
+    /// Build a vertex list with the inputs of each instance in the instance layout.
+    /// `ModelMatrix` comes from the instance's orientation, and other inputs come from the
+    /// instance's render parameters with the same name, then the renderer's, then the
+    /// material's, then zeros.
+    fn build_instance_list(
+        &self,
+        instance_layout: Rc<VertexLayout>,
+        instances: &[&RenderInstance],
+        material: &ResolvedMaterial,
+    ) -> VertexList {
+        // Gather the components of each input for every instance.
+        let components = instance_layout
+            .attributes()
+            .iter()
+            .map(|attribute| {
+                let input = attribute.input();
+                let component_count = input.component_count();
+                let mut data = Vec::with_capacity(instances.len() * component_count);
+                for instance in instances {
+                    let mut values = match input {
+                        VertexInput::ModelMatrix => {
+                            matrix_components(&instance.orientation().get_transform())
+                        }
+                        _ => instance
+                            .render_parameters()
+                            .get(input.name())
+                            .or_else(|| self.parameters.get(input.name()))
+                            .or_else(|| material.get(input.name()))
+                            .and_then(uniform_components)
+                            .unwrap_or_default(),
+                    };
+                    values.resize(component_count, 0.0);
+                    data.extend(values);
+                }
+                (input.clone(), data)
+            })
+            .collect::<Vec<_>>();
+
+        // Convert the components into the instance layout's formats.
+        let inputs = components
+            .iter()
+            .map(|(input, data)| VertexListInput::Components(input.clone(), data))
+            .collect::<Vec<_>>();
+        VertexList::new(instance_layout, &inputs, Vec::new()).unwrap()
+    }
+}
+
+/// Get the components of a matrix in column-major order.
+fn matrix_components(matrix: &Matrix4x4<f32>) -> Vec<f32> {
+    unsafe { std::slice::from_raw_parts(matrix.as_ptr(), 16) }.to_vec()
+}
+
+/// Get the components of a numeric uniform value.
+/// Returns `None` for textures and other non-numeric values.
+fn uniform_components(value: &dyn UniformValue) -> Option<Vec<f32>> {
+    let value = value.as_any();
+    if let Some(value) = value.downcast_ref::<f32>() {
+        Some(vec![*value])
+    } else if let Some(value) = value.downcast_ref::<i32>() {
+        Some(vec![*value as f32])
+    } else if let Some(value) = value.downcast_ref::<Vector2<f32>>() {
+        Some(vec![value.x(), value.y()])
+    } else if let Some(value) = value.downcast_ref::<Vector3<f32>>() {
+        Some(vec![value.x(), value.y(), value.z()])
+    } else if let Some(value) = value.downcast_ref::<Vector4<f32>>() {
+        Some(vec![value.x(), value.y(), value.z(), value.w()])
+    } else {
+        value
+            .downcast_ref::<Matrix4x4<f32>>()
+            .map(matrix_components)
     }
 }
@@ -6,6 +6,10 @@ pub mod render_instance;
 #[allow(unused_imports)]
 pub use render_instance::RenderInstance;
 
+pub mod skybox;
+#[allow(unused_imports)]
+pub use skybox::Skybox;
+
 pub mod viewport;
 #[allow(unused_imports)]
 pub use viewport::Viewport;
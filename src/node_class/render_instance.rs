@@ -1,6 +1,9 @@
 use multiverse_ecs::prelude::*;
 
-use crate::{app::app_prelude::RenderParameters, geometry::orientation::Orientation};
+use crate::{
+    app::app_prelude::RenderParameters,
+    geometry::orientation::{HasOrientation, Orientation},
+};
 
 define_class! {
     /// Represents an instance for rendering.
@@ -12,3 +15,34 @@ define_class! {
         render_parameters: RenderParameters,
     }
 }
+impl RenderInstance {
+    /// Create a new RenderInstance.
+    pub fn new(orientation: Orientation, render_parameters: RenderParameters) -> Self {
+        Self {
+            orientation,
+            render_parameters,
+        }
+    }
+
+    /// Get the instance's render parameters.
+    /// When rendered instanced, these fill the instance inputs with the same names.
+    pub const fn render_parameters(&self) -> &RenderParameters {
+        &self.render_parameters
+    }
+
+    /// Get the instance's render parameters mutably.
+    pub fn render_parameters_mut(&mut self) -> &mut RenderParameters {
+        &mut self.render_parameters
+    }
+}
+
+// The orientation is relative to the instance's renderer.
+impl HasOrientation for RenderInstance {
+    fn orientation(&self) -> &Orientation {
+        &self.orientation
+    }
+
+    fn orientation_mut(&mut self) -> &mut Orientation {
+        &mut self.orientation
+    }
+}
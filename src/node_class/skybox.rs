@@ -0,0 +1,207 @@
+use anyhow::Result;
+use ggmath::prelude::*;
+use multiverse_ecs::prelude::*;
+
+use crate::{
+    app::app_prelude::{RenderParameters, TargetBuffer},
+    engine::FrameInfo,
+    gfx::{
+        cubemap::CubemapView,
+        gfx_cache::{CacheHandle, GfxCache},
+        pipeline_state::{CullMode, DepthState, PipelineState},
+        render_camera::RenderCamera,
+        render_queue::SortKey,
+        shader_gen::prelude::*,
+        vertex_layout::{VertexInput, VertexLayout},
+        vertex_list::{VertexList, VertexListInput},
+    },
+    node_component::render_component::RenderComponent,
+};
+
+define_class! {
+    /// Renders a cube map behind everything else in a `Viewport`.
+    /// Only the camera's rotation is used, so the skybox appears infinitely far away.
+    pub class Skybox {
+        /// The cube map to render.
+        cubemap: CacheHandle,
+        /// The unit cube mesh.
+        mesh: CacheHandle,
+        /// The input layout for the mesh.
+        input_layout: CacheHandle,
+        /// The program to use for rendering the skybox.
+        program: CacheHandle,
+        /// The render component that will render the skybox.
+        render_component: RenderComponent
+    }
+}
+
+impl Skybox {
+    /// Create a new skybox for the given cube map.
+    /// The mesh and program are created in the cache.
+    pub fn new(cache: &mut GfxCache, cubemap: CacheHandle) -> Result<Self> {
+        // Create a position-only vertex layout and input layout.
+        let vertex_layout = cache.create_vertex_layout(None, VertexLayout::with_position);
+        let input_layout = cache.create_input_layout_from_vertex_layout(None, &vertex_layout);
+
+        // Create the unit cube mesh.
+        let layout = cache.get_vertex_layout(&vertex_layout).unwrap().clone();
+        let vertex_list = VertexList::new(
+            layout,
+            &[VertexListInput::Position(&CUBE_POSITIONS)],
+            CUBE_INDICES.to_vec(),
+        )?;
+        let mesh = cache.create_mesh(None, &vertex_layout, vertex_list);
+
+        // Create the program.
+        let program = cache.create_program_vertex_fragment(
+            None,
+            input_layout.clone(),
+            Self::vertex_shader,
+            Self::fragment_shader,
+        )?;
+
+        // Create a render component that will render the skybox.
+        let render_component = RenderComponent::new(Self::__render).with_sort_key(Self::__sort_key);
+
+        Ok(Self {
+            cubemap,
+            mesh,
+            input_layout,
+            program,
+            render_component,
+        })
+    }
+
+    /// Get the cube map being rendered.
+    pub fn cubemap(&self) -> &CacheHandle {
+        &self.cubemap
+    }
+
+    /// Set the cube map to render.
+    pub fn set_cubemap(&mut self, cubemap: CacheHandle) {
+        self.cubemap = cubemap;
+    }
+
+    /// The vertex shader for rendering the skybox.
+    fn vertex_shader(
+        inputs: &ShaderInputs,
+        parameters: &mut ShaderParameters,
+        outputs: &mut ShaderOutputs,
+    ) -> Result<()> {
+        let position = inputs.get(VertexInput::Position)?;
+
+        // Rotate the position by the view matrix, ignoring its translation (w = 0).
+        let view_matrix = parameters.get_view_matrix();
+        let projection_matrix = parameters.get_projection_matrix();
+        let rotated = view_matrix * position.clone().append(0.0) + vector!(0.0, 0.0, 0.0, 1.0);
+        let clip = projection_matrix * rotated;
+
+        // Set z to w so the skybox is always at the far plane.
+        let x = clip.clone().dot(vector!(1.0, 0.0, 0.0, 0.0));
+        let y = clip.clone().dot(vector!(0.0, 1.0, 0.0, 0.0));
+        let w = clip.dot(vector!(0.0, 0.0, 0.0, 1.0));
+        outputs.set_vertex_position(ShaderExpression::vec4(x, y, w.clone(), w));
+
+        // The cube position is the sampling direction.
+        outputs.set("direction", position)?;
+
+        Ok(())
+    }
+
+    /// The fragment shader for rendering the skybox.
+    fn fragment_shader(
+        inputs: &ShaderInputs,
+        parameters: &mut ShaderParameters,
+        outputs: &mut ShaderOutputs,
+    ) -> Result<()> {
+        let direction = inputs.get("direction")?;
+        let cubemap = parameters.get::<CubemapView>("skybox");
+
+        outputs.set_fragment_color(cubemap.sample_cube(direction));
+
+        Ok(())
+    }
+
+    /// Supplied to the render component to sort the skybox in a render queue.
+    /// The skybox is an opaque background, so it is drawn after the other opaque draws in its
+    /// layer and order, where the depth test rejects the parts that are covered.
+    fn __sort_key(node: &Node, _camera: &RenderCamera, cache: &GfxCache) -> SortKey {
+        let skybox = node.class_as::<Skybox>().expect("Node is not a Skybox");
+        let program = cache
+            .get_program(&skybox.program)
+            .expect("Program not found in cache");
+
+        SortKey::new()
+            .with_background(true)
+            .with_program(program.handle())
+    }
+
+    /// Supplied to the render component.
+    fn __render(
+        node: &Node,
+        target_buffer: &TargetBuffer,
+        buffer_size: Vector2<u32>,
+        camera: &RenderCamera,
+        frame_info: &FrameInfo,
+        cache: &mut GfxCache,
+        _universe: Option<&Universe>,
+    ) {
+        if let Some(skybox) = node.class_as::<Skybox>() {
+            // Get the cube map, mesh, input layout, and program from the cache.
+            let cubemap = cache
+                .get_cubemap(&skybox.cubemap)
+                .expect("Cube map not found in cache");
+            let mesh = cache
+                .get_mesh(&skybox.mesh)
+                .expect("Mesh not found in cache");
+            let input_layout = cache
+                .get_input_layout(&skybox.input_layout)
+                .expect("Input layout not found in cache");
+            let program = cache
+                .get_program(&skybox.program)
+                .expect("Program not found in cache");
+
+            // Set the cube map and the built-in parameters.
+            let mut parameters = RenderParameters::new().with("skybox", cubemap.view());
+            parameters.set_builtins(
+                Matrix4x4::identity(),
+                buffer_size.convert_to().unwrap(),
+                camera,
+                frame_info,
+            );
+
+            // Test against the far plane without writing depth, and render the inside faces.
+            let pipeline_state = PipelineState::new()
+                .with_depth(DepthState::READ_ONLY)
+                .with_cull_mode(CullMode::None);
+
+            target_buffer
+                .render_mesh_with_state(program, input_layout, &parameters, mesh, &pipeline_state)
+                .unwrap();
+        } else {
+            panic!("Node is not a Skybox");
+        }
+    }
+}
+
+/// The corners of a unit cube centered at the origin.
+const CUBE_POSITIONS: [Vector3<f32>; 8] = [
+    vector!(-1.0, -1.0, -1.0),
+    vector!(1.0, -1.0, -1.0),
+    vector!(1.0, 1.0, -1.0),
+    vector!(-1.0, 1.0, -1.0),
+    vector!(-1.0, -1.0, 1.0),
+    vector!(1.0, -1.0, 1.0),
+    vector!(1.0, 1.0, 1.0),
+    vector!(-1.0, 1.0, 1.0),
+];
+
+/// The triangles of a unit cube, two per face.
+const CUBE_INDICES: [u32; 36] = [
+    0, 1, 2, 2, 3, 0, // -Z
+    4, 6, 5, 6, 4, 7, // +Z
+    0, 3, 7, 7, 4, 0, // -X
+    1, 5, 6, 6, 2, 1, // +X
+    0, 4, 5, 5, 1, 0, // -Y
+    3, 2, 6, 6, 7, 3, // +Y
+];
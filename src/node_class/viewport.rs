@@ -1,10 +1,18 @@
 use ggmath::prelude::*;
 use multiverse_ecs::prelude::*;
 
-use crate::gfx::render_camera::RenderCamera;
+use crate::{
+    engine::FrameInfo,
+    gfx::{
+        gfx_cache::GfxCache, render_camera::RenderCamera, render_queue::RenderQueue,
+        target_buffer::TargetBuffer,
+    },
+    node_component::render_component::RenderComponent,
+};
 
 define_class! {
     /// A viewport renders its children using the given camera and viewport settings.
+    /// The children are collected into a `RenderQueue` and drawn in sorted order.
     pub class Viewport {
         /// The center position of the viewport.
         center: Vector2<f32>,
@@ -50,6 +58,46 @@ impl Viewport {
         &self.camera
     }
 
+    /// Render the descendants of the viewport's node that have a `RenderComponent`.
+    /// Draws are sorted by layer and order, then opaque draws are grouped by state and drawn
+    /// front-to-back, and transparent draws are drawn back-to-front.
+    pub fn render(
+        &self,
+        node: &Node,
+        target_buffer: &TargetBuffer,
+        buffer_size: Vector2<u32>,
+        frame_info: &FrameInfo,
+        cache: &mut GfxCache,
+        universe: &Universe,
+    ) {
+        // Set the viewport in the state.
+        unsafe {
+            target_buffer.__set_viewport(self.center, self.size, buffer_size);
+        }
+
+        // Collect the draws of all children.
+        let mut queue = RenderQueue::new();
+        for (child, render_component) in universe
+            .nodes_with_handles(node.children())
+            .flatten()
+            .with_component::<RenderComponent>()
+        {
+            render_component.queue(child, &self.camera, cache, universe, &mut queue);
+        }
+
+        // Sort and submit the draws.
+        queue.sort();
+        RenderComponent::submit(
+            &queue,
+            target_buffer,
+            buffer_size,
+            &self.camera,
+            frame_info,
+            cache,
+            universe,
+        );
+    }
+
     /// Get the aspect ratio of the viewport based on the given target buffer size.
     /// This is the width divided by the height.
     pub fn aspect_ratio(&self, target_buffer_size: Vector2<u32>) -> f32 {
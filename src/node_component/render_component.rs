@@ -1,19 +1,90 @@
 use ggmath::prelude::*;
 use multiverse_ecs::prelude::*;
 
-use crate::app::app_prelude::*;
+use crate::{app::app_prelude::*, engine::FrameInfo};
 
 pub struct RenderComponent {
     /// Called with the parent node upon rendering.
-    pub render: fn(&Node, &TargetBuffer, Vector2<u32>, &RenderCamera, &mut GfxCache),
+    /// The universe is provided when available, so renderers can read their children.
+    pub render: fn(
+        &Node,
+        &TargetBuffer,
+        Vector2<u32>,
+        &RenderCamera,
+        &FrameInfo,
+        &mut GfxCache,
+        Option<&Universe>,
+    ),
+    /// Called with the parent node when it is added to a `RenderQueue`.
+    /// Describes how the node's draw is sorted. The layer and order are set by the component.
+    pub sort_key: fn(&Node, &RenderCamera, &GfxCache) -> SortKey,
+    /// The render layer. Lower layers are drawn first.
+    layer: i32,
+    /// The sort order within the layer. Lower orders are drawn first.
+    order: i32,
 }
 
 impl RenderComponent {
     /// Create a new render component with the given render function.
+    /// The node is sorted as an opaque draw in layer 0 with order 0.
     pub fn new(
-        render: fn(&Node, &TargetBuffer, Vector2<u32>, &RenderCamera, &mut GfxCache),
+        render: fn(
+            &Node,
+            &TargetBuffer,
+            Vector2<u32>,
+            &RenderCamera,
+            &FrameInfo,
+            &mut GfxCache,
+            Option<&Universe>,
+        ),
     ) -> Self {
-        Self { render }
+        Self {
+            render,
+            sort_key: |_, _, _| SortKey::new(),
+            layer: 0,
+            order: 0,
+        }
+    }
+
+    /// Set the function that describes how the node's draw is sorted.
+    pub fn with_sort_key(
+        mut self,
+        sort_key: fn(&Node, &RenderCamera, &GfxCache) -> SortKey,
+    ) -> Self {
+        self.sort_key = sort_key;
+        self
+    }
+
+    /// Set the render layer. Lower layers are drawn first.
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Set the sort order within the layer. Lower orders are drawn first.
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Get the render layer.
+    pub const fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Set the render layer.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    /// Get the sort order within the layer.
+    pub const fn order(&self) -> i32 {
+        self.order
+    }
+
+    /// Set the sort order within the layer.
+    pub fn set_order(&mut self, order: i32) {
+        self.order = order;
     }
 
     /// Render the node using the render function.
@@ -25,6 +96,7 @@ impl RenderComponent {
         target_buffer: &TargetBuffer,
         buffer_size: Vector2<u32>,
         camera: &RenderCamera,
+        frame_info: &FrameInfo,
         render_children: bool,
         cache: &mut GfxCache,
         universe: Option<&Universe>,
@@ -43,6 +115,7 @@ impl RenderComponent {
                         target_buffer,
                         buffer_size,
                         camera,
+                        frame_info,
                         true,
                         cache,
                         Some(universe),
@@ -52,6 +125,63 @@ impl RenderComponent {
         }
 
         // Call the render function with the node as the argument.
-        (self.render)(node, target_buffer, buffer_size, camera, cache);
+        (self.render)(
+            node,
+            target_buffer,
+            buffer_size,
+            camera,
+            frame_info,
+            cache,
+            universe,
+        );
+    }
+
+    /// Add the node and its descendants with a render component to the queue.
+    /// The queue is submitted with `RenderComponent::submit` after sorting it.
+    pub fn queue<'a>(
+        &'a self,
+        node: &'a Node,
+        camera: &RenderCamera,
+        cache: &GfxCache,
+        universe: &'a Universe,
+        queue: &mut RenderQueue<(&'a Node, &'a RenderComponent)>,
+    ) {
+        // Queue the children first, so ties are drawn in the same order as `render`.
+        for (child, render_component) in universe
+            .nodes_with_handles(node.children())
+            .flatten()
+            .with_component::<RenderComponent>()
+        {
+            render_component.queue(child, camera, cache, universe, queue);
+        }
+
+        // Describe the node's draw, with the component's layer and order.
+        let key = (self.sort_key)(node, camera, cache)
+            .with_layer(self.layer)
+            .with_order(self.order);
+        queue.push(key, (node, self));
+    }
+
+    /// Render every node in the queue, in the queue's current order.
+    pub fn submit(
+        queue: &RenderQueue<(&Node, &RenderComponent)>,
+        target_buffer: &TargetBuffer,
+        buffer_size: Vector2<u32>,
+        camera: &RenderCamera,
+        frame_info: &FrameInfo,
+        cache: &mut GfxCache,
+        universe: &Universe,
+    ) {
+        for (_, (node, render_component)) in queue.items() {
+            (render_component.render)(
+                node,
+                target_buffer,
+                buffer_size,
+                camera,
+                frame_info,
+                cache,
+                Some(universe),
+            );
+        }
     }
 }
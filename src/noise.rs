@@ -0,0 +1,302 @@
+use ggmath::prelude::*;
+
+/// Represents a type of procedural noise.
+/// The CPU functions in this module produce the same values as the matching shader functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoiseType {
+    /// Interpolated random values on a grid, in the range 0.0 to 1.0.
+    Value,
+    /// Interpolated random gradients on a grid, roughly in the range -1.0 to 1.0.
+    Perlin,
+    /// Random gradients on a simplex grid, roughly in the range -1.0 to 1.0.
+    Simplex,
+    /// Distance to the nearest random feature point (cellular noise), starting at 0.0.
+    Worley,
+}
+
+impl NoiseType {
+    /// Get the name used for this noise type in generated shader functions.
+    pub(crate) const fn glsl_name(self) -> &'static str {
+        match self {
+            NoiseType::Value => "value",
+            NoiseType::Perlin => "perlin",
+            NoiseType::Simplex => "simplex",
+            NoiseType::Worley => "worley",
+        }
+    }
+}
+
+/// Trait for points that noise can be sampled at.
+pub trait NoiseInput: Copy {
+    /// Sample the given type of noise at this point.
+    fn sample_noise(self, noise_type: NoiseType) -> f32;
+
+    /// Get this point multiplied by the given factor.
+    fn scaled(self, factor: f32) -> Self;
+}
+
+impl NoiseInput for Vector2<f32> {
+    fn sample_noise(self, noise_type: NoiseType) -> f32 {
+        sample_array([self.x(), self.y()], noise_type)
+    }
+
+    fn scaled(self, factor: f32) -> Self {
+        self * factor
+    }
+}
+
+impl NoiseInput for Vector3<f32> {
+    fn sample_noise(self, noise_type: NoiseType) -> f32 {
+        sample_array([self.x(), self.y(), self.z()], noise_type)
+    }
+
+    fn scaled(self, factor: f32) -> Self {
+        self * factor
+    }
+}
+
+impl NoiseInput for Vector4<f32> {
+    fn sample_noise(self, noise_type: NoiseType) -> f32 {
+        sample_array([self.x(), self.y(), self.z(), self.w()], noise_type)
+    }
+
+    fn scaled(self, factor: f32) -> Self {
+        self * factor
+    }
+}
+
+/// Sample the given type of noise at a point.
+pub fn sample(noise_type: NoiseType, point: impl NoiseInput) -> f32 {
+    point.sample_noise(noise_type)
+}
+
+/// Sample value noise at a point.
+pub fn value(point: impl NoiseInput) -> f32 {
+    sample(NoiseType::Value, point)
+}
+
+/// Sample Perlin noise at a point.
+pub fn perlin(point: impl NoiseInput) -> f32 {
+    sample(NoiseType::Perlin, point)
+}
+
+/// Sample simplex noise at a point.
+pub fn simplex(point: impl NoiseInput) -> f32 {
+    sample(NoiseType::Simplex, point)
+}
+
+/// Sample Worley (cellular) noise at a point.
+pub fn worley(point: impl NoiseInput) -> f32 {
+    sample(NoiseType::Worley, point)
+}
+
+/// Sample fractal Brownian motion built from the given type of noise.
+/// Each octave multiplies the frequency by `lacunarity` and the amplitude by `gain`.
+/// The result is normalized by the total amplitude, so it has the same range as the base noise.
+pub fn fbm(
+    noise_type: NoiseType,
+    point: impl NoiseInput,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+) -> f32 {
+    let mut point = point;
+    let mut result = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+
+    for _ in 0..octaves {
+        result += amplitude * point.sample_noise(noise_type);
+        total += amplitude;
+        point = point.scaled(lacunarity);
+        amplitude *= gain;
+    }
+
+    result / f32::max(total, 1e-6)
+}
+
+/// Generate an image by calling `f` for every pixel.
+/// `f` receives the pixel center in normalized coordinates (0.0 to 1.0) and returns a color.
+/// The returned color is clamped to the range 0.0 to 1.0.
+pub fn generate_image(
+    size: Vector2<u32>,
+    f: impl Fn(Vector2<f32>) -> Vector4<f32>,
+) -> image::RgbaImage {
+    let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    image::RgbaImage::from_fn(size.x(), size.y(), |x, y| {
+        let point = vector!(
+            (x as f32 + 0.5) / size.x() as f32,
+            (y as f32 + 0.5) / size.y() as f32
+        );
+        let color = f(point);
+        image::Rgba([
+            to_byte(color.x()),
+            to_byte(color.y()),
+            to_byte(color.z()),
+            to_byte(color.w()),
+        ])
+    })
+}
+
+/// Sample the given type of noise at a point with `N` dimensions.
+fn sample_array<const N: usize>(point: [f32; N], noise_type: NoiseType) -> f32 {
+    match noise_type {
+        NoiseType::Value => value_array(point),
+        NoiseType::Perlin => perlin_array(point),
+        NoiseType::Simplex => simplex_array(point),
+        NoiseType::Worley => worley_array(point),
+    }
+}
+
+/// Hash a 32-bit integer (PCG hash).
+/// Matches `_noise_hash` in generated shaders.
+fn hash(x: u32) -> u32 {
+    let state = x.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Get a random value in the range 0.0 to 1.0 for a grid cell.
+/// Matches `_noise_random` in generated shaders.
+fn random<const N: usize>(cell: [i32; N], salt: u32) -> f32 {
+    let mut h = hash(salt);
+    for component in cell {
+        h = hash(h ^ component as u32);
+    }
+    h as f32 / 4294967295.0
+}
+
+/// Get a random vector with components in the range 0.0 to 1.0 for a grid cell.
+fn random_vector<const N: usize>(cell: [i32; N]) -> [f32; N] {
+    std::array::from_fn(|k| random(cell, k as u32))
+}
+
+/// Get a random unit-length gradient for a grid cell.
+fn gradient<const N: usize>(cell: [i32; N]) -> [f32; N] {
+    let gradient = random_vector(cell).map(|v| v * 2.0 - 1.0);
+    let length = f32::max(dot(gradient, gradient).sqrt(), 1e-6);
+    gradient.map(|v| v / length)
+}
+
+/// Quintic fade curve used to interpolate between grid cells.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Dot product of two arrays.
+fn dot<const N: usize>(a: [f32; N], b: [f32; N]) -> f32 {
+    (0..N).map(|k| a[k] * b[k]).sum()
+}
+
+/// Iterate over the corners of a grid cell, calling `f` with the corner offset and
+/// its interpolation weight.
+fn for_each_corner<const N: usize>(u: [f32; N], mut f: impl FnMut([i32; N], f32)) {
+    for corner in 0..(1 << N) {
+        let offset = std::array::from_fn(|k| (corner >> k) & 1);
+        let weight = (0..N)
+            .map(|k| if offset[k] == 1 { u[k] } else { 1.0 - u[k] })
+            .product();
+        f(offset, weight);
+    }
+}
+
+/// Value noise with `N` dimensions.
+fn value_array<const N: usize>(point: [f32; N]) -> f32 {
+    let cell = point.map(f32::floor);
+    let u = std::array::from_fn(|k| fade(point[k] - cell[k]));
+
+    let mut result = 0.0;
+    for_each_corner(u, |offset, weight| {
+        let corner = std::array::from_fn(|k| cell[k] as i32 + offset[k]);
+        result += weight * random(corner, 0);
+    });
+    result
+}
+
+/// Perlin noise with `N` dimensions.
+fn perlin_array<const N: usize>(point: [f32; N]) -> f32 {
+    let cell = point.map(f32::floor);
+    let f: [f32; N] = std::array::from_fn(|k| point[k] - cell[k]);
+    let u = f.map(fade);
+
+    let mut result = 0.0;
+    for_each_corner(u, |offset, weight| {
+        let corner = std::array::from_fn(|k| cell[k] as i32 + offset[k]);
+        let delta = std::array::from_fn(|k| f[k] - offset[k] as f32);
+        result += weight * dot(gradient(corner), delta);
+    });
+    result * (2.0 / (N as f32).sqrt())
+}
+
+/// Get the skew factors, kernel radius and output scale for simplex noise with `N` dimensions.
+/// Matches the constants in generated shaders.
+pub(crate) fn simplex_constants(dimensions: usize) -> (f32, f32, f32, f32) {
+    let n = dimensions as f32;
+    let skew = ((n + 1.0).sqrt() - 1.0) / n;
+    let unskew = (1.0 - 1.0 / (n + 1.0).sqrt()) / n;
+    let (radius_squared, scale) = match dimensions {
+        2 => (0.5, 70.0),
+        3 => (0.6, 32.0),
+        _ => (0.6, 27.0),
+    };
+    (skew, unskew, radius_squared, scale)
+}
+
+/// Simplex noise with `N` dimensions.
+fn simplex_array<const N: usize>(point: [f32; N]) -> f32 {
+    let (skew, unskew, radius_squared, scale) = simplex_constants(N);
+
+    // Skew the input space to find the simplex cell.
+    let skew_offset = point.iter().sum::<f32>() * skew;
+    let cell = point.map(|v| (v + skew_offset).floor());
+    let unskew_offset = cell.iter().sum::<f32>() * unskew;
+    let x0: [f32; N] = std::array::from_fn(|k| point[k] - (cell[k] - unskew_offset));
+
+    // Rank the components to find which simplex the point is in.
+    let mut rank = [0; N];
+    for a in 0..N {
+        for b in (a + 1)..N {
+            if x0[a] >= x0[b] {
+                rank[b] += 1;
+            } else {
+                rank[a] += 1;
+            }
+        }
+    }
+
+    // Sum the contributions of each simplex corner.
+    let mut result = 0.0;
+    for vertex in 0..=N {
+        let offset: [f32; N] = rank.map(|r| if r < vertex { 1.0 } else { 0.0 });
+        let x = std::array::from_fn(|k| x0[k] - offset[k] + vertex as f32 * unskew);
+        let t = radius_squared - dot(x, x);
+        if t > 0.0 {
+            let corner = std::array::from_fn(|k| (cell[k] + offset[k]) as i32);
+            let t = t * t;
+            result += t * t * dot(gradient(corner), x);
+        }
+    }
+    result * scale
+}
+
+/// Worley (cellular) noise with `N` dimensions.
+fn worley_array<const N: usize>(point: [f32; N]) -> f32 {
+    let cell = point.map(f32::floor);
+    let f: [f32; N] = std::array::from_fn(|k| point[k] - cell[k]);
+
+    let mut result = f32::MAX;
+    for neighbor in 0..3usize.pow(N as u32) {
+        let mut rest = neighbor as i32;
+        let offset: [i32; N] = std::array::from_fn(|_| {
+            let component = rest % 3 - 1;
+            rest /= 3;
+            component
+        });
+        let corner = std::array::from_fn(|k| cell[k] as i32 + offset[k]);
+        let feature = random_vector(corner);
+        let delta = std::array::from_fn(|k| offset[k] as f32 + feature[k] - f[k]);
+        result = f32::min(result, dot(delta, delta).sqrt());
+    }
+    result
+}
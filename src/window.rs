@@ -1,19 +1,31 @@
+use anyhow::Result;
 use ggmath::prelude::*;
 use glfw::{Action, Context, Glfw, GlfwReceiver, Key, PWindow, WindowEvent};
 
-use crate::gfx::Gfx;
-
-/// Create the window.
-pub(crate) fn create_window() -> (Glfw, PWindow, GlfwReceiver<(f64, WindowEvent)>) {
-    // Initialize GLFW.
-    let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
+use crate::gfx::{gl_state::GlState, Gfx};
 
+/// Set the window hints for an OpenGL 4.5 core profile context.
+fn set_context_hints(glfw: &mut Glfw) {
     // Use an OpenGL 4.5 core profile.
     glfw.window_hint(glfw::WindowHint::ContextVersion(4, 5));
     glfw.window_hint(glfw::WindowHint::OpenGlProfile(
         glfw::OpenGlProfileHint::Core,
     ));
     glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+}
+
+/// Log a GLFW error instead of panicking, so failures are returned as errors.
+fn log_glfw_error(error: glfw::Error, description: String) {
+    eprintln!("GLFW error {:?}: {}", error, description);
+}
+
+/// Create the window.
+pub(crate) fn create_window() -> (Glfw, PWindow, GlfwReceiver<(f64, WindowEvent)>) {
+    // Initialize GLFW.
+    let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
+
+    // Use an OpenGL 4.5 core profile.
+    set_context_hints(&mut glfw);
 
     // Create the window.
     let (mut window, events) = glfw
@@ -35,6 +47,101 @@ pub(crate) fn create_window() -> (Glfw, PWindow, GlfwReceiver<(f64, WindowEvent)
     (glfw, window, events)
 }
 
+/// Represents the API used to create a headless OpenGL context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextBackend {
+    /// The platform's native context API (GLX, WGL or NSGL).
+    /// This still requires a display server, although the window is never shown.
+    Native,
+    /// EGL on GLFW's null platform, which works on machines without a display.
+    Egl,
+    /// OSMesa, Mesa's off-screen software rasterizer, on GLFW's null platform.
+    /// This requires a GLFW build with OSMesa support.
+    OsMesa,
+}
+
+impl ContextBackend {
+    /// Check if the backend needs a display server.
+    const fn needs_display(self) -> bool {
+        matches!(self, ContextBackend::Native)
+    }
+
+    /// Get the GLFW context creation API.
+    const fn to_glfw(self) -> glfw::ContextCreationApi {
+        match self {
+            ContextBackend::Native => glfw::ContextCreationApi::Native,
+            ContextBackend::Egl => glfw::ContextCreationApi::Egl,
+            ContextBackend::OsMesa => glfw::ContextCreationApi::OsMesa,
+        }
+    }
+}
+
+/// An OpenGL context without a visible window, for offscreen rendering.
+/// The graphics controller is initialized on creation, so `GfxCache`, programs, meshes and
+/// framebuffers can be used as usual. Render into a `Framebuffer` rather than the default
+/// framebuffer, as the contents of a hidden window's framebuffer are undefined.
+/// The context must be kept alive for as long as graphics are used.
+pub struct HeadlessContext {
+    glfw: Glfw,
+    window: PWindow,
+}
+
+impl !Send for HeadlessContext {}
+impl !Sync for HeadlessContext {}
+
+impl HeadlessContext {
+    /// Create a headless context using the given backend and initialize the graphics controller.
+    /// `size` is the size of the hidden window's default framebuffer.
+    /// This should only be called once in the main thread.
+    pub fn new(backend: ContextBackend, size: Vector2<u32>) -> Result<Self> {
+        // Use the null platform when no display is needed, so GLFW does not connect to one.
+        if !backend.needs_display() {
+            glfw::init_hint(glfw::InitHint::Platform(glfw::Platform::Null));
+        }
+
+        // Initialize GLFW, logging errors so a failure to create the context is returned.
+        let mut glfw = glfw::init(log_glfw_error)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize GLFW: {:?}", e))?;
+
+        // Use an OpenGL 4.5 core profile on a hidden window with the given backend.
+        set_context_hints(&mut glfw);
+        glfw.window_hint(glfw::WindowHint::Visible(false));
+        glfw.window_hint(glfw::WindowHint::ContextCreationApi(backend.to_glfw()));
+
+        // Create the hidden window.
+        let (mut window, _) = glfw
+            .create_window(size.x(), size.y(), "Headless", glfw::WindowMode::Windowed)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Failed to create headless context with {:?}", backend)
+            })?;
+
+        // Make the window the current OpenGL context.
+        window.make_current();
+
+        // Init graphics controller.
+        Gfx::init(&mut window);
+
+        Ok(Self { glfw, window })
+    }
+
+    /// Get the GLFW context.
+    pub fn glfw(&mut self) -> &mut Glfw {
+        &mut self.glfw
+    }
+
+    /// Get the hidden window that owns the context.
+    pub fn window(&mut self) -> &mut PWindow {
+        &mut self.window
+    }
+
+    /// Wait for all submitted graphics commands to complete.
+    pub fn finish(&self) {
+        unsafe {
+            gl::Finish();
+        }
+    }
+}
+
 /// Retrieve window events that occurred.
 pub(crate) fn get_window_events(
     glfw: &mut Glfw,
@@ -87,7 +194,7 @@ pub(crate) fn swap_window_buffers(window: &mut PWindow) {
     // Clear the window frame buffer.
     unsafe {
         // Bind the default framebuffer.
-        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        GlState::__bind_draw_framebuffer(0);
         // Set the clear color to cornflower blue.
         gl::ClearColor(0.392, 0.584, 0.929, 1.0);
         // Clear the color buffer.